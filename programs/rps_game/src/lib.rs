@@ -1,17 +1,326 @@
 // Import dependencies
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::{program::invoke, program::invoke_signed, system_instruction};
-use sha2::{Digest, Sha256};
+use anchor_lang::solana_program::{
+    ed25519_program,
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    program::invoke,
+    program::invoke_signed,
+    program::set_return_data,
+    system_instruction, sysvar,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{
+    self, CloseAccount, FreezeAccount, Mint, MintTo, SyncNative, ThawAccount, Token, TokenAccount,
+    Transfer,
+};
+use mpl_bubblegum::instructions::{MintV1Cpi, MintV1CpiAccounts, MintV1InstructionArgs};
+use mpl_bubblegum::programs::{SPL_ACCOUNT_COMPRESSION_ID, SPL_NOOP_ID};
+use mpl_bubblegum::types::{MetadataArgs, TokenProgramVersion, TokenStandard};
+use std::cmp::Ordering;
 
 // ------------------------------------
 // Declare the program ID
 // ------------------------------------
 declare_id!("28AfQg9jGzkW9tJw9zQ857ncvuUnnNHE4vGb4pLpPLRM");
 
+// Emits a lifecycle event to the program logs and again as a self-CPI through
+// the event authority, which indexers can read even when logs are truncated.
+// The accounts struct behind `ctx` must be #[event_cpi]
+macro_rules! emit_lifecycle_event {
+    ($ctx:expr, $event:expr) => {{
+        let ctx = &$ctx;
+        let event = $event;
+        emit!(event);
+        emit_cpi!(event);
+    }};
+}
+
+// Verbose logging for local debugging. Formatting costs compute units, so it
+// is compiled out unless the `debug-logs` feature is enabled
+macro_rules! debug_msg {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "debug-logs")]
+        msg!($($arg)*);
+    };
+}
+
 // ------------------------------------
 // Constants
 // ------------------------------------
 const GAME_SEED: &[u8] = b"game";
+const CONFIG_SEED: &[u8] = b"config";
+const PLAYER_STATS_SEED: &[u8] = b"player_stats";
+const PLAYER_HISTORY_SEED: &[u8] = b"player_history";
+const BADGE_AUTHORITY_SEED: &[u8] = b"badge_authority";
+
+const ACHIEVEMENT_VAULT_SEED: &[u8] = b"achievement_vault";
+const JACKPOT_VAULT_SEED: &[u8] = b"jackpot_vault";
+const LOBBY_SEED: &[u8] = b"lobby";
+const QUEUE_SEED: &[u8] = b"queue";
+const QUEUE_GAME_SEED: &[u8] = b"queue_game";
+const MULTI_GAME_SEED: &[u8] = b"multi_game";
+const LEAGUE_SEED: &[u8] = b"league";
+const NFT_ESCROW_SEED: &[u8] = b"nft_escrow";
+const NFT_AUTHORITY_SEED: &[u8] = b"nft_authority";
+const POT_ESCROW_SEED: &[u8] = b"pot_escrow";
+const BANKROLL_SEED: &[u8] = b"bankroll";
+const LP_POSITION_SEED: &[u8] = b"lp_position";
+const HOUSE_GAME_SEED: &[u8] = b"house_game";
+const JOIN_REQUEST_SEED: &[u8] = b"join_request";
+const ALLOWLIST_SEED: &[u8] = b"allowlist";
+const HIDDEN_GAME_SEED: &[u8] = b"hidden_game";
+const TREASURY_SEED: &[u8] = b"treasury";
+const POINTS_MINT_SEED: &[u8] = b"points_mint";
+const PARLAY_SEED: &[u8] = b"parlay";
+const PRESET_SEED: &[u8] = b"preset";
+const PROFILE_SEED: &[u8] = b"profile";
+const USERNAME_SEED: &[u8] = b"username";
+const LEADERBOARD_SEED: &[u8] = b"leaderboard";
+const SNAPSHOT_SEED: &[u8] = b"snapshot";
+const CALLBACK_SEED: &[u8] = b"callback";
+const INSURANCE_VAULT_SEED: &[u8] = b"insurance_vault";
+const RULESET_SEED: &[u8] = b"ruleset";
+
+// Metaplex Token Metadata program; avatars must have a metadata account
+const TOKEN_METADATA_PROGRAM_ID: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+// Key byte of a Metadata account, followed by its update authority and mint
+const METADATA_V1_KEY: u8 = 4;
+
+// ORAO VRF program, which breaks ties at random. A request for a seed
+// creates the program's Randomness account at a PDA of that seed, whose
+// randomness its oracles fill in
+const ORAO_VRF_PROGRAM_ID: Pubkey = pubkey!("VRFzZoJdhFWL8rkvu87LpKM3RbcVezpMEc6X5GVDr7y");
+const ORAO_NETWORK_STATE_SEED: &[u8] = b"orao-vrf-network-configuration";
+const ORAO_RANDOMNESS_SEED: &[u8] = b"orao-vrf-randomness-request";
+
+// Slots of history kept by the SlotHashes sysvar; house games must settle
+// before their deciding slot hash ages out
+const SLOT_HASHES_MAX_ENTRIES: u64 = 512;
+
+// Placeholder for an unrevealed move in zero-copy accounts
+const NO_MOVE: u8 = u8::MAX;
+
+// Bits of GameState::flags
+#[constant]
+pub const GAME_FLAG_CREATOR_READY: u16 = 1 << 0;
+#[constant]
+pub const GAME_FLAG_JOINER_READY: u16 = 1 << 1;
+#[constant]
+pub const GAME_FLAG_FLAGGED: u16 = 1 << 2; // Marked for emergency refund
+#[constant]
+pub const GAME_FLAG_INVITE_ONLY: u16 = 1 << 3;
+#[constant]
+pub const GAME_FLAG_REPLAY_TIES: u16 = 1 << 4; // TiePolicy::Replay
+#[constant]
+pub const GAME_FLAG_REMATCH_OFFERED: u16 = 1 << 5; // By the settled loser
+#[constant]
+pub const GAME_FLAG_APPROVAL_REQUIRED: u16 = 1 << 6; // Joiners go through join requests
+#[constant]
+pub const GAME_FLAG_ALLOWLIST: u16 = 1 << 7; // Only players on the game's allowlist may join
+#[constant]
+pub const GAME_FLAG_RANDOM_TIES: u16 = 1 << 8; // TiePolicy::Random
+#[constant]
+pub const GAME_FLAG_HUMANS_ONLY: u16 = 1 << 9; // Registered bots may not join
+#[constant]
+pub const GAME_FLAG_HANDICAP: u16 = 1 << 10; // The lower-rated player wins ties
+#[constant]
+pub const GAME_FLAG_ROLLOVER: u16 = 1 << 11; // The winner's payout stays escrowed for a rematch
+#[constant]
+pub const GAME_FLAG_CALLBACK: u16 = 1 << 12; // Settlements may notify a registered program
+
+// Players a challenge can be offered to
+#[constant]
+pub const MAX_ALLOWLIST_LEN: usize = 8;
+
+// Accounts a settlement callback can ask for, after the game account
+#[constant]
+pub const MAX_CALLBACK_ACCOUNTS: usize = 8;
+
+// Moves a game is played with: classic Rock/Paper/Scissors, or a larger odd
+// cycle such as RPS-7 or RPS-9
+#[constant]
+pub const CLASSIC_MOVE_COUNT: u8 = 3;
+#[constant]
+pub const MAX_MOVE_COUNT: u8 = 9;
+
+// SPL token assets a pot can hold next to SOL
+#[constant]
+pub const MAX_POT_ASSETS: usize = 3;
+
+// Username length bounds, in bytes. Names are lowercase ASCII letters,
+// digits and underscores, and must fit in a PDA seed
+#[constant]
+pub const MIN_USERNAME_LEN: usize = 3;
+#[constant]
+pub const MAX_USERNAME_LEN: usize = 32;
+
+// Longest in-game message, in bytes of UTF-8
+#[constant]
+pub const MAX_MESSAGE_LEN: usize = 64;
+
+// Longest game label, in bytes of UTF-8
+#[constant]
+pub const MAX_LABEL_LEN: usize = 32;
+
+// Games a single settle_many call may crank
+#[constant]
+pub const MAX_BATCH_SETTLEMENTS: usize = 8;
+
+// Paid out of an expired game's rent to whoever cranks gc_game. Above the
+// rent-exempt minimum of an empty account, so an unfunded wallet can take it
+#[constant]
+pub const GC_BOUNTY_LAMPORTS: u64 = 1_000_000;
+
+// Games a parlay can combine, and what each winning pick multiplies the
+// stake by, in basis points
+#[constant]
+pub const MIN_PARLAY_LEGS: usize = 2;
+#[constant]
+pub const MAX_PARLAY_LEGS: usize = 5;
+#[constant]
+pub const PARLAY_LEG_ODDS_BPS: u64 = 25_000;
+
+// Compute units a settling reveal_move should stay under, checked by the
+// integration tests
+#[constant]
+pub const REVEAL_MOVE_CU_BUDGET: u32 = 60_000;
+
+// Recent results kept in a player's history ring buffer
+#[constant]
+pub const PLAYER_HISTORY_LEN: usize = 16;
+
+// Open games are listed in one of several lobby buckets, picked by the first
+// byte of the game account key, to spread write contention
+#[constant]
+pub const LOBBY_BUCKETS: u8 = 4;
+const LOBBY_BUCKET_CAPACITY: usize = 32;
+
+// Leaderboard standings are split into rating-band shards so no single
+// account has to hold every player
+#[constant]
+pub const MAX_LEADERBOARD_SHARDS: u8 = 16;
+const LEADERBOARD_SHARD_CAPACITY: usize = 32;
+
+// Weekly competitions: weeks are counted from the Unix epoch and the top
+// places of the leaderboard split the week's prize pool
+const WEEK_SECONDS: i64 = 7 * 24 * 60 * 60;
+const WEEKLY_PRIZE_SPLIT_BPS: [u64; 3] = [5_000, 3_000, 2_000];
+
+// Self-imposed daily loss caps reset at each UTC day boundary
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+// Players waiting in a single matchmaking queue
+const QUEUE_CAPACITY: usize = 16;
+
+// Seats in an elimination game
+#[constant]
+pub const MIN_MULTI_PLAYERS: u8 = 3;
+#[constant]
+pub const MAX_MULTI_PLAYERS: u8 = 8;
+
+// League rosters, points per result and the prize split for the top three
+#[constant]
+pub const MAX_LEAGUE_PLAYERS: usize = 8;
+const LEAGUE_WIN_POINTS: u16 = 3;
+const LEAGUE_TIE_POINTS: u16 = 1;
+const LEAGUE_PRIZE_SPLIT_BPS: [u64; 3] = [6_000, 3_000, 1_000];
+
+// Fees and fee shares are expressed in basis points
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// Accounts the house fee can be split across
+#[constant]
+pub const MAX_FEE_RECIPIENTS: usize = 4;
+
+// Wager brackets the config's fee schedule can hold
+#[constant]
+pub const MAX_FEE_TIERS: usize = 4;
+
+// Players the config's ban list can hold
+#[constant]
+pub const MAX_BANNED_PLAYERS: usize = 32;
+
+// Longest URI prefix the config can hold for badge metadata
+const MAX_BADGE_URI_PREFIX_LEN: usize = 160;
+
+// Number of achievement slots (bits in PlayerStats::achievements)
+const ACHIEVEMENT_SLOTS: usize = 16;
+
+// ELO parameters for player ratings
+#[constant]
+pub const INITIAL_RATING: u32 = 1200;
+#[constant]
+pub const ELO_K_FACTOR: u32 = 32;
+
+// Reputation parameters: every completed game earns a point, a reveal
+// timeout costs the player who timed out
+#[constant]
+pub const INITIAL_REPUTATION: u32 = 100;
+#[constant]
+pub const REPUTATION_GAIN: u32 = 1;
+#[constant]
+pub const REPUTATION_TIMEOUT_PENALTY: u32 = 10;
+
+// Hash function used for move commitments, exposed in the IDL so clients
+// can produce matching hashes. The committed preimage is
+// game_account key || nonce (u64 LE) || move (u8) || salt bytes
+#[constant]
+pub const COMMITMENT_HASH_SCHEME: &str = "sha256";
+
+// Time both players have to reveal once they are ready, in seconds
+#[constant]
+pub const REVEAL_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+// Shorter reveal window for the sudden-death rounds that follow a replayed
+// tie, in seconds
+#[constant]
+pub const SUDDEN_DEATH_REVEAL_WINDOW_SECONDS: i64 = 60 * 60;
+
+// Time the VRF network has to fulfil a tie-break request before the tie is
+// split instead, in seconds
+#[constant]
+pub const TIE_BREAK_WINDOW_SECONDS: i64 = 60 * 60;
+
+// Time an open game waits for a joiner, in seconds
+#[constant]
+pub const JOIN_WINDOW_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+// Session keys act for a player within a scope (bit flags) until their
+// expiry slot, which may be at most MAX_SESSION_SLOTS ahead (~1 day)
+#[constant]
+pub const SESSION_SCOPE_REVEAL: u8 = 1 << 0;
+#[constant]
+pub const MAX_SESSION_SLOTS: u64 = 216_000;
+
+// Layout versions, stored right after the discriminator so migrate_* can
+// read them from any layout
+#[constant]
+pub const GAME_STATE_VERSION: u8 = 1;
+#[constant]
+pub const CONFIG_VERSION: u8 = 1;
+#[constant]
+pub const PLAYER_STATS_VERSION: u8 = 1;
+
+// Byte offsets of the GameState fields indexers filter on, discriminator
+// included. They lead the layout so no variable-size field comes before them.
+// The opponent is an Option: a tag byte, 1 when set, then the key
+#[constant]
+pub const GAME_STATUS_OFFSET: usize = 9;
+#[constant]
+pub const GAME_CREATOR_OFFSET: usize = 10;
+#[constant]
+pub const GAME_WAGER_OFFSET: usize = 42;
+#[constant]
+pub const GAME_OPPONENT_OFFSET: usize = 50;
+
+// Version of the GameView returned by get_game. Fields are only ever
+// appended, and the version bumps whenever they are
+#[constant]
+pub const GAME_VIEW_VERSION: u8 = 2;
 
 // ------------------------------------
 // The Program Module
@@ -21,271 +330,10640 @@ pub mod rps_game {
     use super::*;
 
     // ------------------------------------
-    // Instruction: Create a new game
+    // Instruction: Initialize the program config
     // ------------------------------------
-    pub fn create_game(
-        ctx: Context<CreateGame>,
-        wager: u64,                    // Wager amount (in lamports)
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        min_wager: u64, // Smallest wager allowed (in lamports)
+        max_wager: u64, // Largest wager allowed (in lamports)
     ) -> Result<()> {
-        let game_account = &mut ctx.accounts.game_account;
+        require!(min_wager <= max_wager, ErrorCode::InvalidWagerLimits);
 
-        // Initialize game account fields
-        game_account.creator = *ctx.accounts.creator.key;
-        game_account.opponent = None;
-        game_account.creator_move_hashed = [0u8; 32];
-        game_account.joiner_move_hashed = [0u8; 32];
-        game_account.creator_ready = false;
-        game_account.joiner_ready = false;
-        game_account.wager = wager;
-        game_account.status = GameStatus::Open;
-        game_account.bump = ctx.bumps.game_account;
+        let config = &mut ctx.accounts.config;
 
-        // Transfer wager lamports from creator to game_account
-        if wager > 0 {
-            let ix = system_instruction::transfer(
-                &ctx.accounts.creator.key(),
-                &ctx.accounts.game_account.key(),
-                wager,
-            );
-            invoke(
-                &ix,
-                &[
-                    ctx.accounts.creator.to_account_info(),
-                    ctx.accounts.game_account.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
+        config.version = CONFIG_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.paused = false;
+        config.min_wager = min_wager;
+        config.max_wager = max_wager;
+        config.badge_tree = Pubkey::default();
+        config.badge_uri_prefix = String::new();
+        config.achievement_rewards = [0; ACHIEVEMENT_SLOTS];
+        config.season_id = 1;
+        config.house = *ctx.accounts.admin.key;
+        config.house_fee_bps = 0;
+        config.jackpot_share_bps = 0;
+        config.jackpot_winning_move = 0; // Rock
+        config.jackpot_losing_move = 2; // Scissors
+        config.jackpot_hash_byte = 0x00;
+        config.nft_fee = 0;
+        config.fee_recipients = Vec::new();
+        config.bump = ctx.bumps.config;
+        config.banned = Vec::new();
+        config.fee_holiday_start = 0;
+        config.fee_holiday_end = 0;
+        config.fee_holiday_bps = 0;
+        config.fee_tiers = Vec::new();
+        config.rakeback_bps = 0;
+        config.rakeback_threshold = 0;
+        config.points_per_sol = 0;
+        config.points_tradeable = false;
+        config.tie_break_vrf = false;
+        config.surrender_refund_bps = 0;
+        config.seed_version = 0;
+        config.previous_seed_version = 0;
+        config.seed_migration_ends = 0;
+        config.rating_decay_window = 0;
+        config.rating_decay_bps = 0;
+        config.weekly_prize_pool = 0;
+        config.daily_wager_cap = 0;
+        config.creation_cooldown = 0;
+        config.creation_stake_mint = Pubkey::default();
+        config.creation_stake_min = 0;
+        config.creation_fee = 0;
+        config.waive_draw_fees = false;
+        config.insurance_premium_bps = 0;
+        config.insurance_refund_bps = 0;
+        config.max_open_games = 0;
+        config.game_count = 0;
+        config.latest_game = None;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Pause or unpause new games (admin only)
+    // ------------------------------------
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        ctx.accounts.config.paused = paused;
+        msg!("Paused: {}", paused);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Ban or unban a player (admin only)
+    // ------------------------------------
+    // Banned players can't create or join games; games they are already in
+    // play out normally
+    pub fn set_banned(ctx: Context<UpdateConfig>, player: Pubkey, banned: bool) -> Result<()> {
+        let list = &mut ctx.accounts.config.banned;
+        let position = list.iter().position(|key| *key == player);
+        match (banned, position) {
+            (true, None) => {
+                require!(list.len() < MAX_BANNED_PLAYERS, ErrorCode::BanListFull);
+                list.push(player);
+            }
+            (false, Some(index)) => {
+                list.swap_remove(index);
+            }
+            _ => {}
         }
+        msg!("Banned {}: {}", player, banned);
 
         Ok(())
     }
 
     // ------------------------------------
-    // Instruction: Join an existing game
+    // Instruction: Hand the admin authority to another key (admin only)
     // ------------------------------------
-    pub fn join_game(
-        ctx: Context<JoinGame>,
+    // The new admin may be a PDA such as a multisig vault: admin instructions
+    // only need its signature, never its lamports, so it can sign through a
+    // CPI while a separate payer covers fees and rent
+    pub fn set_admin(ctx: Context<UpdateConfig>, admin: Pubkey) -> Result<()> {
+        ctx.accounts.config.admin = admin;
+        msg!("Admin: {}", admin);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Apply several config changes at once (admin only)
+    // ------------------------------------
+    // A single instruction a DAO proposal can carry: with the admin set to an
+    // SPL Governance PDA, the executed proposal signs for it. Unset fields
+    // are left as they are
+    pub fn update_config(ctx: Context<UpdateConfig>, update: ConfigUpdate) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        if let Some(paused) = update.paused {
+            config.paused = paused;
+        }
+        if let Some(min_wager) = update.min_wager {
+            config.min_wager = min_wager;
+        }
+        if let Some(max_wager) = update.max_wager {
+            config.max_wager = max_wager;
+        }
+        if let Some(house) = update.house {
+            config.house = house;
+        }
+        if let Some(house_fee_bps) = update.house_fee_bps {
+            config.house_fee_bps = house_fee_bps;
+        }
+        if let Some(jackpot_share_bps) = update.jackpot_share_bps {
+            config.jackpot_share_bps = jackpot_share_bps;
+        }
+        require!(
+            config.min_wager <= config.max_wager,
+            ErrorCode::InvalidWagerLimits
+        );
+        require!(
+            config.house_fee_bps as u64 <= BPS_DENOMINATOR
+                && config.jackpot_share_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidFeeBps
+        );
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Update wager limits (admin only)
+    // ------------------------------------
+    pub fn set_wager_limits(
+        ctx: Context<UpdateConfig>,
+        min_wager: u64,
+        max_wager: u64,
     ) -> Result<()> {
-        let game_account = &mut ctx.accounts.game_account;
+        require!(min_wager <= max_wager, ErrorCode::InvalidWagerLimits);
+
+        let config = &mut ctx.accounts.config;
+        config.min_wager = min_wager;
+        config.max_wager = max_wager;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the badge Merkle tree (admin only)
+    // ------------------------------------
+    // The tree must have the badge authority PDA as its creator or delegate
+    pub fn set_badge_tree(
+        ctx: Context<UpdateConfig>,
+        badge_tree: Pubkey,
+        badge_uri_prefix: String, // Badge metadata lives at <prefix><badge>.json
+    ) -> Result<()> {
+        require!(
+            badge_uri_prefix.len() <= MAX_BADGE_URI_PREFIX_LEN,
+            ErrorCode::BadgeUriTooLong
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.badge_tree = badge_tree;
+        config.badge_uri_prefix = badge_uri_prefix;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the reward for an achievement (admin only)
+    // ------------------------------------
+    // Rewards are paid in lamports from the achievement vault PDA
+    pub fn set_achievement_reward(
+        ctx: Context<UpdateConfig>,
+        achievement: Achievement,
+        reward: u64,
+    ) -> Result<()> {
+        ctx.accounts.config.achievement_rewards[achievement as usize] = reward;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the house fee and the jackpot's share of it (admin only)
+    // ------------------------------------
+    // The jackpot vault must hold its rent-exempt minimum before a share is
+    // routed to it
+    pub fn set_fees(
+        ctx: Context<UpdateConfig>,
+        house: Pubkey,
+        house_fee_bps: u16,     // Fee taken from every pot
+        jackpot_share_bps: u16, // Slice of the fee routed to the jackpot vault
+    ) -> Result<()> {
+        require!(
+            house_fee_bps as u64 <= BPS_DENOMINATOR && jackpot_share_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidFeeBps
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.house = house;
+        config.house_fee_bps = house_fee_bps;
+        config.jackpot_share_bps = jackpot_share_bps;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set fees by wager size (admin only)
+    // ------------------------------------
+    // Tiers are sorted by ascending `max_wager`; a game pays the fee of the
+    // first tier its wager fits in, and wagers above every tier pay the
+    // house fee. An empty schedule charges the house fee throughout
+    pub fn set_fee_tiers(ctx: Context<UpdateConfig>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+        require!(fee_tiers.len() <= MAX_FEE_TIERS, ErrorCode::InvalidFeeTiers);
+        require!(
+            fee_tiers
+                .iter()
+                .all(|tier| tier.fee_bps as u64 <= BPS_DENOMINATOR),
+            ErrorCode::InvalidFeeBps
+        );
+        require!(
+            fee_tiers
+                .windows(2)
+                .all(|pair| pair[0].max_wager < pair[1].max_wager),
+            ErrorCode::InvalidFeeTiers
+        );
+
+        ctx.accounts.config.fee_tiers = fee_tiers;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Configure volume rakeback (admin only)
+    // ------------------------------------
+    // Players who have paid at least `threshold` lamports of house fees can
+    // claim `rakeback_bps` of them back from the treasury
+    pub fn set_rakeback(
+        ctx: Context<UpdateConfig>,
+        rakeback_bps: u16,
+        threshold: u64,
+    ) -> Result<()> {
+        require!(rakeback_bps as u64 <= BPS_DENOMINATOR, ErrorCode::InvalidFeeBps);
+
+        let config = &mut ctx.accounts.config;
+        config.rakeback_bps = rakeback_bps;
+        config.rakeback_threshold = threshold;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set how much of their stake a surrender refunds (admin only)
+    // ------------------------------------
+    pub fn set_surrender_refund(ctx: Context<UpdateConfig>, refund_bps: u16) -> Result<()> {
+        require!(refund_bps as u64 <= BPS_DENOMINATOR, ErrorCode::InvalidFeeBps);
+        ctx.accounts.config.surrender_refund_bps = refund_bps;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set how fast idle ratings fall back to the mean (admin only)
+    // ------------------------------------
+    // Every full `window` seconds without a rated game takes `decay_bps` of
+    // the distance above INITIAL_RATING; a zero window turns decay off
+    pub fn set_rating_decay(
+        ctx: Context<UpdateConfig>,
+        window: i64,
+        decay_bps: u16,
+    ) -> Result<()> {
+        require!(window >= 0, ErrorCode::InvalidRatingDecay);
+        require!(decay_bps as u64 <= BPS_DENOMINATOR, ErrorCode::InvalidFeeBps);
+
+        let config = &mut ctx.accounts.config;
+        config.rating_decay_window = window;
+        config.rating_decay_bps = decay_bps;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the weekly leaderboard prize pool (admin only)
+    // ------------------------------------
+    // Paid from the treasury, so it only applies once fees go there
+    pub fn set_weekly_prize(ctx: Context<UpdateConfig>, prize_pool: u64) -> Result<()> {
+        ctx.accounts.config.weekly_prize_pool = prize_pool;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the daily wager cap per wallet (admin only)
+    // ------------------------------------
+    // Zero lifts the cap. Volume already wagered keeps counting
+    pub fn set_daily_wager_cap(ctx: Context<UpdateConfig>, cap: u64) -> Result<()> {
+        ctx.accounts.config.daily_wager_cap = cap;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the cooldown between game creations (admin only)
+    // ------------------------------------
+    // Zero disables it
+    pub fn set_creation_cooldown(ctx: Context<UpdateConfig>, cooldown: i64) -> Result<()> {
+        require!(cooldown >= 0, ErrorCode::InvalidCooldown);
+        ctx.accounts.config.creation_cooldown = cooldown;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Require a token stake to list games (admin only)
+    // ------------------------------------
+    // Lobby creators must then pass a token account of `mint` holding at
+    // least `min_amount`. The default mint lifts the requirement
+    pub fn set_creation_stake(
+        ctx: Context<UpdateConfig>,
+        mint: Pubkey,
+        min_amount: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.creation_stake_mint = mint;
+        config.creation_stake_min = min_amount;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the game creation fee (admin only)
+    // ------------------------------------
+    // Charged on top of the wager when a game is listed in the lobby and
+    // paid into the treasury. Zero waives it
+    pub fn set_creation_fee(ctx: Context<UpdateConfig>, fee: u64) -> Result<()> {
+        ctx.accounts.config.creation_fee = fee;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Waive the house fee on draws (admin only)
+    // ------------------------------------
+    // A tie split under the Split policy then hands each player their full
+    // stake back instead of charging both for a non-result
+    pub fn set_draw_fee_waiver(ctx: Context<UpdateConfig>, waived: bool) -> Result<()> {
+        ctx.accounts.config.waive_draw_fees = waived;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create the loss insurance vault (admin only)
+    // ------------------------------------
+    // Premiums accrue here and insured losers are refunded from it. The
+    // admin can top it up with a plain transfer
+    pub fn initialize_insurance_vault(ctx: Context<InitializeInsuranceVault>) -> Result<()> {
+        let vault = &mut ctx.accounts.insurance_vault;
+        vault.exposure = 0;
+        vault.premiums_collected = 0;
+        vault.claims_paid = 0;
+        vault.bump = ctx.bumps.insurance_vault;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the loss insurance rates (admin only)
+    // ------------------------------------
+    // Both are basis points of the wager. A zero premium stops new policies;
+    // ones already sold keep the refund they were bought with
+    pub fn set_insurance_rates(
+        ctx: Context<UpdateConfig>,
+        premium_bps: u16,
+        refund_bps: u16,
+    ) -> Result<()> {
+        require!(
+            premium_bps as u64 <= BPS_DENOMINATOR && refund_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidFeeBps
+        );
+        let config = &mut ctx.accounts.config;
+        config.insurance_premium_bps = premium_bps;
+        config.insurance_refund_bps = refund_bps;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Cap the open games a wallet may list (admin only)
+    // ------------------------------------
+    // Counts lobby games nobody has joined yet, so one wallet can't flood
+    // the lobby. Zero lifts the cap
+    pub fn set_max_open_games(ctx: Context<UpdateConfig>, max_open_games: u16) -> Result<()> {
+        ctx.accounts.config.max_open_games = max_open_games;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Claim rakeback on the fees paid so far
+    // ------------------------------------
+    pub fn claim_rakeback(ctx: Context<ClaimRakeback>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let stats = &mut ctx.accounts.player_stats;
+        require!(
+            stats.rake_paid >= config.rakeback_threshold,
+            ErrorCode::RakebackThresholdNotMet
+        );
+        let earned = (stats.rake_paid as u128 * config.rakeback_bps as u128
+            / BPS_DENOMINATOR as u128) as u64;
+        let owed = earned.saturating_sub(stats.rakeback_claimed);
+        require!(owed > 0, ErrorCode::NoRakebackOwed);
+        stats.rakeback_claimed = earned;
+
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+        let rent_floor = Rent::get()?.minimum_balance(treasury_info.data_len());
+        require!(
+            owed <= treasury_info.lamports().saturating_sub(rent_floor),
+            ErrorCode::InsufficientTreasury
+        );
+        move_lamports(&treasury_info, ctx.accounts.player.as_ref(), owed)?;
+        msg!("Rakeback: {}", owed);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create the loyalty points mint (admin only)
+    // ------------------------------------
+    // The mint is its own mint and freeze authority, so only this program
+    // can issue points
+    pub fn initialize_points_mint(_ctx: Context<InitializePointsMint>) -> Result<()> {
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Configure loyalty points (admin only)
+    // ------------------------------------
+    // Both players of a settled game earn `points_per_sol` points per SOL
+    // wagered. Non-tradeable points stay frozen in the holder's account
+    pub fn set_points(
+        ctx: Context<UpdateConfig>,
+        points_per_sol: u64,
+        tradeable: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.points_per_sol = points_per_sol;
+        config.points_tradeable = tradeable;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Mint the loyalty points earned so far
+    // ------------------------------------
+    pub fn claim_points(ctx: Context<ClaimPoints>) -> Result<()> {
+        let amount = ctx.accounts.player_stats.points_unclaimed;
+        require!(amount > 0, ErrorCode::NoPointsOwed);
+        ctx.accounts.player_stats.points_unclaimed = 0;
+
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let mint = ctx.accounts.points_mint.to_account_info();
+        let account = ctx.accounts.player_token.to_account_info();
+        let signer_seeds: &[&[&[u8]]] = &[&[POINTS_MINT_SEED, &[ctx.bumps.points_mint]]];
+
+        if ctx.accounts.player_token.is_frozen() {
+            token::thaw_account(CpiContext::new_with_signer(
+                token_program.clone(),
+                ThawAccount {
+                    account: account.clone(),
+                    mint: mint.clone(),
+                    authority: mint.clone(),
+                },
+                signer_seeds,
+            ))?;
+        }
+        token::mint_to(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                MintTo {
+                    mint: mint.clone(),
+                    to: account.clone(),
+                    authority: mint.clone(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+        if !ctx.accounts.config.points_tradeable {
+            token::freeze_account(CpiContext::new_with_signer(
+                token_program,
+                FreezeAccount {
+                    account,
+                    mint: mint.clone(),
+                    authority: mint,
+                },
+                signer_seeds,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Schedule a promotional fee window (admin only)
+    // ------------------------------------
+    // Pots settled from `start` up to, but not including, `end` are charged
+    // `fee_bps` instead of the house fee. An empty window turns it off
+    pub fn set_fee_holiday(
+        ctx: Context<UpdateConfig>,
+        start: i64,
+        end: i64,
+        fee_bps: u16,
+    ) -> Result<()> {
+        require!(start <= end, ErrorCode::InvalidFeeHoliday);
+        require!(fee_bps as u64 <= BPS_DENOMINATOR, ErrorCode::InvalidFeeBps);
+
+        let config = &mut ctx.accounts.config;
+        config.fee_holiday_start = start;
+        config.fee_holiday_end = end;
+        config.fee_holiday_bps = fee_bps;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Split the house fee across several recipients (admin only)
+    // ------------------------------------
+    // Weights must add up to 100%; an empty list sends the whole fee to the
+    // house account. Settlement takes the recipients as remaining accounts
+    pub fn set_fee_recipients(
+        ctx: Context<UpdateConfig>,
+        fee_recipients: Vec<FeeRecipient>,
+    ) -> Result<()> {
+        require!(
+            fee_recipients.len() <= MAX_FEE_RECIPIENTS,
+            ErrorCode::InvalidFeeRecipients
+        );
+        let total_bps: u64 = fee_recipients
+            .iter()
+            .map(|recipient| recipient.weight_bps as u64)
+            .sum();
+        require!(
+            fee_recipients.is_empty() || total_bps == BPS_DENOMINATOR,
+            ErrorCode::InvalidFeeRecipients
+        );
+
+        ctx.accounts.config.fee_recipients = fee_recipients;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create the fee treasury (admin only)
+    // ------------------------------------
+    // Points the config's house at the treasury PDA, so settlement fees
+    // accrue there until the admin withdraws them
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.total_withdrawn = 0;
+        treasury.bump = ctx.bumps.treasury;
+
+        ctx.accounts.config.house = treasury.key();
+        msg!("House: {}", treasury.key());
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Withdraw accrued fees from the treasury (admin only)
+    // ------------------------------------
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+        let rent_floor = Rent::get()?.minimum_balance(treasury_info.data_len());
+        require!(
+            amount > 0 && amount <= treasury_info.lamports().saturating_sub(rent_floor),
+            ErrorCode::InsufficientTreasury
+        );
+        move_lamports(&treasury_info, ctx.accounts.recipient.as_ref(), amount)?;
+
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.total_withdrawn = treasury
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        emit!(FeesWithdrawn {
+            admin: ctx.accounts.admin.key(),
+            recipient: ctx.accounts.recipient.key(),
+            amount,
+            total_withdrawn: treasury.total_withdrawn,
+        });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the jackpot trigger rule (admin only)
+    // ------------------------------------
+    // The jackpot pays out when the winner beats `losing_move` with
+    // `winning_move` and the game's deciding slot hash ends in `hash_byte`,
+    // see jackpot_triggered
+    pub fn set_jackpot_rule(
+        ctx: Context<UpdateConfig>,
+        winning_move: u8,
+        losing_move: u8,
+        hash_byte: u8,
+    ) -> Result<()> {
+        require!(winning_move <= 2 && losing_move <= 2, ErrorCode::InvalidMove);
+
+        let config = &mut ctx.accounts.config;
+        config.jackpot_winning_move = winning_move;
+        config.jackpot_losing_move = losing_move;
+        config.jackpot_hash_byte = hash_byte;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Turn VRF tie-breaks on or off (admin only)
+    // ------------------------------------
+    // While off, games set to break ties at random split them instead
+    pub fn set_tie_break_vrf(ctx: Context<UpdateConfig>, enabled: bool) -> Result<()> {
+        ctx.accounts.config.tie_break_vrf = enabled;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Move new game addresses to another seed schema (admin only)
+    // ------------------------------------
+    // Games may still be created at addresses of the previous schema for
+    // `migration_window` seconds, so clients can upgrade at their own pace
+    pub fn set_seed_version(
+        ctx: Context<UpdateConfig>,
+        seed_version: u8,
+        migration_window: i64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            seed_version > config.seed_version,
+            ErrorCode::InvalidSeedVersion
+        );
+        require!(migration_window >= 0, ErrorCode::InvalidSeedVersion);
+
+        config.previous_seed_version = config.seed_version;
+        config.seed_version = seed_version;
+        config.seed_migration_ends = Clock::get()?
+            .unix_timestamp
+            .checked_add(migration_window)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set the lamport fee for staking an NFT (admin only)
+    // ------------------------------------
+    pub fn set_nft_fee(ctx: Context<UpdateConfig>, nft_fee: u64) -> Result<()> {
+        ctx.accounts.config.nft_fee = nft_fee;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Start a new season (admin only)
+    // ------------------------------------
+    // Player stats are archived lazily the next time each player settles a game
+    pub fn roll_season(ctx: Context<UpdateConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.season_id = config
+            .season_id
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        msg!("Season: {}", config.season_id);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Apply rating decay to an idle player
+    // ------------------------------------
+    // Permissionless crank so leaderboards don't wait for the player's next
+    // game, which applies any decay due first anyway
+    pub fn apply_decay(ctx: Context<ApplyDecay>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let decayed = ctx.accounts.player_stats.apply_decay(
+            config.rating_decay_window,
+            config.rating_decay_bps,
+            Clock::get()?.unix_timestamp,
+        );
+        require!(decayed, ErrorCode::NoRatingDecay);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Register a session key
+    // ------------------------------------
+    // The session key may sign for the player within `scope` until
+    // `expires_at_slot`; registering again replaces the previous key
+    pub fn set_session_key(
+        ctx: Context<SetSessionKey>,
+        session_key: Pubkey,
+        expires_at_slot: u64,
+        scope: u8, // Bit flags, see SESSION_SCOPE_*
+    ) -> Result<()> {
+        let slot = Clock::get()?.slot;
+        require!(
+            expires_at_slot > slot && expires_at_slot - slot <= MAX_SESSION_SLOTS,
+            ErrorCode::InvalidSessionExpiry
+        );
+
+        let player_stats = &mut ctx.accounts.player_stats;
+        player_stats.session_key = session_key;
+        player_stats.session_expires_at_slot = expires_at_slot;
+        player_stats.session_scope = scope;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Revoke the current session key
+    // ------------------------------------
+    pub fn revoke_session_key(ctx: Context<SetSessionKey>) -> Result<()> {
+        let player_stats = &mut ctx.accounts.player_stats;
+        player_stats.session_key = Pubkey::default();
+        player_stats.session_expires_at_slot = 0;
+        player_stats.session_scope = 0;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Take payouts as wrapped SOL
+    // ------------------------------------
+    // Settlement then pays the player's winnings into the wSOL token account
+    // passed for them instead of their wallet
+    pub fn set_wsol_payouts(ctx: Context<SetPayoutPreference>, enabled: bool) -> Result<()> {
+        ctx.accounts.player_stats.wsol_payouts = enabled;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Set responsible gaming limits
+    // ------------------------------------
+    // A zero limit means none. Tightening applies at once; loosening waits
+    // until the lock from an earlier call expires. `lock_seconds` extends
+    // that lock from now
+    pub fn set_player_limits(
+        ctx: Context<SetPlayerLimits>,
+        max_wager: u64,
+        daily_loss_limit: u64,
+        lock_seconds: i64,
+    ) -> Result<()> {
+        require!(lock_seconds >= 0, ErrorCode::InvalidLimitLock);
+        let now = Clock::get()?.unix_timestamp;
+        let stats = &mut ctx.accounts.player_stats;
+
+        let tightens = |limit: u64, current: u64| current == 0 || (limit > 0 && limit <= current);
+        if !tightens(max_wager, stats.max_wager_limit)
+            || !tightens(daily_loss_limit, stats.daily_loss_limit)
+        {
+            require!(now >= stats.limits_locked_until, ErrorCode::LimitsLocked);
+        }
+        stats.max_wager_limit = max_wager;
+        stats.daily_loss_limit = daily_loss_limit;
+        stats.limits_locked_until = stats
+            .limits_locked_until
+            .max(now.saturating_add(lock_seconds));
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Register a wallet as a bot
+    // ------------------------------------
+    // Signed by the admin or by the bot itself. Bots can't join humans-only
+    // games or matchmaking queues, and their games against each other are
+    // unrated and never pay the jackpot
+    pub fn register_bot(ctx: Context<RegisterBot>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == ctx.accounts.config.admin || authority == ctx.accounts.bot.key(),
+            ErrorCode::Unauthorized
+        );
+        init_player_stats(
+            &mut ctx.accounts.bot_stats,
+            ctx.accounts.bot.key(),
+            ctx.bumps.bot_stats,
+            ctx.accounts.config.season_id,
+        );
+        ctx.accounts.bot_stats.bot = true;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Remove a wallet's bot registration (admin only)
+    // ------------------------------------
+    pub fn unregister_bot(ctx: Context<UnregisterBot>) -> Result<()> {
+        ctx.accounts.bot_stats.bot = false;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Self-exclude until a timestamp
+    // ------------------------------------
+    // The player can't create or join games until then. An exclusion can be
+    // extended but never shortened
+    pub fn self_exclude(ctx: Context<SetPlayerLimits>, until: i64) -> Result<()> {
+        let stats = &mut ctx.accounts.player_stats;
+        stats.excluded_until = stats.excluded_until.max(until);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Open a match history for the signer
+    // ------------------------------------
+    // Optional; once it exists, settled games passing it record their result
+    pub fn init_player_history(ctx: Context<InitPlayerHistory>) -> Result<()> {
+        let history = &mut ctx.accounts.player_history.load_init()?;
+        history.player = ctx.accounts.player.key();
+        history.bump = ctx.bumps.player_history;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Claim a username for the signer's profile
+    // ------------------------------------
+    // Creates the profile on first use. The username record maps the name
+    // back to the wallet, and since it is derived from the name no two
+    // players can hold the same one
+    pub fn register_username(ctx: Context<RegisterUsername>, username: String) -> Result<()> {
+        require!(is_valid_username(&username), ErrorCode::InvalidUsername);
+        let profile = &mut ctx.accounts.profile;
+        require!(profile.username.is_empty(), ErrorCode::UsernameAlreadySet);
+
+        profile.player = ctx.accounts.player.key();
+        profile.username = username;
+        profile.bump = ctx.bumps.profile;
+
+        let record = &mut ctx.accounts.username_record;
+        record.player = ctx.accounts.player.key();
+        record.bump = ctx.bumps.username_record;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Give up the profile's username
+    // ------------------------------------
+    // Frees the name for anyone to register and refunds the record's rent
+    pub fn release_username(ctx: Context<ReleaseUsername>) -> Result<()> {
+        ctx.accounts.profile.username = String::new();
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Show an NFT the signer holds as their avatar
+    // ------------------------------------
+    // Ownership is only checked now; frontends should recheck it before
+    // rendering if the NFT may have been sold since
+    pub fn set_avatar(ctx: Context<SetAvatar>) -> Result<()> {
+        let metadata = ctx.accounts.metadata.try_borrow_data()?;
+        require!(
+            metadata.len() >= 65
+                && metadata[0] == METADATA_V1_KEY
+                && metadata[33..65] == ctx.accounts.mint.key().to_bytes(),
+            ErrorCode::InvalidMetadata
+        );
+
+        let profile = &mut ctx.accounts.profile;
+        profile.player = ctx.accounts.player.key();
+        profile.avatar = Some(ctx.accounts.mint.key());
+        profile.bump = ctx.bumps.profile;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Remove the profile's avatar
+    // ------------------------------------
+    pub fn clear_avatar(ctx: Context<ClearAvatar>) -> Result<()> {
+        ctx.accounts.profile.avatar = None;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create a lobby bucket (admin only)
+    // ------------------------------------
+    pub fn initialize_lobby(ctx: Context<InitializeLobby>, bucket: u8) -> Result<()> {
+        require!(bucket < LOBBY_BUCKETS, ErrorCode::InvalidLobbyBucket);
+
+        let lobby = &mut ctx.accounts.lobby;
+        lobby.bucket = bucket;
+        lobby.games = Vec::new();
+        lobby.bump = ctx.bumps.lobby;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Lay out the leaderboard shards (admin only)
+    // ------------------------------------
+    // Shard i holds ratings from base_rating + i * band_width up to the next
+    // shard; the first and last shards are open-ended
+    pub fn initialize_leaderboard(
+        ctx: Context<InitializeLeaderboard>,
+        shard_count: u8,
+        base_rating: u32,
+        band_width: u32,
+    ) -> Result<()> {
+        require!(
+            (1..=MAX_LEADERBOARD_SHARDS).contains(&shard_count) && band_width > 0,
+            ErrorCode::InvalidShardMap
+        );
+
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.shard_count = shard_count;
+        leaderboard.base_rating = base_rating;
+        leaderboard.band_width = band_width;
+        leaderboard.bump = ctx.bumps.leaderboard;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create a leaderboard shard (admin only)
+    // ------------------------------------
+    pub fn initialize_leaderboard_shard(
+        ctx: Context<InitializeLeaderboardShard>,
+        index: u8,
+    ) -> Result<()> {
+        require!(
+            index < ctx.accounts.leaderboard.shard_count,
+            ErrorCode::InvalidShardMap
+        );
+
+        let shard = &mut ctx.accounts.shard;
+        shard.index = index;
+        shard.entries = Vec::new();
+        shard.bump = ctx.bumps.shard;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Refresh a player's leaderboard standing
+    // ------------------------------------
+    // Permissionless crank. `shard` must be the one for the player's current
+    // rating; if they are listed in another shard, pass it as
+    // `previous_shard` so they are promoted or demoted out of it. A player
+    // who doesn't beat the lowest entry of a full shard is left off
+    pub fn update_standing(ctx: Context<UpdateStanding>) -> Result<()> {
+        let player = ctx.accounts.player_stats.player;
+        let rating = ctx.accounts.player_stats.rating;
+        require!(
+            ctx.accounts.shard.index == ctx.accounts.leaderboard.shard_for(rating),
+            ErrorCode::WrongLeaderboardShard
+        );
+
+        if let Some(previous_shard) = ctx.accounts.previous_shard.as_mut() {
+            require!(
+                previous_shard.index != ctx.accounts.shard.index
+                    && previous_shard.remove(&player),
+                ErrorCode::WrongLeaderboardShard
+            );
+        }
+        ctx.accounts.shard.remove(&player);
+        ctx.accounts.shard.insert(LeaderboardEntry { player, rating });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Record the week's top players and fund their prizes
+    // ------------------------------------
+    // Permissionless crank for the week that just ended. The shards follow
+    // as remaining accounts from the highest band down, as many as it takes
+    // to fill the prize places. Standings are taken as last cranked by
+    // update_standing. The prize pool moves from the treasury into the
+    // snapshot, capped at what the treasury holds
+    pub fn snapshot_leaderboard(ctx: Context<SnapshotLeaderboard>, week: u64) -> Result<()> {
+        let current_week = Clock::get()?.unix_timestamp / WEEK_SECONDS;
+        require!(
+            week.checked_add(1) == Some(current_week as u64),
+            ErrorCode::InvalidSnapshotWeek
+        );
+
+        let places = WEEKLY_PRIZE_SPLIT_BPS.len();
+        let mut winners: Vec<LeaderboardEntry> = Vec::with_capacity(places);
+        let mut shards = ctx.remaining_accounts.iter();
+        for index in (0..ctx.accounts.leaderboard.shard_count).rev() {
+            if winners.len() == places {
+                break;
+            }
+            let info = shards.next().ok_or(ErrorCode::WrongLeaderboardShard)?;
+            require!(info.owner == &ID, ErrorCode::WrongLeaderboardShard);
+            let shard = LeaderboardShard::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+            require!(shard.index == index, ErrorCode::WrongLeaderboardShard);
+            let open = places - winners.len();
+            winners.extend(shard.entries.iter().take(open).cloned());
+        }
+
+        let treasury_info = ctx.accounts.treasury.to_account_info();
+        let rent_floor = Rent::get()?.minimum_balance(treasury_info.data_len());
+        let pool = if winners.is_empty() {
+            0
+        } else {
+            ctx.accounts
+                .config
+                .weekly_prize_pool
+                .min(treasury_info.lamports().saturating_sub(rent_floor))
+        };
+        let share = |place: usize| {
+            (pool as u128 * WEEKLY_PRIZE_SPLIT_BPS[place] as u128 / BPS_DENOMINATOR as u128) as u64
+        };
+        // First place takes the rounding dust and any unfilled places' shares
+        let mut prizes: Vec<u64> = (0..winners.len()).map(share).collect();
+        if let Some(first) = prizes.first_mut() {
+            *first = pool - (1..winners.len()).map(share).sum::<u64>();
+        }
+        move_lamports(&treasury_info, &ctx.accounts.snapshot.to_account_info(), pool)?;
+
+        let snapshot = &mut ctx.accounts.snapshot;
+        snapshot.week = week;
+        snapshot.winners = winners;
+        snapshot.prizes = prizes;
+        snapshot.claimed = 0;
+        snapshot.bump = ctx.bumps.snapshot;
+        emit!(LeaderboardSnapshotted { week, prize_pool: pool });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Claim a weekly leaderboard prize
+    // ------------------------------------
+    pub fn claim_weekly_prize(ctx: Context<ClaimWeeklyPrize>) -> Result<()> {
+        let snapshot = &mut ctx.accounts.snapshot;
+        let place = snapshot
+            .winners
+            .iter()
+            .position(|entry| entry.player == ctx.accounts.player.key())
+            .ok_or(ErrorCode::NotASnapshotWinner)?;
+        require!(
+            snapshot.claimed & (1 << place) == 0,
+            ErrorCode::PrizeAlreadyClaimed
+        );
+        snapshot.claimed |= 1 << place;
+
+        let prize = snapshot.prizes[place];
+        move_lamports(
+            &snapshot.to_account_info(),
+            &ctx.accounts.player.to_account_info(),
+            prize,
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create a new game
+    // ------------------------------------
+    pub fn create_game(
+        ctx: Context<CreateGame>,
+        wager: u64,                    // Wager amount (in lamports)
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+
+        init_game(
+            &mut ctx.accounts.game_account,
+            ctx.accounts.creator.key(),
+            wager,
+            ctx.bumps.game_account,
+        )?;
+        if let Some(ruleset) = &ctx.accounts.ruleset {
+            ctx.accounts.game_account.apply_ruleset(ruleset)?;
+        }
+
+        init_player_stats(
+            &mut ctx.accounts.creator_stats,
+            ctx.accounts.creator.key(),
+            ctx.bumps.creator_stats,
+            config.season_id,
+        );
+        admit_creation(
+            config,
+            ctx.accounts.creator.key,
+            ctx.accounts.creator_stake.as_deref(),
+            &mut ctx.accounts.creator_stats,
+            wager,
+        )?;
+
+        // List the game in its lobby bucket
+        ctx.accounts.lobby.register(LobbyEntry {
+            game: ctx.accounts.game_account.key(),
+            creator: ctx.accounts.creator.key(),
+            wager,
+            category: GameCategory::Casual,
+        });
+
+        emit_lifecycle_event!(
+            ctx,
+            GameCreated {
+                game: ctx.accounts.game_account.key(),
+                creator: ctx.accounts.creator.key(),
+                opponent: None,
+                wager,
+            }
+        );
+
+        // Transfer wager lamports to game_account from the creator, or from
+        // the sponsor fronting them
+        let funder = match &ctx.accounts.sponsor {
+            Some(sponsor) => sponsor.to_account_info(),
+            None => ctx.accounts.creator.to_account_info(),
+        };
+        ctx.accounts.game_account.creator_sponsor =
+            ctx.accounts.sponsor.as_ref().map(|sponsor| sponsor.key());
+        if wager > 0 {
+            let ix = system_instruction::transfer(
+                funder.key,
+                &ctx.accounts.game_account.key(),
+                wager,
+            );
+            invoke(
+                &ix,
+                &[
+                    funder,
+                    ctx.accounts.game_account.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+        charge_creation_fee(
+            &ctx.accounts.config,
+            &ctx.accounts.creator,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )?;
+
+        index_game(&mut ctx.accounts.game_account, &mut ctx.accounts.config)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Save reusable game settings
+    // ------------------------------------
+    // Creates the creator's preset `id`, or overwrites it. The wager is only
+    // checked against the config limits when a game is created from it
+    pub fn save_preset(ctx: Context<SavePreset>, _id: u64, settings: PresetSettings) -> Result<()> {
+        require!(
+            settings.join_window > 0 && settings.join_window <= JOIN_WINDOW_SECONDS,
+            ErrorCode::InvalidJoinWindow
+        );
+        require!(settings.time_bank >= 0, ErrorCode::InvalidTimeBank);
+
+        let preset = &mut ctx.accounts.preset;
+        preset.creator = ctx.accounts.creator.key();
+        preset.settings = settings;
+        preset.bump = ctx.bumps.preset;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Delete a preset and reclaim its rent
+    // ------------------------------------
+    pub fn close_preset(_ctx: Context<ClosePreset>) -> Result<()> {
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Publish a custom ruleset
+    // ------------------------------------
+    // `beats[i]` is a bitmask of the moves move i beats; two moves that don't
+    // beat each other tie. Rulesets can't be edited once published, and
+    // games copy the rules in when they are created, so closing one never
+    // changes a game in progress
+    pub fn publish_ruleset(
+        ctx: Context<PublishRuleset>,
+        _id: u64,
+        move_count: u8,
+        beats: [u16; MAX_MOVE_COUNT as usize],
+        tie_policy: TiePolicy,
+        join_window: i64,
+        time_bank: i64,
+    ) -> Result<()> {
+        require!(
+            (2..=MAX_MOVE_COUNT).contains(&move_count),
+            ErrorCode::InvalidMoveCount
+        );
+        for (i, mask) in beats.iter().enumerate() {
+            // No move beats itself or a move outside the set, and no two
+            // moves beat each other
+            let valid = if i < move_count as usize {
+                mask >> move_count == 0
+                    && mask & (1 << i) == 0
+                    && (0..move_count as usize)
+                        .all(|j| mask & (1 << j) == 0 || beats[j] & (1 << i) == 0)
+            } else {
+                *mask == 0
+            };
+            require!(valid, ErrorCode::InvalidRuleset);
+        }
+        require!(
+            join_window > 0 && join_window <= JOIN_WINDOW_SECONDS,
+            ErrorCode::InvalidJoinWindow
+        );
+        require!(time_bank >= 0, ErrorCode::InvalidTimeBank);
+
+        let ruleset = &mut ctx.accounts.ruleset;
+        ruleset.author = ctx.accounts.author.key();
+        ruleset.move_count = move_count;
+        ruleset.beats = beats;
+        ruleset.tie_policy = tie_policy;
+        ruleset.join_window = join_window;
+        ruleset.time_bank = time_bank;
+        ruleset.bump = ctx.bumps.ruleset;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Delete a ruleset and reclaim its rent
+    // ------------------------------------
+    pub fn close_ruleset(_ctx: Context<CloseRuleset>) -> Result<()> {
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create a new game from a preset
+    // ------------------------------------
+    pub fn create_game_from_preset(ctx: Context<CreateGameFromPreset>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let settings = ctx.accounts.preset.settings.clone();
+        let wager = settings.wager;
+
+        let game_account = &mut ctx.accounts.game_account;
+        init_game(
+            game_account,
+            ctx.accounts.creator.key(),
+            wager,
+            ctx.bumps.game_account,
+        )?;
+        game_account.join_deadline = game_account
+            .created_at
+            .checked_add(settings.join_window)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        game_account.set_tie_policy(settings.tie_policy);
+        game_account.reveal_order = settings.reveal_order;
+        game_account.time_bank = settings.time_bank;
+        game_account.creator_time_left = settings.time_bank;
+        game_account.joiner_time_left = settings.time_bank;
+        game_account.min_reputation = settings.min_reputation;
+        game_account.set_flag(GAME_FLAG_INVITE_ONLY, settings.invite_only);
+        game_account.set_flag(GAME_FLAG_APPROVAL_REQUIRED, settings.approval_required);
+
+        init_player_stats(
+            &mut ctx.accounts.creator_stats,
+            ctx.accounts.creator.key(),
+            ctx.bumps.creator_stats,
+            config.season_id,
+        );
+        admit_creation(
+            config,
+            ctx.accounts.creator.key,
+            ctx.accounts.creator_stake.as_deref(),
+            &mut ctx.accounts.creator_stats,
+            wager,
+        )?;
+
+        ctx.accounts.lobby.register(LobbyEntry {
+            game: ctx.accounts.game_account.key(),
+            creator: ctx.accounts.creator.key(),
+            wager,
+            category: GameCategory::Casual,
+        });
+
+        emit_lifecycle_event!(
+            ctx,
+            GameCreated {
+                game: ctx.accounts.game_account.key(),
+                creator: ctx.accounts.creator.key(),
+                opponent: None,
+                wager,
+            }
+        );
+
+        // Transfer wager lamports to game_account from the creator, or from
+        // the sponsor fronting them
+        let funder = match &ctx.accounts.sponsor {
+            Some(sponsor) => sponsor.to_account_info(),
+            None => ctx.accounts.creator.to_account_info(),
+        };
+        ctx.accounts.game_account.creator_sponsor =
+            ctx.accounts.sponsor.as_ref().map(|sponsor| sponsor.key());
+        if wager > 0 {
+            let ix = system_instruction::transfer(
+                funder.key,
+                &ctx.accounts.game_account.key(),
+                wager,
+            );
+            invoke(
+                &ix,
+                &[
+                    funder,
+                    ctx.accounts.game_account.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+        charge_creation_fee(
+            &ctx.accounts.config,
+            &ctx.accounts.creator,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )?;
+
+        index_game(&mut ctx.accounts.game_account, &mut ctx.accounts.config)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Join an existing game
+    // ------------------------------------
+    pub fn join_game(
+        mut ctx: Context<JoinGame>,
+    ) -> Result<()> {
+        check_direct_join(&ctx.accounts.game_account)?;
+
+        apply_join(&mut ctx)
+    }
+
+    // ------------------------------------
+    // Instruction: Join a game with a permit from the creator
+    // ------------------------------------
+    // The creator signs game key || joiner || expires_at (i64 LE) off-chain;
+    // the signature is checked by an ed25519 program instruction placed
+    // right before this one
+    pub fn join_game_with_permit(mut ctx: Context<JoinGame>, expires_at: i64) -> Result<()> {
+        require!(
+            Clock::get()?.unix_timestamp <= expires_at,
+            ErrorCode::PermitExpired
+        );
+
+        let message = [
+            ctx.accounts.game_account.key().as_ref(),
+            ctx.accounts.joiner.key().as_ref(),
+            &expires_at.to_le_bytes(),
+        ]
+        .concat();
+        verify_ed25519_signature(
+            &ctx.accounts.instructions,
+            &ctx.accounts.game_account.creator,
+            &message,
+        )?;
+
+        apply_join(&mut ctx)
+    }
+
+    // ------------------------------------
+    // Instruction: Join a game and reveal in one step
+    // ------------------------------------
+    // Only allowed once the creator's commitment is locked in by readying up,
+    // since the joiner's move is public from here on
+    pub fn join_and_reveal(
+        mut ctx: Context<JoinGame>,
+        original_move: u8,
+        salt: String,
+    ) -> Result<()> {
+        ctx.accounts.game_account.check_move(original_move)?;
+        check_direct_join(&ctx.accounts.game_account)?;
+        require!(
+            ctx.accounts.game_account.has_flag(GAME_FLAG_CREATOR_READY),
+            ErrorCode::CreatorNotReady
+        );
+        require!(
+            ctx.accounts.game_account.assets.is_empty(),
+            ErrorCode::PotDepositMissing
+        );
+        require!(
+            ctx.accounts.game_account.reveal_order != RevealOrder::CreatorFirst,
+            ErrorCode::RevealOutOfOrder
+        );
+
+        apply_join(&mut ctx)?;
+
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &mut ctx.accounts.game_account;
+        game_account.joiner_move_hashed =
+            commitment_hash(&game_key, game_account.nonce, original_move, salt.as_bytes());
+        game_account.joiner_move = Some(original_move);
+        game_account.set_flag(GAME_FLAG_JOINER_READY, true);
+
+        // Only the creator's reveal is outstanding
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        game_account.status = GameStatus::Revealing;
+        game_account.phase_started_at = now;
+        game_account.reveal_slot = clock.slot;
+        game_account.record_reveal(now);
+        game_account.reveal_deadline = now
+            .checked_add(REVEAL_WINDOW_SECONDS)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create a game with a hidden wager
+    // ------------------------------------
+    // The game address is derived from `wager_commitment` (see
+    // wager_commitment) instead of the wager. The creator, or a sponsor
+    // fronting them, escrows `deposit`, at least the wager, and shares the
+    // wager and salt with their opponent off-chain; the game is not listed
+    // in a lobby
+    pub fn create_hidden_game(
+        ctx: Context<CreateHiddenGame>,
+        _wager_commitment: [u8; 32],
+        deposit: u64,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+
+        // The wager stays zero until the joiner reveals it
+        let game_account = &mut ctx.accounts.game_account;
+        init_game(game_account, ctx.accounts.creator.key(), 0, ctx.bumps.game_account)?;
+        game_account.escrow = deposit;
+        game_account.status = GameStatus::Hidden;
+
+        init_player_stats(
+            &mut ctx.accounts.creator_stats,
+            ctx.accounts.creator.key(),
+            ctx.bumps.creator_stats,
+            config.season_id,
+        );
+        // The deposit bounds what the hidden wager can be
+        admit_creation(
+            config,
+            ctx.accounts.creator.key,
+            ctx.accounts.creator_stake.as_deref(),
+            &mut ctx.accounts.creator_stats,
+            deposit,
+        )?;
+
+        let funder = match &ctx.accounts.sponsor {
+            Some(sponsor) => sponsor.to_account_info(),
+            None => ctx.accounts.creator.to_account_info(),
+        };
+        ctx.accounts.game_account.creator_sponsor =
+            ctx.accounts.sponsor.as_ref().map(|sponsor| sponsor.key());
+        let ix = system_instruction::transfer(
+            funder.key,
+            &ctx.accounts.game_account.key(),
+            deposit,
+        );
+        invoke(
+            &ix,
+            &[
+                funder,
+                ctx.accounts.game_account.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        emit_lifecycle_event!(
+            ctx,
+            GameCreated {
+                game: ctx.accounts.game_account.key(),
+                creator: ctx.accounts.creator.key(),
+                opponent: None,
+                wager: 0, // Hidden
+            }
+        );
+        charge_creation_fee(
+            &ctx.accounts.config,
+            &ctx.accounts.creator,
+            &ctx.accounts.treasury,
+            &ctx.accounts.system_program,
+        )?;
+
+        index_game(&mut ctx.accounts.game_account, &mut ctx.accounts.config)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Join a hidden-wager game
+    // ------------------------------------
+    // The wager and salt must open the commitment the game address was
+    // derived from. The creator's deposit above the wager is refunded, to
+    // their sponsor if they had one, and the game is then joined like any
+    // open one
+    pub fn join_hidden_game(ctx: Context<JoinHiddenGame>, wager: u64, _salt: String) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(wager >= config.min_wager, ErrorCode::WagerTooLow);
+        require!(wager <= config.max_wager, ErrorCode::WagerTooHigh);
+
+        let joiner = ctx.accounts.joiner.key();
+        let game_account = &mut ctx.accounts.game_account;
+        let excess = game_account
+            .escrow
+            .checked_sub(wager)
+            .ok_or(ErrorCode::InsufficientEscrow)?;
+        let sponsor = game_account.creator_sponsor;
+        pay_player(
+            game_account,
+            sponsor,
+            ctx.accounts.creator_sponsor.as_deref(),
+            ctx.accounts.creator.as_ref(),
+            excess,
+        )?;
+        game_account.wager = wager;
+        game_account.status = GameStatus::Open;
+        check_direct_join(game_account)?;
+
+        init_player_stats(
+            &mut ctx.accounts.joiner_stats,
+            joiner,
+            ctx.bumps.joiner_stats,
+            config.season_id,
+        );
+        admit_joiner(
+            game_account,
+            config,
+            &joiner,
+            &mut ctx.accounts.joiner_stats,
+            ctx.accounts.allowlist.as_deref(),
+        )?;
+        // Only the wager counts against the creator's daily cap, not the
+        // deposit that hid it
+        let created_at = ctx.accounts.game_account.created_at;
+        let creator_stats = &mut ctx.accounts.creator_stats;
+        creator_stats.close_open_game();
+        creator_stats.release_wager(excess, created_at);
+
+        let funder = match &ctx.accounts.sponsor {
+            Some(sponsor) => sponsor.to_account_info(),
+            None => ctx.accounts.joiner.to_account_info(),
+        };
+        ctx.accounts.game_account.joiner_sponsor =
+            ctx.accounts.sponsor.as_ref().map(|sponsor| sponsor.key());
+        let ix = system_instruction::transfer(funder.key, &ctx.accounts.game_account.key(), wager);
+        invoke(
+            &ix,
+            &[
+                funder,
+                ctx.accounts.game_account.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        emit_lifecycle_event!(
+            ctx,
+            GameJoined {
+                game: ctx.accounts.game_account.key(),
+                joiner,
+                wager,
+            }
+        );
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Require a permit to join an open game
+    // ------------------------------------
+    pub fn set_invite_only(ctx: Context<UpdateOpenGame>, invite_only: bool) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        game_account.set_flag(GAME_FLAG_INVITE_ONLY, invite_only);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Choose what happens on a tie
+    // ------------------------------------
+    pub fn set_tie_policy(ctx: Context<UpdateOpenGame>, tie_policy: TiePolicy) -> Result<()> {
+        ctx.accounts.game_account.set_tie_policy(tie_policy);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Choose who has to reveal first
+    // ------------------------------------
+    pub fn set_reveal_order(ctx: Context<UpdateOpenGame>, reveal_order: RevealOrder) -> Result<()> {
+        ctx.accounts.game_account.reveal_order = reveal_order;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Give both players a chess-clock time bank
+    // ------------------------------------
+    // Each player gets `seconds` for all their commits and reveals in the
+    // match; 0 turns the clocks off
+    pub fn set_time_bank(ctx: Context<UpdateOpenGame>, seconds: i64) -> Result<()> {
+        require!(seconds >= 0, ErrorCode::InvalidTimeBank);
+
+        let game_account = &mut ctx.accounts.game_account;
+        game_account.time_bank = seconds;
+        game_account.creator_time_left = seconds;
+        game_account.joiner_time_left = seconds;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Require a minimum reputation from joiners
+    // ------------------------------------
+    pub fn set_min_reputation(ctx: Context<UpdateOpenGame>, min_reputation: u32) -> Result<()> {
+        ctx.accounts.game_account.min_reputation = min_reputation;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Require a reveal bond from both players
+    // ------------------------------------
+    // The creator posts their bond now and the joiner theirs on joining. A
+    // player who fails to reveal in time forfeits it to the opponent, see
+    // claim_reveal_timeout
+    pub fn set_reveal_bond(ctx: Context<SetRevealBond>, bond: u64) -> Result<()> {
+        require!(
+            bond <= ctx.accounts.config.max_wager,
+            ErrorCode::WagerTooHigh
+        );
+        let posted = ctx.accounts.game_account.bond;
+        ctx.accounts.game_account.bond = bond;
+
+        if bond > posted {
+            let ix = system_instruction::transfer(
+                &ctx.accounts.creator.key(),
+                &ctx.accounts.game_account.key(),
+                bond - posted,
+            );
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.creator.to_account_info(),
+                    ctx.accounts.game_account.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        } else {
+            move_lamports(
+                ctx.accounts.game_account.as_ref(),
+                ctx.accounts.creator.as_ref(),
+                posted - bond,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Raise the wager of an open game
+    // ------------------------------------
+    // The game keeps its address, which stays derived from the wager it was
+    // created with; clients should look games up by address or through the
+    // lobby rather than re-deriving them from the current wager
+    pub fn increase_wager(mut ctx: Context<ChangeWager>, amount: u64) -> Result<()> {
+        let wager = ctx
+            .accounts
+            .game_account
+            .wager
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(wager <= ctx.accounts.config.max_wager, ErrorCode::WagerTooHigh);
+
+        change_wager(&mut ctx, wager)
+    }
+
+    // ------------------------------------
+    // Instruction: Lower the wager of an open game
+    // ------------------------------------
+    // The difference goes back to the creator; see increase_wager for the
+    // game address
+    pub fn decrease_wager(mut ctx: Context<ChangeWager>, amount: u64) -> Result<()> {
+        let wager = ctx
+            .accounts
+            .game_account
+            .wager
+            .checked_sub(amount)
+            .ok_or(ErrorCode::WagerTooLow)?;
+        require!(wager >= ctx.accounts.config.min_wager, ErrorCode::WagerTooLow);
+
+        change_wager(&mut ctx, wager)
+    }
+
+    // ------------------------------------
+    // Instruction: Counter an open game's wager
+    // ------------------------------------
+    // A prospective joiner names the stake they would play for. The game
+    // holds one offer at a time and a newer offer replaces it
+    pub fn offer_wager(ctx: Context<OfferWager>, wager: u64) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(!config.paused, ErrorCode::ProgramPaused);
+        config.check_not_banned(ctx.accounts.joiner.key)?;
+        require!(wager >= config.min_wager, ErrorCode::WagerTooLow);
+        require!(wager <= config.max_wager, ErrorCode::WagerTooHigh);
+
+        let joiner = ctx.accounts.joiner.key();
+        let game_account = &mut ctx.accounts.game_account;
+        check_joinable(game_account, &joiner)?;
+        game_account.counter_offer = Some(CounterOffer { joiner, wager });
+
+        emit!(CounterOffered {
+            game: game_account.key(),
+            joiner,
+            wager,
+        });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Accept the pending counter-offer
+    // ------------------------------------
+    // Moves the game to the offered wager and reserves the seat for the
+    // player who made the offer, who then joins as usual. The expected offer
+    // is passed in so a replaced offer is never accepted by mistake
+    pub fn accept_counter_offer(
+        mut ctx: Context<ChangeWager>,
+        joiner: Pubkey,
+        wager: u64,
+    ) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            game_account.counter_offer == Some(CounterOffer { joiner, wager }),
+            ErrorCode::CounterOfferMismatch
+        );
+        game_account.counter_offer = None;
+        game_account.reserved_for = Some(joiner);
+
+        change_wager(&mut ctx, wager)
+    }
+
+    // ------------------------------------
+    // Instruction: Decline the pending counter-offer
+    // ------------------------------------
+    pub fn decline_counter_offer(ctx: Context<UpdateOpenGame>) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        let offer = game_account
+            .counter_offer
+            .take()
+            .ok_or(ErrorCode::CounterOfferMismatch)?;
+
+        emit!(CounterOfferDeclined {
+            game: game_account.key(),
+            joiner: offer.joiner,
+            wager: offer.wager,
+        });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Keep registered bots out of an open game
+    // ------------------------------------
+    pub fn set_humans_only(ctx: Context<UpdateOpenGame>, humans_only: bool) -> Result<()> {
+        ctx.accounts
+            .game_account
+            .set_flag(GAME_FLAG_HUMANS_ONLY, humans_only);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Choose what the house fee is charged on
+    // ------------------------------------
+    pub fn set_fee_mode(ctx: Context<UpdateOpenGame>, fee_mode: FeeMode) -> Result<()> {
+        ctx.accounts.game_account.fee_mode = fee_mode;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Hold the winner's payout in the game for a rematch
+    // ------------------------------------
+    // Agreed while the game is open. After a win the loser may offer to play
+    // for the held payout double or nothing; the winner can collect it with
+    // claim_rollover instead
+    pub fn set_rollover(ctx: Context<UpdateOpenGame>, rollover: bool) -> Result<()> {
+        ctx.accounts.game_account.set_flag(GAME_FLAG_ROLLOVER, rollover);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Award ties to the lower-rated player
+    // ------------------------------------
+    // Agreed while the game is open, so a joiner sees the handicap before
+    // taking the seat. Players with equal ratings still tie
+    pub fn set_handicap(ctx: Context<UpdateOpenGame>, handicap: bool) -> Result<()> {
+        ctx.accounts.game_account.set_flag(GAME_FLAG_HANDICAP, handicap);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Give an open game a display name
+    // ------------------------------------
+    // Stored zero-padded, so the label may not contain NUL itself. An empty
+    // label clears it
+    pub fn set_label(ctx: Context<UpdateOpenGame>, label: String) -> Result<()> {
+        require!(
+            label.len() <= MAX_LABEL_LEN && !label.contains('\0'),
+            ErrorCode::InvalidLabel
+        );
+        let game_account = &mut ctx.accounts.game_account;
+        game_account.label = [0u8; MAX_LABEL_LEN];
+        game_account.label[..label.len()].copy_from_slice(label.as_bytes());
+
+        emit!(GameLabelled {
+            game: game_account.key(),
+            label,
+        });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Tag an open game for lobby filtering
+    // ------------------------------------
+    // Games start out Casual. The category is mirrored into the game's lobby
+    // entry so clients can filter a bucket without fetching every game
+    pub fn set_category(ctx: Context<SetCategory>, category: GameCategory) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        ctx.accounts.game_account.category = category;
+        ctx.accounts.lobby.update_category(&game_key, category);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Play with a larger set of moves
+    // ------------------------------------
+    // Any odd count from 3 up to MAX_MOVE_COUNT keeps the game fair: each
+    // move beats half of the others. Moves are numbered 0 to count - 1
+    pub fn set_move_count(ctx: Context<UpdateOpenGame>, move_count: u8) -> Result<()> {
+        require!(
+            ctx.accounts.game_account.ruleset.is_none(),
+            ErrorCode::RulesetGame
+        );
+        require!(
+            (CLASSIC_MOVE_COUNT..=MAX_MOVE_COUNT).contains(&move_count) && move_count % 2 == 1,
+            ErrorCode::InvalidMoveCount
+        );
+        ctx.accounts.game_account.move_count = move_count;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Make joiners wait for the creator's approval
+    // ------------------------------------
+    pub fn set_join_approval(ctx: Context<UpdateOpenGame>, required: bool) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        game_account.set_flag(GAME_FLAG_APPROVAL_REQUIRED, required);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Offer an open game to a group of players
+    // ------------------------------------
+    // Send in the same transaction as create_game so nobody outside the
+    // group can join first
+    pub fn set_allowlist(ctx: Context<SetAllowlist>, players: Vec<Pubkey>) -> Result<()> {
+        require!(
+            !players.is_empty() && players.len() <= MAX_ALLOWLIST_LEN,
+            ErrorCode::InvalidAllowlist
+        );
+
+        let allowlist = &mut ctx.accounts.allowlist;
+        allowlist.game = ctx.accounts.game_account.key();
+        allowlist.creator = ctx.accounts.creator.key();
+        allowlist.players = players;
+        allowlist.bump = ctx.bumps.allowlist;
+
+        ctx.accounts
+            .game_account
+            .set_flag(GAME_FLAG_ALLOWLIST, true);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Register a program to notify when the game ends
+    // ------------------------------------
+    // Send in the same transaction as create_game. A game settled by play,
+    // timeout, forfeit or surrender then invokes `program` with the game
+    // account followed by `accounts`, but only when whoever settles passes
+    // the callback: the creator controls that program, so a failing one can
+    // never hold up the payouts
+    pub fn set_callback(
+        ctx: Context<SetCallback>,
+        program: Pubkey,
+        accounts: Vec<CallbackAccount>,
+    ) -> Result<()> {
+        require!(
+            program != ID && accounts.len() <= MAX_CALLBACK_ACCOUNTS,
+            ErrorCode::InvalidCallback
+        );
+
+        let callback = &mut ctx.accounts.callback;
+        callback.game = ctx.accounts.game_account.key();
+        callback.creator = ctx.accounts.creator.key();
+        callback.program = program;
+        callback.accounts = accounts;
+        callback.bump = ctx.bumps.callback;
+        ctx.accounts
+            .game_account
+            .set_flag(GAME_FLAG_CALLBACK, true);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Remove a game's callback and reclaim its rent
+    // ------------------------------------
+    // Later settlements go without it
+    pub fn close_callback(ctx: Context<CloseCallback>) -> Result<()> {
+        let game_info = &ctx.accounts.game_account;
+        if game_info.owner == &ID && !game_info.data_is_empty() {
+            let mut game_account =
+                GameState::try_deserialize(&mut &game_info.try_borrow_data()?[..])?;
+            game_account.set_flag(GAME_FLAG_CALLBACK, false);
+            game_account.try_serialize(&mut &mut game_info.try_borrow_mut_data()?[..])?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Reclaim an allowlist's rent
+    // ------------------------------------
+    // Only once its game can no longer be joined
+    pub fn close_allowlist(ctx: Context<CloseAllowlist>) -> Result<()> {
+        let game_info = &ctx.accounts.game_account;
+        if game_info.owner == &ID && !game_info.data_is_empty() {
+            let game_account = GameState::try_deserialize(&mut &game_info.try_borrow_data()?[..])?;
+            require!(
+                game_account.status != GameStatus::Open,
+                ErrorCode::GameStillOpen
+            );
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Ask to join a game that requires approval
+    // ------------------------------------
+    // The wager is escrowed in the request until the creator accepts it or
+    // the joiner withdraws
+    pub fn request_join(ctx: Context<RequestJoin>) -> Result<()> {
+        let joiner = ctx.accounts.joiner.key();
+        check_joinable(&ctx.accounts.game_account, &joiner)?;
+        check_allowlist(
+            &ctx.accounts.game_account,
+            ctx.accounts.allowlist.as_deref(),
+            &joiner,
+        )?;
+
+        let join_request = &mut ctx.accounts.join_request;
+        join_request.game = ctx.accounts.game_account.key();
+        join_request.joiner = joiner;
+        join_request.wager = ctx.accounts.game_account.wager;
+        join_request.bond = ctx.accounts.game_account.bond;
+        join_request.bump = ctx.bumps.join_request;
+
+        let wager = join_request.wager;
+        let deposit = wager
+            .checked_add(join_request.bond)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        if deposit > 0 {
+            let ix = system_instruction::transfer(&joiner, &join_request.key(), deposit);
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.joiner.to_account_info(),
+                    ctx.accounts.join_request.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        emit!(JoinRequested {
+            game: ctx.accounts.game_account.key(),
+            joiner,
+            wager,
+        });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Accept a join request
+    // ------------------------------------
+    // Seats the requesting player; their escrowed wager moves into the game
+    // and the request's rent goes back to them
+    pub fn accept_join_request(ctx: Context<AcceptJoinRequest>) -> Result<()> {
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        ctx.accounts.config.check_not_banned(ctx.accounts.joiner.key)?;
+        let joiner = ctx.accounts.joiner.key();
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &mut ctx.accounts.game_account;
+        check_joinable(game_account, &joiner)?;
+        require!(
+            ctx.accounts.join_request.bond == game_account.bond,
+            ErrorCode::BondMismatch
+        );
+        require!(
+            ctx.accounts.join_request.wager == game_account.wager,
+            ErrorCode::WagerMismatch
+        );
+        seat_joiner(game_account, joiner)?;
+        ctx.accounts.creator_stats.close_open_game();
+
+        init_player_stats(
+            &mut ctx.accounts.joiner_stats,
+            joiner,
+            ctx.bumps.joiner_stats,
+            ctx.accounts.config.season_id,
+        );
+        check_reputation(&ctx.accounts.game_account, &ctx.accounts.joiner_stats)?;
+        check_humans_only(&ctx.accounts.game_account, &ctx.accounts.joiner_stats)?;
+        ctx.accounts.joiner_stats.admit_wager(
+            ctx.accounts.game_account.wager,
+            ctx.accounts.config.daily_wager_cap,
+            Clock::get()?.unix_timestamp,
+        )?;
+        ctx.accounts.lobby.deregister(&game_key);
+
+        let wager = ctx.accounts.join_request.wager;
+        move_lamports(
+            ctx.accounts.join_request.as_ref(),
+            ctx.accounts.game_account.as_ref(),
+            wager + ctx.accounts.join_request.bond,
+        )?;
+
+        emit_lifecycle_event!(
+            ctx,
+            GameJoined {
+                game: game_key,
+                joiner,
+                wager,
+            }
+        );
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Withdraw a join request
+    // ------------------------------------
+    // Returns the escrowed wager and rent; requests that were not accepted
+    // can always be withdrawn
+    pub fn withdraw_join_request(_ctx: Context<WithdrawJoinRequest>) -> Result<()> {
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create and join a game in one transaction
+    // ------------------------------------
+    // Both players sign and commit up front, so the game starts Committed and
+    // is never listed in a lobby. The commitments are made before the game
+    // exists, so the players pick the nonce they are bound to
+    pub fn create_and_join(
+        ctx: Context<CreateAndJoin>,
+        wager: u64,
+        nonce: u64,
+        creator_move_hashed: [u8; 32],
+        joiner_move_hashed: [u8; 32],
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(!config.paused, ErrorCode::ProgramPaused);
+        config.check_not_banned(ctx.accounts.creator.key)?;
+        config.check_not_banned(ctx.accounts.joiner.key)?;
+        require!(wager >= config.min_wager, ErrorCode::WagerTooLow);
+        require!(wager <= config.max_wager, ErrorCode::WagerTooHigh);
+
+        let game_account = &mut ctx.accounts.game_account;
+        init_game(
+            game_account,
+            ctx.accounts.creator.key(),
+            wager,
+            ctx.bumps.game_account,
+        )?;
+        game_account.opponent = Some(ctx.accounts.joiner.key());
+        game_account.joined_at = game_account.created_at;
+        game_account.nonce = nonce;
+        game_account.creator_move_hashed = creator_move_hashed;
+        game_account.joiner_move_hashed = joiner_move_hashed;
+        game_account.status = GameStatus::Committed;
+        game_account.escrow = wager.checked_mul(2).ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        init_player_stats(
+            &mut ctx.accounts.creator_stats,
+            ctx.accounts.creator.key(),
+            ctx.bumps.creator_stats,
+            config.season_id,
+        );
+        init_player_stats(
+            &mut ctx.accounts.joiner_stats,
+            ctx.accounts.joiner.key(),
+            ctx.bumps.joiner_stats,
+            config.season_id,
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let cap = config.daily_wager_cap;
+        ctx.accounts.creator_stats.admit_wager(wager, cap, now)?;
+        ctx.accounts.creator_stats.record_creation(config.creation_cooldown, now)?;
+        ctx.accounts.joiner_stats.admit_wager(wager, cap, now)?;
+
+        emit_lifecycle_event!(
+            ctx,
+            GameCreated {
+                game: ctx.accounts.game_account.key(),
+                creator: ctx.accounts.creator.key(),
+                opponent: Some(ctx.accounts.joiner.key()),
+                wager,
+            }
+        );
+
+        // Transfer both wagers into the game account, each from the player
+        // or from the sponsor fronting it
+        ctx.accounts.game_account.creator_sponsor =
+            ctx.accounts.creator_sponsor.as_ref().map(|sponsor| sponsor.key());
+        ctx.accounts.game_account.joiner_sponsor =
+            ctx.accounts.joiner_sponsor.as_ref().map(|sponsor| sponsor.key());
+        if wager > 0 {
+            let funders = [
+                (&ctx.accounts.creator_sponsor, &ctx.accounts.creator),
+                (&ctx.accounts.joiner_sponsor, &ctx.accounts.joiner),
+            ];
+            for (sponsor, player) in funders {
+                let funder = match sponsor {
+                    Some(sponsor) => sponsor.to_account_info(),
+                    None => player.to_account_info(),
+                };
+                let ix = system_instruction::transfer(
+                    funder.key,
+                    &ctx.accounts.game_account.key(),
+                    wager,
+                );
+                invoke(
+                    &ix,
+                    &[
+                        funder,
+                        ctx.accounts.game_account.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                )?;
+            }
+        }
+
+        index_game(&mut ctx.accounts.game_account, &mut ctx.accounts.config)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create a matchmaking queue for a wager band (admin only)
+    // ------------------------------------
+    pub fn initialize_queue(ctx: Context<InitializeQueue>, band: u8, wager: u64) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(wager >= config.min_wager, ErrorCode::WagerTooLow);
+        require!(wager <= config.max_wager, ErrorCode::WagerTooHigh);
+
+        let queue = &mut ctx.accounts.queue;
+        queue.band = band;
+        queue.wager = wager;
+        queue.waiting = Vec::new();
+        queue.matches = 0;
+        queue.bump = ctx.bumps.queue;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Join a matchmaking queue
+    // ------------------------------------
+    pub fn join_queue(ctx: Context<JoinQueue>, _band: u8) -> Result<()> {
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        ctx.accounts.config.check_not_banned(ctx.accounts.player.key)?;
+
+        let player_key = ctx.accounts.player.key();
+        let queue = &mut ctx.accounts.queue;
+
+        require!(!queue.waiting.contains(&player_key), ErrorCode::AlreadyQueued);
+        require!(queue.waiting.len() < QUEUE_CAPACITY, ErrorCode::QueueFull);
+        queue.waiting.push(player_key);
+
+        init_player_stats(
+            &mut ctx.accounts.player_stats,
+            player_key,
+            ctx.bumps.player_stats,
+            ctx.accounts.config.season_id,
+        );
+        // Queued players can't choose their opponent, so bots stay out
+        require!(!ctx.accounts.player_stats.bot, ErrorCode::BotsNotAllowed);
+        ctx.accounts.player_stats.admit_wager(
+            ctx.accounts.queue.wager,
+            ctx.accounts.config.daily_wager_cap,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Escrow the band's wager in the queue until matched
+        let wager = ctx.accounts.queue.wager;
+        if wager > 0 {
+            let ix = system_instruction::transfer(&player_key, &ctx.accounts.queue.key(), wager);
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.player.to_account_info(),
+                    ctx.accounts.queue.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Leave a matchmaking queue
+    // ------------------------------------
+    pub fn leave_queue(ctx: Context<LeaveQueue>, _band: u8) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let queue = &mut ctx.accounts.queue;
+
+        let position = queue
+            .waiting
+            .iter()
+            .position(|waiting| *waiting == player_key)
+            .ok_or(ErrorCode::NotQueued)?;
+        queue.waiting.remove(position);
+
+        let wager = queue.wager;
+        move_lamports(
+            &queue.to_account_info(),
+            &ctx.accounts.player.to_account_info(),
+            wager,
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Match the two longest-waiting players in a queue
+    // ------------------------------------
+    // Anyone can crank this; the caller pays the new game account's rent
+    pub fn match_players(ctx: Context<MatchPlayers>, _band: u8) -> Result<()> {
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+
+        let queue = &mut ctx.accounts.queue;
+        require!(queue.waiting.len() >= 2, ErrorCode::NotEnoughQueuedPlayers);
+
+        let creator = queue.waiting.remove(0);
+        let joiner = queue.waiting.remove(0);
+        let wager = queue.wager;
+        queue.matches = queue
+            .matches
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        // The game starts out committed, with both wagers escrowed
+        let game_account = &mut ctx.accounts.game_account;
+        init_game(game_account, creator, wager, ctx.bumps.game_account)?;
+        game_account.opponent = Some(joiner);
+        game_account.joined_at = game_account.created_at;
+        game_account.status = GameStatus::Committed;
+        game_account.escrow = wager.checked_mul(2).ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        move_lamports(
+            &ctx.accounts.queue.to_account_info(),
+            &ctx.accounts.game_account.to_account_info(),
+            wager.checked_mul(2).ok_or(ErrorCode::ArithmeticOverflow)?,
+        )?;
+
+        msg!("Matched {} vs {}", creator, joiner);
+        emit_lifecycle_event!(
+            ctx,
+            GameCreated {
+                game: ctx.accounts.game_account.key(),
+                creator,
+                opponent: Some(joiner),
+                wager,
+            }
+        );
+
+        index_game(&mut ctx.accounts.game_account, &mut ctx.accounts.config)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Cancel an open game
+    // ------------------------------------
+    pub fn cancel_game(ctx: Context<CancelGame>) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &mut ctx.accounts.game_account;
+        require!(!game_account.holds_escrowed_tokens(), ErrorCode::TokensStillEscrowed);
+
+        // Refund the wager; the account itself is closed to the creator
+        let refund = game_account.escrow;
+        let sponsor = game_account.creator_sponsor.take();
+        pay_player(
+            game_account,
+            sponsor,
+            ctx.accounts.creator_sponsor.as_deref(),
+            ctx.accounts.creator.as_ref(),
+            refund,
+        )?;
+        ctx.accounts.creator_stats.close_open_game();
+        game_account.end(GameResult::Cancelled)?;
+
+        ctx.accounts.lobby.deregister(&game_key);
+
+        emit_lifecycle_event!(
+            ctx,
+            GameCancelled {
+                game: game_key,
+                creator: ctx.accounts.creator.key(),
+                refund,
+            }
+        );
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Collect an open game nobody joined in time
+    // ------------------------------------
+    // Anyone can crank it once the join deadline has passed. The creator gets
+    // their wager and the account's rent back, less GC_BOUNTY_LAMPORTS paid to
+    // the caller. Hidden games are collected too, so they stop counting
+    // against the creator's open games
+    pub fn gc_game(ctx: Context<GcGame>) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &mut ctx.accounts.game_account;
+        require!(!game_account.holds_escrowed_tokens(), ErrorCode::TokensStillEscrowed);
+        require!(
+            Clock::get()?.unix_timestamp > game_account.join_deadline,
+            ErrorCode::JoinDeadlineNotReached
+        );
+
+        let refund = game_account.escrow;
+        let sponsor = game_account.creator_sponsor.take();
+        pay_player(
+            game_account,
+            sponsor,
+            ctx.accounts.creator_sponsor.as_deref(),
+            ctx.accounts.creator.as_ref(),
+            refund,
+        )?;
+        game_account.end(GameResult::Expired)?;
+        ctx.accounts.creator_stats.close_open_game();
+
+        // The escrow is empty, so the bounty comes out of the rent the close
+        // would otherwise return to the creator
+        let game_info: &AccountInfo = game_account.as_ref();
+        let bounty = GC_BOUNTY_LAMPORTS.min(game_info.lamports());
+        move_lamports(game_info, ctx.accounts.caller.as_ref(), bounty)?;
+
+        ctx.accounts.lobby.deregister(&game_key);
+
+        emit_lifecycle_event!(
+            ctx,
+            GameCollected {
+                game: game_key,
+                creator: ctx.accounts.creator.key(),
+                refund,
+                collector: ctx.accounts.caller.key(),
+                bounty,
+            }
+        );
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Surrender a match before any reveal
+    // ------------------------------------
+    // The surrendering player gets the config's share of their stake back
+    // and the opponent takes the rest of the escrow, their own stake
+    // included. No house fee is charged; each player recovers their bond
+    pub fn surrender<'info>(ctx: Context<'_, '_, '_, 'info, Surrender<'info>>) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        let player_key = ctx.accounts.player.key();
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            game_account.creator_move.is_none() && game_account.joiner_move.is_none(),
+            ErrorCode::AlreadyRevealed
+        );
+        require!(!game_account.holds_escrowed_tokens(), ErrorCode::TokensStillEscrowed);
+
+        let refund = (game_account.wager as u128
+            * ctx.accounts.config.surrender_refund_bps as u128
+            / BPS_DENOMINATOR as u128) as u64;
+        let creator_info: &AccountInfo = ctx.accounts.creator.as_ref();
+        let joiner_info: &AccountInfo = ctx.accounts.joiner.as_ref();
+        let creator_side = (
+            game_account.creator_sponsor.take(),
+            ctx.accounts.creator_sponsor.as_deref(),
+            creator_info,
+        );
+        let joiner_side = (
+            game_account.joiner_sponsor.take(),
+            ctx.accounts.joiner_sponsor.as_deref(),
+            joiner_info,
+        );
+        let (winner, surrendering, opponent) = if player_key == game_account.creator {
+            (RPSResult::JoinerWins, creator_side, joiner_side)
+        } else {
+            (RPSResult::CreatorWins, joiner_side, creator_side)
+        };
+        let opponent_payout = game_account
+            .escrow
+            .checked_sub(refund)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let (sponsor, sponsor_info, player) = surrendering;
+        pay_player(game_account, sponsor, sponsor_info, player, refund)?;
+        let (sponsor, sponsor_info, player) = opponent;
+        pay_player(game_account, sponsor, sponsor_info, player, opponent_payout)?;
+        pay_bonds(game_account, creator_info, 1)?;
+        pay_bonds(game_account, joiner_info, 1)?;
+        game_account.bond = 0;
+        game_account.end(winner.into())?;
+        // A surrender is not a loss the insurance covers
+        settle_insurance(
+            game_account,
+            RPSResult::Tie,
+            ctx.accounts.insurance_vault.as_mut(),
+            creator_info,
+            joiner_info,
+        )?;
+        notify_callback(
+            game_account,
+            ctx.accounts.callback.as_ref(),
+            ctx.accounts.callback_program.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+
+        let season_id = ctx.accounts.config.season_id;
+        ctx.accounts.creator_stats.sync_season(season_id);
+        ctx.accounts.joiner_stats.sync_season(season_id);
+        record_result(
+            &winner,
+            ctx.accounts.game_account.wager,
+            &ctx.accounts.config,
+            &mut ctx.accounts.creator_stats,
+            &mut ctx.accounts.joiner_stats,
+        )?;
+
+        emit_lifecycle_event!(
+            ctx,
+            Surrendered {
+                game: game_key,
+                player: player_key,
+                refund,
+                opponent_payout,
+            }
+        );
+
+        // No house fee is charged on a surrender
+        return_settlement(&ctx.accounts.game_account, refund + opponent_payout, 0)
+    }
+
+    // ------------------------------------
+    // Instruction: Select a move
+    // ------------------------------------
+    pub fn select_move(
+        ctx: Context<SelectMove>,
+        hashed_move: [u8; 32], // Commitment computed client-side, see COMMITMENT_HASH_SCHEME
+    ) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        let player_key = ctx.accounts.player.key();
+
+        // Moves can only be (re)committed before both players are ready; the
+        // creator may also commit while waiting for a joiner
+        let creator_early =
+            player_key == game_account.creator && game_account.status == GameStatus::Open;
+        require!(
+            game_account.status == GameStatus::Committed || creator_early,
+            ErrorCode::GameNotCommitted
+        );
+
+        // Update the appropriate player's hashed move
+        if player_key == game_account.creator {
+            require!(
+                !game_account.has_flag(GAME_FLAG_CREATOR_READY),
+                ErrorCode::AlreadyReady
+            );
+            game_account.creator_move_hashed = hashed_move;
+        } else {
+            require!(
+                !game_account.has_flag(GAME_FLAG_JOINER_READY),
+                ErrorCode::AlreadyReady
+            );
+            game_account.joiner_move_hashed = hashed_move;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Insure a wager against a loss
+    // ------------------------------------
+    // Bought during the commit phase, before the player is ready. The
+    // premium goes to the insurance vault, which must be able to cover the
+    // refund on top of every policy still outstanding
+    pub fn insure_wager(ctx: Context<InsureWager>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(config.insurance_premium_bps > 0, ErrorCode::InsuranceDisabled);
+        let game_account = &mut ctx.accounts.game_account;
+        let wager = game_account.wager as u128;
+        let premium =
+            (wager * config.insurance_premium_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        let refund = (wager * config.insurance_refund_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        let is_creator = ctx.accounts.player.key() == game_account.creator;
+        let ready_flag = if is_creator {
+            GAME_FLAG_CREATOR_READY
+        } else {
+            GAME_FLAG_JOINER_READY
+        };
+        require!(!game_account.has_flag(ready_flag), ErrorCode::AlreadyReady);
+        let cover = if is_creator {
+            &mut game_account.creator_cover
+        } else {
+            &mut game_account.joiner_cover
+        };
+        require!(*cover == 0, ErrorCode::AlreadyInsured);
+        *cover = refund;
+
+        let ix = system_instruction::transfer(
+            ctx.accounts.player.key,
+            &ctx.accounts.insurance_vault.key(),
+            premium,
+        );
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.player.to_account_info(),
+                ctx.accounts.insurance_vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let vault_info = ctx.accounts.insurance_vault.to_account_info();
+        let rent_floor = Rent::get()?.minimum_balance(vault_info.data_len());
+        let vault = &mut ctx.accounts.insurance_vault;
+        let exposure = vault
+            .exposure
+            .checked_add(refund)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(
+            exposure <= vault_info.lamports().saturating_sub(rent_floor),
+            ErrorCode::InsuranceVaultInsolvent
+        );
+        vault.exposure = exposure;
+        vault.premiums_collected = vault
+            .premiums_collected
+            .checked_add(premium)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        msg!("Insured: {} for {}", refund, premium);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Ready up
+    // ------------------------------------
+    pub fn ready_up(ctx: Context<ReadyUp>) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        let player_key = ctx.accounts.player.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        // Only during the commit phase, like select_move; the creator may
+        // lock in early while waiting for a joiner
+        let creator_early =
+            player_key == game_account.creator && game_account.status == GameStatus::Open;
+        require!(
+            game_account.status == GameStatus::Committed || creator_early,
+            ErrorCode::GameNotCommitted
+        );
+
+        // Check if the player has selected a move
+        if player_key == game_account.creator {
+            require!(
+                !game_account.has_flag(GAME_FLAG_CREATOR_READY),
+                ErrorCode::AlreadyReady
+            );
+            require!(
+                game_account.creator_move_hashed != [0u8; 32],
+                ErrorCode::MoveNotSelected
+            );
+            game_account.charge_clock(true, now)?;
+            game_account.set_flag(GAME_FLAG_CREATOR_READY, true);
+        } else {
+            require!(
+                !game_account.has_flag(GAME_FLAG_JOINER_READY),
+                ErrorCode::AlreadyReady
+            );
+            require!(
+                game_account.joiner_move_hashed != [0u8; 32],
+                ErrorCode::MoveNotSelected
+            );
+            require!(
+                game_account.assets.iter().all(|asset| asset.joiner_deposited),
+                ErrorCode::PotDepositMissing
+            );
+            game_account.charge_clock(false, now)?;
+            game_account.set_flag(GAME_FLAG_JOINER_READY, true);
+        }
+    
+        // Check if both players are ready; the joiner only readies up once
+        // Committed, so this is the one transition into the reveal phase
+        if game_account.status == GameStatus::Committed
+            && game_account.has_flag(GAME_FLAG_CREATOR_READY | GAME_FLAG_JOINER_READY)
+        {
+            // Both commitments are locked in; move on to the reveal phase
+            game_account.status = GameStatus::Revealing;
+            game_account.phase_started_at = now;
+            game_account.reveal_slot = Clock::get()?.slot;
+            game_account.reveal_deadline = now
+                .checked_add(game_account.reveal_window())
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+    
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Register interest in a game
+    // ------------------------------------
+    // Anyone may call this; lobbies can sort games by the count
+    pub fn spectate(ctx: Context<Spectate>) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        game_account.spectators = game_account.spectators.saturating_add(1);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Send a message to the other player
+    // ------------------------------------
+    // Messages are not stored; they only go out as events
+    pub fn send_message(ctx: Context<SendMessage>, message: String) -> Result<()> {
+        require!(
+            !message.is_empty() && message.len() <= MAX_MESSAGE_LEN,
+            ErrorCode::InvalidMessage
+        );
+
+        emit!(GameMessage {
+            game: ctx.accounts.game_account.key(),
+            sender: ctx.accounts.sender.key(),
+            message,
+        });
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Reveal a move
+    // ------------------------------------
+    pub fn reveal_move<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevealMove<'info>>,
+        original_move: u8, // 0=Rock, 1=Paper, 2=Scissors
+        salt: String,
+    ) -> Result<()> {
+        let signer_key = ctx.accounts.player.key();
+        let slot = Clock::get()?.slot;
+
+        // The signer is either a player or a session key acting for one
+        let player_key = if ctx.accounts.creator_stats.session_allows(
+            &signer_key,
+            SESSION_SCOPE_REVEAL,
+            slot,
+        ) {
+            ctx.accounts.creator_stats.player
+        } else if ctx.accounts.joiner_stats.session_allows(
+            &signer_key,
+            SESSION_SCOPE_REVEAL,
+            slot,
+        ) {
+            ctx.accounts.joiner_stats.player
+        } else {
+            signer_key
+        };
+
+        apply_reveal(ctx, player_key, original_move, &salt)
+    }
+
+    // ------------------------------------
+    // Instruction: Reveal a move relayed by a third party
+    // ------------------------------------
+    // The relayer signs and pays for the transaction; the player proves the
+    // reveal with an ed25519 signature over game key || move || salt, checked
+    // by an ed25519 program instruction placed right before this one
+    pub fn relay_reveal_move<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevealMove<'info>>,
+        player: Pubkey,
+        original_move: u8,
+        salt: String,
+    ) -> Result<()> {
+        let message = [
+            ctx.accounts.game_account.key().as_ref(),
+            &[original_move],
+            salt.as_bytes(),
+        ]
+        .concat();
+        verify_ed25519_signature(&ctx.accounts.instructions, &player, &message)?;
+
+        apply_reveal(ctx, player, original_move, &salt)
+    }
+
+    // ------------------------------------
+    // Instruction: Settle a game one player failed to reveal
+    // ------------------------------------
+    // Once the reveal deadline has passed, the player who revealed takes the
+    // pot as a win plus both reveal bonds. Anyone can crank this
+    pub fn claim_reveal_timeout<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevealMove<'info>>,
+    ) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            Clock::get()?.unix_timestamp > game_account.reveal_deadline,
+            ErrorCode::RevealDeadlineNotReached
+        );
+        if game_account.tie_break_pending() {
+            // The oracle never answered, so the tie is split after all
+            let creator_move = game_account.creator_move.ok_or(ErrorCode::NothingToClaim)?;
+            let joiner_move = game_account.joiner_move.ok_or(ErrorCode::NothingToClaim)?;
+            return settle_revealed(ctx, creator_move, joiner_move, RPSResult::Tie);
+        }
+        let winner = match (game_account.creator_move, game_account.joiner_move) {
+            (Some(_), None) => RPSResult::CreatorWins,
+            (None, Some(_)) => RPSResult::JoinerWins,
+            _ => return err!(ErrorCode::NothingToClaim),
+        };
+
+        let season_id = ctx.accounts.config.season_id;
+        ctx.accounts.creator_stats.sync_season(season_id);
+        ctx.accounts.joiner_stats.sync_season(season_id);
+        record_result(
+            &winner,
+            game_account.wager,
+            &ctx.accounts.config,
+            &mut ctx.accounts.creator_stats,
+            &mut ctx.accounts.joiner_stats,
+        )?;
+        match winner {
+            RPSResult::CreatorWins => ctx.accounts.joiner_stats.record_timeout(),
+            _ => ctx.accounts.creator_stats.record_timeout(),
+        }
+
+        let creator_info: &AccountInfo = ctx.accounts.creator.as_ref();
+        let joiner_info: &AccountInfo = ctx.accounts.joiner.as_ref();
+        let winner_info = match winner {
+            RPSResult::CreatorWins => creator_info,
+            _ => joiner_info,
+        };
+        let settlement = handle_payout(
+            winner,
+            game_account,
+            &ctx.accounts.config,
+            payout_account(&ctx.accounts.creator_stats, creator_info, &ctx.accounts.creator_wsol)?,
+            payout_account(&ctx.accounts.joiner_stats, joiner_info, &ctx.accounts.joiner_wsol)?,
+            [
+                ctx.accounts.creator_sponsor.as_deref(),
+                ctx.accounts.joiner_sponsor.as_deref(),
+            ],
+            ctx.accounts.house.as_ref(),
+            ctx.remaining_accounts,
+            ctx.accounts.jackpot_vault.as_ref(),
+        )?;
+        sync_wrapped_payouts(
+            &ctx.accounts.token_program,
+            [&ctx.accounts.creator_wsol, &ctx.accounts.joiner_wsol],
+        )?;
+        record_rake(
+            settlement.house_fee,
+            &mut ctx.accounts.creator_stats,
+            &mut ctx.accounts.joiner_stats,
+        )?;
+        award_points(
+            &ctx.accounts.config,
+            game_account.wager,
+            &mut ctx.accounts.creator_stats,
+            &mut ctx.accounts.joiner_stats,
+        )?;
+        let forfeited_bond = game_account.bond;
+        pay_bonds(game_account, winner_info, 2)?;
+        game_account.bond = 0;
+        game_account.end(winner.into())?;
+        settle_insurance(
+            game_account,
+            winner,
+            ctx.accounts.insurance_vault.as_mut(),
+            creator_info,
+            joiner_info,
+        )?;
+        notify_callback(
+            game_account,
+            ctx.accounts.callback.as_ref(),
+            ctx.accounts.callback_program.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+
+        emit_lifecycle_event!(
+            ctx,
+            RevealTimedOut {
+                game: game_key,
+                result: winner,
+                forfeited_bond,
+            }
+        );
+
+        return_settlement(
+            &ctx.accounts.game_account,
+            settlement.creator_payout + settlement.joiner_payout,
+            settlement.house_fee,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Settle a match a player lost on time
+    // ------------------------------------
+    // Anyone can crank this once a player's time bank has run out while they
+    // still had to commit or reveal. If both clocks ran out, the player
+    // further over loses
+    pub fn claim_time_forfeit<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimTimeForfeit<'info>>,
+    ) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &mut ctx.accounts.game_account;
+        require!(game_account.time_bank > 0, ErrorCode::NoTimeForfeit);
+        let now = Clock::get()?.unix_timestamp;
+        let creator_left = game_account.time_left(true, now);
+        let joiner_left = game_account.time_left(false, now);
+        let winner = if creator_left <= 0 && creator_left < joiner_left {
+            RPSResult::JoinerWins
+        } else if joiner_left <= 0 && joiner_left < creator_left {
+            RPSResult::CreatorWins
+        } else {
+            return err!(ErrorCode::NoTimeForfeit);
+        };
+
+        let season_id = ctx.accounts.config.season_id;
+        ctx.accounts.creator_stats.sync_season(season_id);
+        ctx.accounts.joiner_stats.sync_season(season_id);
+        record_result(
+            &winner,
+            game_account.wager,
+            &ctx.accounts.config,
+            &mut ctx.accounts.creator_stats,
+            &mut ctx.accounts.joiner_stats,
+        )?;
+        match winner {
+            RPSResult::CreatorWins => ctx.accounts.joiner_stats.record_timeout(),
+            _ => ctx.accounts.creator_stats.record_timeout(),
+        }
+
+        let creator_info: &AccountInfo = ctx.accounts.creator.as_ref();
+        let joiner_info: &AccountInfo = ctx.accounts.joiner.as_ref();
+        let winner_info = match winner {
+            RPSResult::CreatorWins => creator_info,
+            _ => joiner_info,
+        };
+        let settlement = handle_payout(
+            winner,
+            game_account,
+            &ctx.accounts.config,
+            payout_account(&ctx.accounts.creator_stats, creator_info, &ctx.accounts.creator_wsol)?,
+            payout_account(&ctx.accounts.joiner_stats, joiner_info, &ctx.accounts.joiner_wsol)?,
+            [
+                ctx.accounts.creator_sponsor.as_deref(),
+                ctx.accounts.joiner_sponsor.as_deref(),
+            ],
+            ctx.accounts.house.as_ref(),
+            ctx.remaining_accounts,
+            ctx.accounts.jackpot_vault.as_ref(),
+        )?;
+        sync_wrapped_payouts(
+            &ctx.accounts.token_program,
+            [&ctx.accounts.creator_wsol, &ctx.accounts.joiner_wsol],
+        )?;
+        record_rake(
+            settlement.house_fee,
+            &mut ctx.accounts.creator_stats,
+            &mut ctx.accounts.joiner_stats,
+        )?;
+        award_points(
+            &ctx.accounts.config,
+            game_account.wager,
+            &mut ctx.accounts.creator_stats,
+            &mut ctx.accounts.joiner_stats,
+        )?;
+        pay_bonds(game_account, winner_info, 2)?;
+        game_account.bond = 0;
+        game_account.end(winner.into())?;
+        settle_insurance(
+            game_account,
+            winner,
+            ctx.accounts.insurance_vault.as_mut(),
+            creator_info,
+            joiner_info,
+        )?;
+        notify_callback(
+            game_account,
+            ctx.accounts.callback.as_ref(),
+            ctx.accounts.callback_program.as_ref(),
+            ctx.remaining_accounts,
+        )?;
+
+        emit_lifecycle_event!(
+            ctx,
+            TimeForfeited {
+                game: game_key,
+                result: winner,
+            }
+        );
+
+        return_settlement(
+            &ctx.accounts.game_account,
+            settlement.creator_payout + settlement.joiner_payout,
+            settlement.house_fee,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Award a tied pot with the VRF's randomness
+    // ------------------------------------
+    // Anyone can crank this once the VRF network has fulfilled the request
+    // made when the tie was revealed, passing its randomness account
+    pub fn fulfill_tie_break<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevealMove<'info>>,
+    ) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &ctx.accounts.game_account;
+        require!(game_account.tie_break_pending(), ErrorCode::NoTieBreakPending);
+        let randomness = fulfilled_randomness(
+            ctx.accounts.vrf_randomness.as_ref(),
+            &game_account.tie_break_seed,
+        )?;
+        let winner = if randomness[0] & 1 == 0 {
+            RPSResult::CreatorWins
+        } else {
+            RPSResult::JoinerWins
+        };
+        let creator_move = game_account.creator_move.ok_or(ErrorCode::NoTieBreakPending)?;
+        let joiner_move = game_account.joiner_move.ok_or(ErrorCode::NoTieBreakPending)?;
+
+        emit_lifecycle_event!(
+            ctx,
+            TieBroken {
+                game: game_key,
+                randomness,
+                result: winner,
+            }
+        );
+        settle_revealed(ctx, creator_move, joiner_move, winner)
+    }
+
+    // ------------------------------------
+    // Instruction: Preview the settlement of a game
+    // ------------------------------------
+    // Read-only; meant for simulated transactions. Moves already revealed on
+    // chain take precedence over the given ones. Jackpot hits are random and
+    // not included; neither are ties broken at random, which report no payouts
+    pub fn simulate_result(
+        ctx: Context<SimulateResult>,
+        creator_move: u8,
+        joiner_move: u8,
+    ) -> Result<SimulatedResult> {
+        let game_account = &ctx.accounts.game_account;
+        require!(
+            game_account.status == GameStatus::Committed
+                || game_account.status == GameStatus::Revealing,
+            ErrorCode::GameNotCommitted
+        );
+        game_account.check_move(creator_move)?;
+        game_account.check_move(joiner_move)?;
+
+        let mut winner = game_account.decide(
+            game_account.creator_move.unwrap_or(creator_move),
+            game_account.joiner_move.unwrap_or(joiner_move),
+        )?;
+        if let (Some(creator_stats), Some(joiner_stats)) =
+            (&ctx.accounts.creator_stats, &ctx.accounts.joiner_stats)
+        {
+            let season_id = ctx.accounts.config.season_id;
+            winner = game_account.handicap_result(
+                winner,
+                creator_stats.rating_in(season_id),
+                joiner_stats.rating_in(season_id),
+            );
+        }
+        let replay = game_account.has_flag(GAME_FLAG_REPLAY_TIES);
+        if winner == RPSResult::Tie
+            && (replay || game_account.breaks_ties_at_random(&ctx.accounts.config))
+        {
+            return Ok(SimulatedResult {
+                result: winner,
+                replay,
+                house_fee: 0,
+                jackpot_cut: 0,
+                creator_payout: 0,
+                joiner_payout: 0,
+            });
+        }
+        settlement_amounts(game_account, &ctx.accounts.config, winner)
+    }
+
+    // ------------------------------------
+    // Instruction: Return a game as a stable view
+    // ------------------------------------
+    // Lets other programs read a game through CPI return data without
+    // depending on the account layout, which may change between versions
+    pub fn get_game(ctx: Context<GetGame>) -> Result<GameView> {
+        let game_account = &ctx.accounts.game_account;
+        Ok(GameView {
+            view_version: GAME_VIEW_VERSION,
+            game: game_account.key(),
+            creator: game_account.creator,
+            opponent: game_account.opponent,
+            wager: game_account.wager,
+            escrow: game_account.escrow,
+            status: game_account.status.clone(),
+            creator_move: game_account.creator_move,
+            joiner_move: game_account.joiner_move,
+            creator_ready: game_account.has_flag(GAME_FLAG_CREATOR_READY),
+            joiner_ready: game_account.has_flag(GAME_FLAG_JOINER_READY),
+            created_at: game_account.created_at,
+            join_deadline: game_account.join_deadline,
+            reveal_deadline: game_account.reveal_deadline,
+            league: game_account.league,
+            winner: game_account.winner,
+            result: game_account.result,
+        })
+    }
+
+    // ------------------------------------
+    // Instruction: Offer a double-or-nothing rematch
+    // ------------------------------------
+    // The loser of a settled rollover game matches the winner's held payout
+    // in the same game account; the winner accepts with the payout as their
+    // stake
+    pub fn propose_rematch(ctx: Context<Rematch>) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            game_account.status == GameStatus::Ended,
+            ErrorCode::GameNotEnded
+        );
+        require!(
+            !game_account.has_flag(GAME_FLAG_REMATCH_OFFERED),
+            ErrorCode::RematchAlreadyOffered
+        );
+        require!(!game_account.holds_escrowed_tokens(), ErrorCode::TokensStillEscrowed);
+        require!(
+            settled_loser(game_account) == Some(player_key),
+            ErrorCode::NotRematchLoser
+        );
+
+        // Only the winner's held payout is left in escrow
+        let stake = game_account.escrow;
+        require!(
+            game_account.has_flag(GAME_FLAG_ROLLOVER) && stake > 0,
+            ErrorCode::NoRolloverPot
+        );
+        require!(
+            stake <= ctx.accounts.config.max_wager,
+            ErrorCode::WagerTooHigh
+        );
+        game_account.set_flag(GAME_FLAG_REMATCH_OFFERED, true);
+        game_account.escrow = game_account
+            .escrow
+            .checked_add(stake)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        deposit_stake(&ctx, stake)
+    }
+
+    // ------------------------------------
+    // Instruction: Accept a double-or-nothing rematch
+    // ------------------------------------
+    pub fn accept_rematch(ctx: Context<Rematch>) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            game_account.has_flag(GAME_FLAG_REMATCH_OFFERED),
+            ErrorCode::NoRematchOffered
+        );
+        let loser = settled_loser(game_account).ok_or(ErrorCode::NoRematchOffered)?;
+        require!(
+            game_account.status == GameStatus::Ended,
+            ErrorCode::GameNotEnded
+        );
+        require!(
+            game_account.is_player(&player_key),
+            ErrorCode::NotAPlayer
+        );
+        require!(player_key != loser, ErrorCode::OwnRematchOffer);
+
+        // Reset the game for a fresh round; the escrow already holds the
+        // winner's payout and the loser's matching stake
+        game_account.wager = game_account.escrow / 2;
+        game_account.reset_round()?;
+        game_account.sudden_death_rounds = 0;
+        game_account.creator_time_left = game_account.time_bank;
+        game_account.joiner_time_left = game_account.time_bank;
+        game_account.set_flag(GAME_FLAG_REMATCH_OFFERED, false);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Withdraw an unaccepted rematch offer
+    // ------------------------------------
+    pub fn withdraw_rematch(ctx: Context<Rematch>) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            game_account.status == GameStatus::Ended,
+            ErrorCode::GameNotEnded
+        );
+        require!(
+            game_account.has_flag(GAME_FLAG_REMATCH_OFFERED)
+                && settled_loser(game_account) == Some(ctx.accounts.player.key()),
+            ErrorCode::NoRematchOffered
+        );
+        game_account.set_flag(GAME_FLAG_REMATCH_OFFERED, false);
+
+        // The stake matched the held payout, so it is half the escrow
+        let refund = game_account.escrow / 2;
+        transfer_from_escrow(game_account, &ctx.accounts.player.to_account_info(), refund)
+    }
+
+    // ------------------------------------
+    // Instruction: Collect a payout held for a rematch
+    // ------------------------------------
+    // Declines a pending rematch offer, refunding the loser's stake
+    pub fn claim_rollover(ctx: Context<ClaimRollover>) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        if game_account.has_flag(GAME_FLAG_REMATCH_OFFERED) {
+            game_account.set_flag(GAME_FLAG_REMATCH_OFFERED, false);
+            let refund = game_account.escrow / 2;
+            transfer_from_escrow(game_account, &ctx.accounts.loser.to_account_info(), refund)?;
+        }
+        let payout = game_account.escrow;
+        require!(payout > 0, ErrorCode::NothingToClaim);
+        transfer_from_escrow(game_account, &ctx.accounts.winner.to_account_info(), payout)
+    }
+
+    // ------------------------------------
+    // Instruction: Stake an NFT as (part of) a player's wager
+    // ------------------------------------
+    // The creator stakes while the game is open, the joiner before readying
+    // up; the house fee for NFTs is charged in lamports up front
+    pub fn stake_nft(ctx: Context<StakeNft>) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let mint = ctx.accounts.mint.key();
+        let game_account = &mut ctx.accounts.game_account;
+
+        if player_key == game_account.creator {
+            require!(
+                game_account.status == GameStatus::Open,
+                ErrorCode::GameNotOpen
+            );
+            require!(game_account.creator_nft.is_none(), ErrorCode::NftAlreadyStaked);
+            game_account.creator_nft = Some(mint);
+        } else if Some(player_key) == game_account.opponent {
+            require!(
+                game_account.status == GameStatus::Committed
+                    && !game_account.has_flag(GAME_FLAG_JOINER_READY),
+                ErrorCode::GameNotCommitted
+            );
+            require!(game_account.joiner_nft.is_none(), ErrorCode::NftAlreadyStaked);
+            game_account.joiner_nft = Some(mint);
+        } else {
+            return err!(ErrorCode::NotAPlayer);
+        }
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.player_token.to_account_info(),
+                    to: ctx.accounts.escrow_token.to_account_info(),
+                    authority: ctx.accounts.player.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        let nft_fee = ctx.accounts.config.nft_fee;
+        if nft_fee > 0 {
+            let ix = system_instruction::transfer(&player_key, &ctx.accounts.house.key(), nft_fee);
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.player.to_account_info(),
+                    ctx.accounts.house.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Release a staked NFT
+    // ------------------------------------
+    // Settled games release to the winner (or back to the staker on a tie);
+    // unsettled games release back to the staker while the game is open,
+    // flagged, or stale. The recipient signs and recovers the escrow rent
+    pub fn release_nft(ctx: Context<ReleaseNft>) -> Result<()> {
+        let mint = ctx.accounts.mint.key();
+        let game_account = &mut ctx.accounts.game_account;
+
+        let staker = if game_account.creator_nft == Some(mint) {
+            game_account.creator_nft = None;
+            game_account.creator
+        } else if game_account.joiner_nft == Some(mint) {
+            game_account.joiner_nft = None;
+            game_account.opponent.ok_or(ErrorCode::NftNotStaked)?
+        } else {
+            return err!(ErrorCode::NftNotStaked);
+        };
+
+        let recipient = match game_account.status {
+            GameStatus::Ended => match settled_loser(game_account) {
+                Some(loser) if loser == game_account.creator => {
+                    game_account.opponent.ok_or(ErrorCode::NftNotStaked)?
+                }
+                Some(_) => game_account.creator,
+                None => staker,
+            },
+            GameStatus::Open => staker,
+            GameStatus::Revealing
+                if game_account.creator_move.is_none()
+                    && game_account.joiner_move.is_none()
+                    && Clock::get()?.unix_timestamp > game_account.reveal_deadline =>
+            {
+                staker
+            }
+            _ if game_account.has_flag(GAME_FLAG_FLAGGED) => staker,
+            _ => return err!(ErrorCode::EscrowLocked),
+        };
+        require!(
+            ctx.accounts.recipient.key() == recipient,
+            ErrorCode::WrongRecipient
+        );
+
+        let signer_seeds: &[&[&[u8]]] = &[&[NFT_AUTHORITY_SEED, &[ctx.bumps.nft_authority]]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.escrow_token.to_account_info(),
+                    to: ctx.accounts.recipient_token.to_account_info(),
+                    authority: ctx.accounts.nft_authority.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.escrow_token.to_account_info(),
+                destination: ctx.accounts.recipient.to_account_info(),
+                authority: ctx.accounts.nft_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Add an SPL token to the pot
+    // ------------------------------------
+    // The creator deposits `amount` while the game is open; the joiner must
+    // match it before readying up
+    pub fn add_pot_asset(ctx: Context<AddPotAsset>, amount: u64) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            game_account.assets.len() < MAX_POT_ASSETS,
+            ErrorCode::TooManyPotAssets
+        );
+        require!(amount > 0, ErrorCode::InvalidPotAmount);
+
+        // Space for pot assets is only paid for by games that use them
+        grow_account(
+            &game_account.to_account_info(),
+            &ctx.accounts.creator.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            8 + GameState::MAX_SIZE + PotAsset::SIZE * (game_account.assets.len() + 1),
+        )?;
+        game_account.assets.push(PotAsset {
+            mint: ctx.accounts.mint.key(),
+            amount,
+            joiner_deposited: false,
+            settled: false,
+        });
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.creator_token.to_account_info(),
+                    to: ctx.accounts.escrow_token.to_account_info(),
+                    authority: ctx.accounts.creator.to_account_info(),
+                },
+            ),
+            amount,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Match a pot asset as the joiner
+    // ------------------------------------
+    pub fn deposit_pot_asset(ctx: Context<DepositPotAsset>, index: u8) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        let asset = game_account
+            .assets
+            .get_mut(index as usize)
+            .ok_or(ErrorCode::InvalidPotAsset)?;
+        require!(
+            asset.mint == ctx.accounts.escrow_token.mint,
+            ErrorCode::InvalidPotAsset
+        );
+        require!(!asset.joiner_deposited, ErrorCode::PotAlreadyDeposited);
+        asset.joiner_deposited = true;
+        let amount = asset.amount;
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.joiner_token.to_account_info(),
+                    to: ctx.accounts.escrow_token.to_account_info(),
+                    authority: ctx.accounts.joiner.to_account_info(),
+                },
+            ),
+            amount,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Settle one pot asset
+    // ------------------------------------
+    // Settled games pay the asset out like the SOL pot (house fee per the
+    // game's fee mode, then winner takes all or a tie splits); open, stale or flagged games
+    // refund each deposit. Anyone can crank this
+    pub fn settle_pot_asset(ctx: Context<SettlePotAsset>, index: u8) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        let refundable = match game_account.status {
+            GameStatus::Ended => false,
+            GameStatus::Open => true,
+            GameStatus::Revealing
+                if game_account.creator_move.is_none()
+                    && game_account.joiner_move.is_none()
+                    && Clock::get()?.unix_timestamp > game_account.reveal_deadline =>
+            {
+                true
+            }
+            _ if game_account.has_flag(GAME_FLAG_FLAGGED) => true,
+            _ => return err!(ErrorCode::EscrowLocked),
+        };
+        let loser = settled_loser(game_account);
+        let wager = game_account.wager;
+        let creator = game_account.creator;
+        let fee_mode = game_account.fee_mode;
+
+        let asset = game_account
+            .assets
+            .get_mut(index as usize)
+            .ok_or(ErrorCode::InvalidPotAsset)?;
+        require!(
+            asset.mint == ctx.accounts.escrow_token.mint,
+            ErrorCode::InvalidPotAsset
+        );
+        require!(!asset.settled, ErrorCode::PotAlreadySettled);
+        asset.settled = true;
+
+        let joiner_deposit = if asset.joiner_deposited { asset.amount } else { 0 };
+        let (fee, creator_share, joiner_share) = if refundable {
+            (0, asset.amount, joiner_deposit)
+        } else {
+            let pot = asset
+                .amount
+                .checked_add(joiner_deposit)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+            let fee_bps = ctx
+                .accounts
+                .config
+                .fee_bps_for(wager, Clock::get()?.unix_timestamp);
+            // In winner-profit mode only the loser's deposit is raked
+            let fee_base = match (fee_mode, loser) {
+                (FeeMode::Pot, _) => pot,
+                (FeeMode::WinnerProfit, None) => 0,
+                (FeeMode::WinnerProfit, Some(loser)) if loser == creator => asset.amount,
+                (FeeMode::WinnerProfit, Some(_)) => joiner_deposit,
+            };
+            let fee = (fee_base as u128 * fee_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+            let payout = pot - fee;
+            match loser {
+                Some(loser) if loser == creator => (fee, 0, payout),
+                Some(_) => (fee, payout, 0),
+                None => (fee, payout - payout / 2, payout / 2),
+            }
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[NFT_AUTHORITY_SEED, &[ctx.bumps.token_authority]]];
+        let payouts = [
+            (&ctx.accounts.house_token, fee),
+            (&ctx.accounts.creator_token, creator_share),
+            (&ctx.accounts.joiner_token, joiner_share),
+        ];
+        for (destination, amount) in payouts {
+            if amount == 0 {
+                continue;
+            }
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.escrow_token.to_account_info(),
+                        to: destination.to_account_info(),
+                        authority: ctx.accounts.token_authority.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+            )?;
+        }
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.escrow_token.to_account_info(),
+                destination: ctx.accounts.creator.to_account_info(),
+                authority: ctx.accounts.token_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Refund a game nobody revealed
+    // ------------------------------------
+    pub fn refund_stale_game(ctx: Context<RefundStaleGame>) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+
+        require!(!game_account.holds_escrowed_tokens(), ErrorCode::TokensStillEscrowed);
+        require!(
+            Clock::get()?.unix_timestamp > game_account.reveal_deadline,
+            ErrorCode::RevealDeadlineNotReached
+        );
+
+        // Return each wager and bond; the account itself is closed to the
+        // creator
+        let wager = game_account.wager;
+        let sponsor = game_account.joiner_sponsor.take();
+        pay_player(
+            game_account,
+            sponsor,
+            ctx.accounts.joiner_sponsor.as_deref(),
+            ctx.accounts.joiner.as_ref(),
+            wager,
+        )?;
+        pay_bonds(game_account, ctx.accounts.joiner.as_ref(), 1)?;
+        let creator_refund = game_account.escrow;
+        let sponsor = game_account.creator_sponsor.take();
+        pay_player(
+            game_account,
+            sponsor,
+            ctx.accounts.creator_sponsor.as_deref(),
+            ctx.accounts.creator.as_ref(),
+            creator_refund,
+        )?;
+        game_account.end(GameResult::Refunded)?;
+        settle_insurance(
+            game_account,
+            RPSResult::Tie,
+            ctx.accounts.insurance_vault.as_mut(),
+            ctx.accounts.creator.as_ref(),
+            ctx.accounts.joiner.as_ref(),
+        )?;
+
+        emit_lifecycle_event!(
+            ctx,
+            StaleGameRefunded {
+                game: ctx.accounts.game_account.key(),
+                creator_refund,
+                joiner_refund: wager,
+            }
+        );
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Flag a stuck game for emergency refund (admin only)
+    // ------------------------------------
+    pub fn flag_game(ctx: Context<FlagGame>, flagged: bool) -> Result<()> {
+        let game_account = &mut ctx.accounts.game_account;
+        game_account.set_flag(GAME_FLAG_FLAGGED, flagged);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Refund deposits of a flagged game (admin only)
+    // ------------------------------------
+    pub fn emergency_refund(ctx: Context<EmergencyRefund>) -> Result<()> {
+        let game_key = ctx.accounts.game_account.key();
+        let game_account = &mut ctx.accounts.game_account;
+
+        require!(!game_account.holds_escrowed_tokens(), ErrorCode::TokensStillEscrowed);
+        ctx.accounts.lobby.deregister(&game_key);
+
+        // Each player gets back exactly what they deposited
+        let mut joiner_refund = 0;
+        if let Some(opponent) = game_account.opponent {
+            let joiner = ctx
+                .accounts
+                .joiner
+                .as_ref()
+                .ok_or(ErrorCode::WrongOpponent)?;
+            require_keys_eq!(joiner.key(), opponent, ErrorCode::WrongOpponent);
+
+            joiner_refund = game_account.wager;
+            let sponsor = game_account.joiner_sponsor.take();
+            pay_player(
+                game_account,
+                sponsor,
+                ctx.accounts.joiner_sponsor.as_deref(),
+                joiner,
+                joiner_refund,
+            )?;
+            pay_bonds(game_account, joiner, 1)?;
+        }
+        let creator_refund = game_account.wager;
+        let sponsor = game_account.creator_sponsor.take();
+        pay_player(
+            game_account,
+            sponsor,
+            ctx.accounts.creator_sponsor.as_deref(),
+            ctx.accounts.creator.as_ref(),
+            creator_refund,
+        )?;
+        game_account.end(GameResult::Refunded)?;
+        // Policies are only sold once both players are seated
+        if let Some(joiner) = ctx.accounts.joiner.as_ref() {
+            settle_insurance(
+                game_account,
+                RPSResult::Tie,
+                ctx.accounts.insurance_vault.as_mut(),
+                ctx.accounts.creator.as_ref(),
+                joiner,
+            )?;
+        }
+
+        let (creator, joiner) = (game_account.creator, game_account.opponent);
+        emit_lifecycle_event!(
+            ctx,
+            EmergencyRefunded {
+                game: game_key,
+                admin: ctx.accounts.admin.key(),
+                creator,
+                creator_refund,
+                joiner,
+                joiner_refund,
+            }
+        );
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Mint a compressed NFT badge for a milestone
+    // ------------------------------------
+    pub fn mint_badge(ctx: Context<MintBadge>, badge: Badge) -> Result<()> {
+        let player_stats = &mut ctx.accounts.player_stats;
+
+        require!(badge.is_earned(player_stats), ErrorCode::BadgeNotEarned);
+        require!(
+            player_stats.badges_minted & badge.flag() == 0,
+            ErrorCode::BadgeAlreadyMinted
+        );
+        player_stats.badges_minted |= badge.flag();
+
+        let metadata = MetadataArgs {
+            name: badge.name().to_string(),
+            symbol: "RPS".to_string(),
+            uri: format!("{}{}.json", ctx.accounts.config.badge_uri_prefix, badge.slug()),
+            seller_fee_basis_points: 0,
+            primary_sale_happened: false,
+            is_mutable: false,
+            edition_nonce: None,
+            token_standard: Some(TokenStandard::NonFungible),
+            collection: None,
+            uses: None,
+            token_program_version: TokenProgramVersion::Original,
+            creators: vec![],
+        };
+
+        // The badge authority PDA signs as the tree creator or delegate
+        let bubblegum_program = ctx.accounts.bubblegum_program.to_account_info();
+        let tree_config = ctx.accounts.tree_config.to_account_info();
+        let player = ctx.accounts.player.to_account_info();
+        let merkle_tree = ctx.accounts.merkle_tree.to_account_info();
+        let badge_authority = ctx.accounts.badge_authority.to_account_info();
+        let log_wrapper = ctx.accounts.log_wrapper.to_account_info();
+        let compression_program = ctx.accounts.compression_program.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        MintV1Cpi::new(
+            &bubblegum_program,
+            MintV1CpiAccounts {
+                tree_config: &tree_config,
+                leaf_owner: &player,
+                leaf_delegate: &player,
+                merkle_tree: &merkle_tree,
+                payer: &player,
+                tree_creator_or_delegate: &badge_authority,
+                log_wrapper: &log_wrapper,
+                compression_program: &compression_program,
+                system_program: &system_program,
+            },
+            MintV1InstructionArgs { metadata },
+        )
+        .invoke_signed(&[&[BADGE_AUTHORITY_SEED, &[ctx.bumps.badge_authority]]])?;
+
+        msg!("Minted badge: {}", badge.name());
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Claim the reward attached to an achievement
+    // ------------------------------------
+    pub fn claim_achievement_reward(
+        ctx: Context<ClaimAchievementReward>,
+        achievement: Achievement,
+    ) -> Result<()> {
+        let player_stats = &mut ctx.accounts.player_stats;
+
+        require!(
+            player_stats.achievements & achievement.flag() != 0,
+            ErrorCode::AchievementLocked
+        );
+        require!(
+            player_stats.achievements_claimed & achievement.flag() == 0,
+            ErrorCode::AchievementAlreadyClaimed
+        );
+        player_stats.achievements_claimed |= achievement.flag();
+
+        // Pay the reward out of the vault, which signs as a PDA
+        let reward = ctx.accounts.config.achievement_rewards[achievement as usize];
+        if reward > 0 {
+            let ix = system_instruction::transfer(
+                &ctx.accounts.achievement_vault.key(),
+                &ctx.accounts.player.key(),
+                reward,
+            );
+            invoke_signed(
+                &ix,
+                &[
+                    ctx.accounts.achievement_vault.to_account_info(),
+                    ctx.accounts.player.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[&[ACHIEVEMENT_VAULT_SEED, &[ctx.bumps.achievement_vault]]],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create an elimination game for 3-8 players
+    // ------------------------------------
+    pub fn create_multi_game(ctx: Context<CreateMultiGame>, wager: u64, seats: u8) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(!config.paused, ErrorCode::ProgramPaused);
+        config.check_not_banned(ctx.accounts.creator.key)?;
+        require!(wager >= config.min_wager, ErrorCode::WagerTooLow);
+        require!(wager <= config.max_wager, ErrorCode::WagerTooHigh);
+        require!(
+            (MIN_MULTI_PLAYERS..=MAX_MULTI_PLAYERS).contains(&seats),
+            ErrorCode::InvalidSeatCount
+        );
+
+        {
+            // Zero-copy accounts start zeroed, so only non-zero fields are set
+            let multi_game = &mut ctx.accounts.multi_game.load_init()?;
+            multi_game.creator = ctx.accounts.creator.key();
+            multi_game.wager = wager;
+            multi_game.seats = seats;
+            multi_game.set_status(GameStatus::Open);
+            multi_game.bump = ctx.bumps.multi_game;
+            take_seat(multi_game, ctx.accounts.creator.key())?;
+        }
+
+        escrow_seat_wager(
+            &ctx.accounts.multi_game,
+            &ctx.accounts.creator,
+            &ctx.accounts.system_program,
+            wager,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Take a seat in an elimination game
+    // ------------------------------------
+    // The game moves to the commit phase once every seat is taken
+    pub fn join_multi_game(ctx: Context<JoinMultiGame>) -> Result<()> {
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        ctx.accounts.config.check_not_banned(ctx.accounts.player.key)?;
+
+        let wager = {
+            let multi_game = &mut ctx.accounts.multi_game.load_mut()?;
+            take_seat(multi_game, ctx.accounts.player.key())?;
+            multi_game.wager
+        };
+
+        escrow_seat_wager(
+            &ctx.accounts.multi_game,
+            &ctx.accounts.player,
+            &ctx.accounts.system_program,
+            wager,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Commit a move for the current round
+    // ------------------------------------
+    // Commitments cover multi_game key || player || round (u32 LE) || move ||
+    // salt; the round moves to reveals once every remaining player committed
+    pub fn commit_multi_move(ctx: Context<PlayMultiMove>, hashed_move: [u8; 32]) -> Result<()> {
+        let multi_game = &mut ctx.accounts.multi_game.load_mut()?;
+        require!(
+            multi_game.status()? == GameStatus::Committed,
+            ErrorCode::GameNotCommitted
+        );
+
+        let seat = multi_game.seat_mut(&ctx.accounts.player.key())?;
+        seat.commitment = hashed_move;
+
+        if multi_game
+            .seated()
+            .iter()
+            .all(|seat| seat.is_eliminated() || seat.commitment != [0u8; 32])
+        {
+            multi_game.set_status(GameStatus::Revealing);
+            multi_game.reveal_deadline = Clock::get()?
+                .unix_timestamp
+                .checked_add(REVEAL_WINDOW_SECONDS)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Reveal a move for the current round
+    // ------------------------------------
+    // The last reveal resolves the round: if exactly two moves were thrown,
+    // everyone who threw the losing one is eliminated, otherwise the round
+    // is replayed
+    pub fn reveal_multi_move(
+        ctx: Context<PlayMultiMove>,
+        original_move: u8,
+        salt: String,
+    ) -> Result<()> {
+        let game_key = ctx.accounts.multi_game.key();
+        let player_key = ctx.accounts.player.key();
+        let multi_game = &mut ctx.accounts.multi_game.load_mut()?;
+        require!(
+            multi_game.status()? == GameStatus::Revealing,
+            ErrorCode::NotRevealPhase
+        );
+        require!(original_move <= 2, ErrorCode::InvalidMove);
+
+        let round = multi_game.round;
+        let seat = multi_game.seat_mut(&player_key)?;
+        require!(seat.revealed().is_none(), ErrorCode::AlreadyRevealed);
+        let hashed_move = hashv(&[
+            game_key.as_ref(),
+            player_key.as_ref(),
+            &round.to_le_bytes(),
+            &[original_move],
+            salt.as_bytes(),
+        ])
+        .to_bytes();
+        require!(seat.commitment == hashed_move, ErrorCode::CommitmentMismatch);
+        seat.revealed = original_move;
+
+        if multi_game
+            .seated()
+            .iter()
+            .any(|seat| !seat.is_eliminated() && seat.revealed().is_none())
+        {
+            return Ok(());
+        }
+
+        eliminate_round_losers(multi_game.seated_mut());
+        let remaining: Vec<Pubkey> = multi_game
+            .seated()
+            .iter()
+            .filter(|seat| !seat.is_eliminated())
+            .map(|seat| seat.player)
+            .collect();
+        match remaining[..] {
+            [winner] => {
+                multi_game.winner = winner;
+                multi_game.set_status(GameStatus::Ended);
+                msg!("Winner: {}", winner);
+            }
+            _ => {
+                // Start the next round with fresh commitments
+                multi_game.round = multi_game
+                    .round
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                for seat in multi_game.seated_mut() {
+                    seat.commitment = [0u8; 32];
+                    seat.revealed = NO_MOVE;
+                }
+                multi_game.set_status(GameStatus::Committed);
+            }
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Claim the pot of a finished elimination game
+    // ------------------------------------
+    pub fn claim_multi_pot(ctx: Context<ClaimMultiPot>) -> Result<()> {
+        let fee = {
+            let multi_game = &mut ctx.accounts.multi_game.load_mut()?;
+            require!(
+                multi_game.status()? == GameStatus::Ended,
+                ErrorCode::GameNotEnded
+            );
+
+            // The house fee comes off the top; the account closes to the winner
+            let fee_bps = ctx
+                .accounts
+                .config
+                .fee_bps_for(multi_game.wager, Clock::get()?.unix_timestamp);
+            let fee = multi_game
+                .escrow
+                .checked_mul(fee_bps as u64)
+                .ok_or(ErrorCode::ArithmeticOverflow)?
+                / BPS_DENOMINATOR;
+            multi_game.escrow = 0;
+            fee
+        };
+        move_lamports(
+            &ctx.accounts.multi_game.to_account_info(),
+            &ctx.accounts.house.to_account_info(),
+            fee,
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create a round-robin league
+    // ------------------------------------
+    // Every rostered player plays every other once between `starts_at` and
+    // `ends_at`; entry fees form the prize pool. Players enter during the
+    // registration window, which must close by the start
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_league(
+        ctx: Context<CreateLeague>,
+        league_id: u64,
+        entry_fee: u64,
+        registration_opens_at: i64,
+        registration_closes_at: i64,
+        starts_at: i64,
+        ends_at: i64,
+        roster: Vec<Pubkey>,
+    ) -> Result<()> {
+        require!(
+            (2..=MAX_LEAGUE_PLAYERS).contains(&roster.len()),
+            ErrorCode::InvalidLeagueRoster
+        );
+        require!(
+            registration_opens_at < registration_closes_at
+                && registration_closes_at <= starts_at
+                && starts_at < ends_at,
+            ErrorCode::InvalidLeagueWindow
+        );
+        for (i, player) in roster.iter().enumerate() {
+            require!(
+                !roster[..i].contains(player),
+                ErrorCode::InvalidLeagueRoster
+            );
+        }
+
+        let league = &mut ctx.accounts.league.load_init()?;
+        league.organizer = ctx.accounts.organizer.key();
+        league.league_id = league_id;
+        league.entry_fee = entry_fee;
+        league.registration_opens_at = registration_opens_at;
+        league.registration_closes_at = registration_closes_at;
+        league.starts_at = starts_at;
+        league.ends_at = ends_at;
+        for (standing, player) in league.standings.iter_mut().zip(roster.iter()) {
+            standing.player = *player;
+        }
+        league.roster_len = roster.len() as u8;
+        league.bump = ctx.bumps.league;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Pay the entry fee for a league
+    // ------------------------------------
+    pub fn enter_league(ctx: Context<EnterLeague>) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let entry_fee = {
+            let league = &mut ctx.accounts.league.load_mut()?;
+            require!(league.finalized == 0, ErrorCode::LeagueFinalized);
+            let now = Clock::get()?.unix_timestamp;
+            require!(
+                (league.registration_opens_at..league.registration_closes_at).contains(&now),
+                ErrorCode::RegistrationClosed
+            );
+
+            let index = league.position(&player_key)?;
+            require!(
+                league.standings[index].entered == 0,
+                ErrorCode::AlreadyEntered
+            );
+            league.standings[index].entered = 1;
+            league.prize_pool = league
+                .prize_pool
+                .checked_add(league.entry_fee)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+            league.entry_fee
+        };
+
+        if entry_fee > 0 {
+            let ix =
+                system_instruction::transfer(&player_key, &ctx.accounts.league.key(), entry_fee);
+            invoke(
+                &ix,
+                &[
+                    ctx.accounts.player.to_account_info(),
+                    ctx.accounts.league.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Seed the league by rating once it has started
+    // ------------------------------------
+    // Permissionless crank that must run before any fixture is scheduled.
+    // The stats of every rostered player follow as remaining accounts in
+    // roster order; the roster is reordered best rating first, keeping
+    // registration order among equal ratings. Roster order breaks ties in
+    // the final standings
+    pub fn seed_league(ctx: Context<SeedLeague>) -> Result<()> {
+        let league = &mut ctx.accounts.league.load_mut()?;
+        require!(
+            Clock::get()?.unix_timestamp >= league.starts_at,
+            ErrorCode::LeagueNotActive
+        );
+        require!(league.seeded == 0, ErrorCode::LeagueAlreadySeeded);
+        require!(
+            ctx.remaining_accounts.len() == league.roster().len(),
+            ErrorCode::NotInLeague
+        );
+
+        let mut seeds = Vec::with_capacity(league.roster().len());
+        for (standing, stats_info) in league.roster().iter().zip(ctx.remaining_accounts) {
+            require!(stats_info.owner == &ID, ErrorCode::NotInLeague);
+            let stats = PlayerStats::try_deserialize(&mut &stats_info.try_borrow_data()?[..])?;
+            require_keys_eq!(stats.player, standing.player, ErrorCode::NotInLeague);
+            seeds.push((stats.rating, *standing));
+        }
+        seeds.sort_by_key(|(rating, _)| std::cmp::Reverse(*rating));
+        for (slot, (_, standing)) in league.standings.iter_mut().zip(seeds) {
+            *slot = standing;
+        }
+        league.seeded = 1;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Schedule an open game as a league fixture
+    // ------------------------------------
+    // Each pairing can be scheduled once; the seat is reserved for the
+    // rostered opponent
+    pub fn schedule_league_game(ctx: Context<ScheduleLeagueGame>, opponent: Pubkey) -> Result<()> {
+        let league_key = ctx.accounts.league.key();
+        let league = &mut ctx.accounts.league.load_mut()?;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= league.starts_at && now <= league.ends_at,
+            ErrorCode::LeagueNotActive
+        );
+        require!(league.seeded == 1, ErrorCode::LeagueNotSeeded);
+
+        let game_account = &mut ctx.accounts.game_account;
+        require!(
+            game_account.status == GameStatus::Open,
+            ErrorCode::GameNotOpen
+        );
+
+        let creator_index = league.position(&game_account.creator)?;
+        let opponent_index = league.position(&opponent)?;
+        require!(
+            league.standings[creator_index].entered == 1
+                && league.standings[opponent_index].entered == 1,
+            ErrorCode::NotEntered
+        );
+        let fixture = league_fixture_bit(creator_index, opponent_index)?;
+        require!(league.scheduled & fixture == 0, ErrorCode::FixtureAlreadyScheduled);
+        league.scheduled |= fixture;
+
+        game_account.league = Some(league_key);
+        game_account.reserved_for = Some(opponent);
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Record a finished league game in the standings
+    // ------------------------------------
+    // Anyone can crank this once the game has ended
+    pub fn report_league_result(ctx: Context<ReportLeagueResult>) -> Result<()> {
+        let game_account = &ctx.accounts.game_account;
+        let league_key = ctx.accounts.league.key();
+        let league = &mut ctx.accounts.league.load_mut()?;
+        require!(league.finalized == 0, ErrorCode::LeagueFinalized);
+        require!(
+            game_account.league == Some(league_key),
+            ErrorCode::NotLeagueGame
+        );
+        require!(
+            game_account.status == GameStatus::Ended,
+            ErrorCode::GameNotEnded
+        );
+
+        let (Some(opponent), Some(creator_move), Some(joiner_move)) = (
+            game_account.opponent,
+            game_account.creator_move,
+            game_account.joiner_move,
+        ) else {
+            return err!(ErrorCode::NotLeagueGame);
+        };
+        let creator_index = league.position(&game_account.creator)?;
+        let opponent_index = league.position(&opponent)?;
+        let fixture = league_fixture_bit(creator_index, opponent_index)?;
+        require!(league.scheduled & fixture != 0, ErrorCode::NotLeagueGame);
+        require!(league.played & fixture == 0, ErrorCode::FixtureAlreadyPlayed);
+        league.played |= fixture;
+
+        let (creator_outcome, opponent_outcome) =
+            game_account.decide(creator_move, joiner_move)?.outcomes();
+        league.standings[creator_index].record(creator_outcome)?;
+        league.standings[opponent_index].record(opponent_outcome)?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Close the league once its window has passed
+    // ------------------------------------
+    pub fn finalize_league(ctx: Context<FinalizeLeague>) -> Result<()> {
+        let league = &mut ctx.accounts.league.load_mut()?;
+        require!(
+            Clock::get()?.unix_timestamp > league.ends_at,
+            ErrorCode::LeagueNotOver
+        );
+        require!(league.finalized == 0, ErrorCode::LeagueFinalized);
+        league.finalized = 1;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Claim a league prize
+    // ------------------------------------
+    // See League::prize for the split
+    pub fn claim_league_prize(ctx: Context<ClaimLeaguePrize>) -> Result<()> {
+        let player_key = ctx.accounts.player.key();
+        let prize = {
+            let league = &mut ctx.accounts.league.load_mut()?;
+            require!(league.finalized == 1, ErrorCode::LeagueNotOver);
+
+            let index = league.position(&player_key)?;
+            require!(league.standings[index].entered == 1, ErrorCode::NotEntered);
+            require!(
+                league.standings[index].claimed == 0,
+                ErrorCode::PrizeAlreadyClaimed
+            );
+            league.standings[index].claimed = 1;
+            league.prize(index)
+        };
+        move_lamports(
+            &ctx.accounts.league.to_account_info(),
+            &ctx.accounts.player.to_account_info(),
+            prize,
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Pay out every unclaimed league prize and close the league
+    // ------------------------------------
+    // Permissionless crank once the league is finalized. Every rostered
+    // player follows as a writable remaining account, in roster order; the
+    // organizer gets back the account's rent
+    pub fn distribute_league_prizes(ctx: Context<DistributeLeaguePrizes>) -> Result<()> {
+        let prizes = {
+            let league = &mut ctx.accounts.league.load_mut()?;
+            require!(league.finalized == 1, ErrorCode::LeagueNotOver);
+            require!(
+                ctx.remaining_accounts.len() == league.roster().len(),
+                ErrorCode::NotInLeague
+            );
+
+            let mut prizes = Vec::with_capacity(league.roster().len());
+            for (index, player_info) in ctx.remaining_accounts.iter().enumerate() {
+                require_keys_eq!(
+                    player_info.key(),
+                    league.standings[index].player,
+                    ErrorCode::NotInLeague
+                );
+                let standing = &league.standings[index];
+                let prize = if standing.entered == 1 && standing.claimed == 0 {
+                    league.prize(index)
+                } else {
+                    0
+                };
+                league.standings[index].claimed = 1;
+                prizes.push(prize);
+            }
+            prizes
+        };
+
+        let league_info = ctx.accounts.league.to_account_info();
+        for (player_info, prize) in ctx.remaining_accounts.iter().zip(prizes) {
+            if prize > 0 {
+                move_lamports(&league_info, player_info, prize)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Create the house bankroll (admin only)
+    // ------------------------------------
+    pub fn initialize_bankroll(ctx: Context<InitializeBankroll>) -> Result<()> {
+        let bankroll = &mut ctx.accounts.bankroll;
+        bankroll.total_shares = 0;
+        bankroll.bump = ctx.bumps.bankroll;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Deposit SOL into the bankroll for shares
+    // ------------------------------------
+    pub fn deposit_bankroll(ctx: Context<DepositBankroll>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidBankrollAmount);
+
+        let assets = bankroll_assets(&ctx.accounts.bankroll.to_account_info())?;
+        let bankroll = &mut ctx.accounts.bankroll;
+        let shares = if bankroll.total_shares == 0 || assets == 0 {
+            amount
+        } else {
+            (amount as u128 * bankroll.total_shares as u128 / assets as u128) as u64
+        };
+        require!(shares > 0, ErrorCode::InvalidBankrollAmount);
+        bankroll.total_shares = bankroll
+            .total_shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        let position = &mut ctx.accounts.lp_position;
+        position.owner = ctx.accounts.depositor.key();
+        position.bump = ctx.bumps.lp_position;
+        position.shares = position
+            .shares
+            .checked_add(shares)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+        let ix = system_instruction::transfer(
+            &ctx.accounts.depositor.key(),
+            &ctx.accounts.bankroll.key(),
+            amount,
+        );
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.depositor.to_account_info(),
+                ctx.accounts.bankroll.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Redeem bankroll shares for SOL
+    // ------------------------------------
+    pub fn withdraw_bankroll(ctx: Context<WithdrawBankroll>, shares: u64) -> Result<()> {
+        let position = &mut ctx.accounts.lp_position;
+        require!(
+            shares > 0 && shares <= position.shares,
+            ErrorCode::InvalidBankrollAmount
+        );
+        position.shares -= shares;
+
+        let bankroll_info = ctx.accounts.bankroll.to_account_info();
+        let assets = bankroll_assets(&bankroll_info)?;
+        let bankroll = &mut ctx.accounts.bankroll;
+        let amount = (shares as u128 * assets as u128 / bankroll.total_shares as u128) as u64;
+        bankroll.total_shares -= shares;
+
+        move_lamports(
+            &bankroll_info,
+            &ctx.accounts.depositor.to_account_info(),
+            amount,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Play against the house
+    // ------------------------------------
+    // The bankroll matches the wager. The house move comes from the hash of
+    // the first slot after this one, so neither side can know it up front
+    pub fn create_house_game(
+        ctx: Context<CreateHouseGame>,
+        wager: u64,
+        player_move: u8,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(!config.paused, ErrorCode::ProgramPaused);
+        config.check_not_banned(ctx.accounts.player.key)?;
+        require!(wager >= config.min_wager, ErrorCode::WagerTooLow);
+        require!(wager <= config.max_wager, ErrorCode::WagerTooHigh);
+        require!(player_move <= 2, ErrorCode::InvalidMove);
+
+        let bankroll_info = ctx.accounts.bankroll.to_account_info();
+        require!(
+            bankroll_assets(&bankroll_info)? >= wager,
+            ErrorCode::InsufficientBankroll
+        );
+
+        let house_game = &mut ctx.accounts.house_game;
+        house_game.player = ctx.accounts.player.key();
+        house_game.wager = wager;
+        house_game.player_move = player_move;
+        house_game.commit_slot = Clock::get()?.slot;
+        house_game.bump = ctx.bumps.house_game;
+
+        // Escrow both sides in the house game account
+        move_lamports(&bankroll_info, &house_game.to_account_info(), wager)?;
+        let ix = system_instruction::transfer(
+            &ctx.accounts.player.key(),
+            &ctx.accounts.house_game.key(),
+            wager,
+        );
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.player.to_account_info(),
+                ctx.accounts.house_game.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Settle a game against the house
+    // ------------------------------------
+    // Anyone can crank this. If the deciding slot hash has aged out of the
+    // sysvar the bankroll keeps the pot, so a losing player gains nothing by
+    // stalling
+    pub fn settle_house_game(ctx: Context<SettleHouseGame>) -> Result<()> {
+        let house_game = &ctx.accounts.house_game;
+        let (pot, player_payout) =
+            house_game_payout(house_game, &ctx.accounts.slot_hashes, Clock::get()?.slot)?
+                .ok_or(ErrorCode::HouseMoveNotReady)?;
+
+        // The account itself closes to the player
+        let house_game_info = ctx.accounts.house_game.to_account_info();
+        move_lamports(
+            &house_game_info,
+            &ctx.accounts.player.to_account_info(),
+            player_payout,
+        )?;
+        move_lamports(
+            &house_game_info,
+            &ctx.accounts.bankroll.to_account_info(),
+            pot - player_payout,
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Settle a batch of resolvable games
+    // ------------------------------------
+    // Remaining accounts list each game followed by its payout accounts:
+    // house games as (house_game, player), stale games as (game, creator,
+    // joiner). Games that can't be settled yet are skipped so one early entry
+    // doesn't fail a crank's whole batch, and so are stale games with a
+    // sponsor, which refund_stale_game settles
+    pub fn settle_many(ctx: Context<SettleMany>) -> Result<()> {
+        let clock = Clock::get()?;
+        let bankroll_info = ctx.accounts.bankroll.to_account_info();
+        let mut accounts = ctx.remaining_accounts;
+        let (mut games, mut settled) = (0, 0);
+
+        while let Some(game_info) = accounts.first() {
+            games += 1;
+            require!(
+                games <= MAX_BATCH_SETTLEMENTS,
+                ErrorCode::TooManySettlements
+            );
+            require!(game_info.owner == &ID, ErrorCode::InvalidSettlementAccount);
+            let discriminator: [u8; 8] = game_info
+                .try_borrow_data()?
+                .get(..8)
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or(ErrorCode::InvalidSettlementAccount)?;
+
+            if discriminator == HouseGame::DISCRIMINATOR {
+                let [_, player_info, ..] = accounts else {
+                    return err!(ErrorCode::InvalidSettlementAccount);
+                };
+                accounts = &accounts[2..];
+
+                let house_game =
+                    HouseGame::try_deserialize(&mut &game_info.try_borrow_data()?[..])?;
+                require_keys_eq!(
+                    player_info.key(),
+                    house_game.player,
+                    ErrorCode::WrongPlayer
+                );
+                let Some((pot, player_payout)) =
+                    house_game_payout(&house_game, &ctx.accounts.slot_hashes, clock.slot)?
+                else {
+                    continue;
+                };
+                move_lamports(game_info, player_info, player_payout)?;
+                move_lamports(game_info, &bankroll_info, pot - player_payout)?;
+                close_account(game_info, player_info)?;
+            } else if discriminator == GameState::DISCRIMINATOR {
+                let [_, creator_info, joiner_info, ..] = accounts else {
+                    return err!(ErrorCode::InvalidSettlementAccount);
+                };
+                accounts = &accounts[3..];
+
+                let game_account =
+                    GameState::try_deserialize(&mut &game_info.try_borrow_data()?[..])?;
+                require_keys_eq!(
+                    creator_info.key(),
+                    game_account.creator,
+                    ErrorCode::WrongCreator
+                );
+                require!(
+                    game_account.opponent == Some(joiner_info.key()),
+                    ErrorCode::WrongOpponent
+                );
+                if !is_stale(&game_account, clock.unix_timestamp) {
+                    continue;
+                }
+                // Sponsors must be repaid first, which takes their accounts;
+                // refund_stale_game handles those games
+                if game_account.creator_sponsor.is_some() || game_account.joiner_sponsor.is_some() {
+                    continue;
+                }
+                // Same split as refund_stale_game: the joiner gets their
+                // wager and bond back, the creator the rest with the rent
+                let joiner_refund = game_account
+                    .wager
+                    .checked_add(game_account.bond)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                move_lamports(game_info, joiner_info, joiner_refund)?;
+                // A refund claims nothing, so insured games just release
+                // their cover, as settle_insurance does on a tie
+                let cover = game_account
+                    .creator_cover
+                    .checked_add(game_account.joiner_cover)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                if cover > 0 {
+                    let vault = ctx
+                        .accounts
+                        .insurance_vault
+                        .as_mut()
+                        .ok_or(ErrorCode::InsuranceVaultMissing)?;
+                    vault.exposure = vault.exposure.saturating_sub(cover);
+                }
+                let creator_refund = game_info.lamports();
+                close_account(game_info, creator_info)?;
+                emit_lifecycle_event!(
+                    ctx,
+                    StaleGameRefunded {
+                        game: game_info.key(),
+                        creator_refund,
+                        joiner_refund,
+                    }
+                );
+            } else {
+                return err!(ErrorCode::InvalidSettlementAccount);
+            }
+            settled += 1;
+        }
+
+        msg!("Settled {} of {} games", settled, games);
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Bet on the results of several games at once
+    // ------------------------------------
+    // The games follow as remaining accounts, in the order of `picks`, and
+    // must not have reached their reveal phase. The bankroll acts as the
+    // bookmaker: it reserves the full return in the parlay account next to
+    // the stake, the same way house games hold both sides
+    pub fn place_parlay(
+        ctx: Context<PlaceParlay>,
+        _id: u64,
+        stake: u64,
+        picks: Vec<RPSResult>,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(!config.paused, ErrorCode::ProgramPaused);
+        config.check_not_banned(ctx.accounts.bettor.key)?;
+        require!(stake >= config.min_wager, ErrorCode::WagerTooLow);
+        require!(stake <= config.max_wager, ErrorCode::WagerTooHigh);
+        require!(
+            (MIN_PARLAY_LEGS..=MAX_PARLAY_LEGS).contains(&picks.len())
+                && ctx.remaining_accounts.len() == picks.len(),
+            ErrorCode::InvalidParlayLegs
+        );
+
+        let mut legs = Vec::with_capacity(picks.len());
+        for (game_info, pick) in ctx.remaining_accounts.iter().zip(picks) {
+            require!(game_info.owner == &ID, ErrorCode::GameNotInFlight);
+            let game_account = GameState::try_deserialize(&mut &game_info.try_borrow_data()?[..])?;
+            require!(
+                game_account.status == GameStatus::Open
+                    || game_account.status == GameStatus::Committed,
+                ErrorCode::GameNotInFlight
+            );
+            require!(
+                legs.iter().all(|leg: &ParlayLeg| leg.game != game_info.key()),
+                ErrorCode::InvalidParlayLegs
+            );
+            legs.push(ParlayLeg {
+                game: game_info.key(),
+                nonce: game_account.nonce,
+                pick,
+                outcome: LegOutcome::Pending,
+            });
+        }
+
+        let payout = parlay_return(stake, legs.len())?;
+        let reserve = payout - stake;
+        let bankroll_info = ctx.accounts.bankroll.to_account_info();
+        require!(
+            bankroll_assets(&bankroll_info)? >= reserve,
+            ErrorCode::InsufficientBankroll
+        );
+
+        let parlay = &mut ctx.accounts.parlay;
+        parlay.bettor = ctx.accounts.bettor.key();
+        parlay.stake = stake;
+        parlay.payout = payout;
+        parlay.legs = legs;
+        parlay.bump = ctx.bumps.parlay;
+
+        move_lamports(&bankroll_info, &parlay.to_account_info(), reserve)?;
+        let ix = system_instruction::transfer(
+            &ctx.accounts.bettor.key(),
+            &ctx.accounts.parlay.key(),
+            stake,
+        );
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.bettor.to_account_info(),
+                ctx.accounts.parlay.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Grade a parlay against its games
+    // ------------------------------------
+    // Anyone can crank this with the parlay's games as remaining accounts,
+    // in leg order, whenever some of them have ended. A lost pick sends the
+    // stake and reserve to the bankroll; once every pick has won the bettor
+    // collects the return. Legs whose game was closed, replayed or rematched
+    // before it could be graded are void and drop out of the odds
+    pub fn settle_parlay(ctx: Context<SettleParlay>) -> Result<()> {
+        let parlay = &mut ctx.accounts.parlay;
+        require!(
+            ctx.remaining_accounts.len() == parlay.legs.len(),
+            ErrorCode::InvalidParlayLegs
+        );
+
+        let mut lost = false;
+        for (leg, game_info) in parlay.legs.iter_mut().zip(ctx.remaining_accounts) {
+            require_keys_eq!(game_info.key(), leg.game, ErrorCode::InvalidParlayLegs);
+            if leg.outcome != LegOutcome::Pending {
+                continue;
+            }
+            if game_info.owner != &ID {
+                leg.outcome = LegOutcome::Void;
+                continue;
+            }
+            let game_account = GameState::try_deserialize(&mut &game_info.try_borrow_data()?[..])?;
+            if game_account.nonce != leg.nonce {
+                leg.outcome = LegOutcome::Void;
+                continue;
+            }
+            if game_account.status != GameStatus::Ended {
+                continue;
+            }
+            leg.outcome = match (game_account.creator_move, game_account.joiner_move) {
+                (Some(creator_move), Some(joiner_move)) => {
+                    if game_account.decide(creator_move, joiner_move)? == leg.pick {
+                        LegOutcome::Won
+                    } else {
+                        lost = true;
+                        LegOutcome::Lost
+                    }
+                }
+                // Ended without both moves on chain, e.g. by a timeout
+                _ => LegOutcome::Void,
+            };
+        }
+
+        let parlay_info = parlay.to_account_info();
+        let bankroll_info = ctx.accounts.bankroll.to_account_info();
+        if lost {
+            move_lamports(&parlay_info, &bankroll_info, parlay.payout)?;
+        } else if parlay.legs.iter().all(|leg| leg.outcome != LegOutcome::Pending) {
+            let won = parlay
+                .legs
+                .iter()
+                .filter(|leg| leg.outcome == LegOutcome::Won)
+                .count();
+            let payout = parlay_return(parlay.stake, won)?;
+            move_lamports(&parlay_info, &bankroll_info, parlay.payout - payout)?;
+        } else {
+            return Ok(());
+        }
+
+        // The rest, the return if any plus rent, goes to the bettor
+        emit!(ParlaySettled {
+            parlay: parlay.key(),
+            bettor: parlay.bettor,
+            payout: parlay_info.lamports() - Rent::get()?.minimum_balance(parlay_info.data_len()),
+        });
+        close_account(&parlay_info, ctx.accounts.bettor.as_ref())
+    }
+
+    // ------------------------------------
+    // Instruction: Upgrade a game account to the current layout
+    // ------------------------------------
+    // Migrations are deterministic, so anyone can run them; the payer covers
+    // any extra rent
+    pub fn migrate_game(ctx: Context<MigrateAccount>) -> Result<()> {
+        // Games from before accounts carried a version byte are recognised
+        // by their size
+        let account = &ctx.accounts.account;
+        let legacy = {
+            let data = account.try_borrow_data()?;
+            if account.owner == &ID
+                && data.len() == 8 + LegacyGameState::SIZE
+                && data[..8] == GameState::DISCRIMINATOR
+            {
+                Some(LegacyGameState::deserialize(&mut &data[8..])?)
+            } else {
+                None
+            }
+        };
+        let Some(old) = legacy else {
+            return migrate_account(
+                account,
+                &ctx.accounts.payer,
+                &ctx.accounts.system_program,
+                GameState::DISCRIMINATOR,
+                GAME_STATE_VERSION,
+                8 + GameState::MAX_SIZE,
+                no_upgrade,
+            );
+        };
+
+        grow_account(
+            account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            8 + GameState::MAX_SIZE,
+        )?;
+        let game = upgrade_legacy_game(old)?;
+        let mut data = account.try_borrow_mut_data()?;
+        data[8..].fill(0);
+        game.serialize(&mut &mut data[8..])?;
+        msg!("Migrated a legacy game to version {}", GAME_STATE_VERSION);
+        Ok(())
+    }
+
+    // ------------------------------------
+    // Instruction: Upgrade the config account to the current layout
+    // ------------------------------------
+    pub fn migrate_config(ctx: Context<MigrateAccount>) -> Result<()> {
+        require!(
+            ctx.accounts.account.key() == find_config_address().0,
+            ErrorCode::InvalidMigrationAccount
+        );
+        migrate_account(
+            &ctx.accounts.account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            Config::DISCRIMINATOR,
+            CONFIG_VERSION,
+            8 + Config::MAX_SIZE,
+            no_upgrade,
+        )
+    }
+
+    // ------------------------------------
+    // Instruction: Upgrade a player stats account to the current layout
+    // ------------------------------------
+    pub fn migrate_player_stats(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account(
+            &ctx.accounts.account,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            PlayerStats::DISCRIMINATOR,
+            PLAYER_STATS_VERSION,
+            8 + PlayerStats::MAX_SIZE,
+            no_upgrade,
+        )
+    }
+}
+
+// ------------------------------------
+// Helper Functions
+// ------------------------------------
+// Moves form a cycle in which each one beats the (move_count - 1) / 2 moves
+// before it; with three that is Paper over Rock, Scissors over Paper and
+// Rock over Scissors
+fn decide_winner(creator_move: u8, joiner_move: u8, move_count: u8) -> Result<RPSResult> {
+    require!(
+        creator_move < move_count && joiner_move < move_count,
+        ErrorCode::InvalidMove
+    );
+    let distance = (creator_move + move_count - joiner_move) % move_count;
+    Ok(match distance {
+        0 => RPSResult::Tie,
+        d if d <= move_count / 2 => RPSResult::CreatorWins,
+        _ => RPSResult::JoinerWins,
+    })
+}
+
+// Records a verified player's reveal and settles the game once both moves
+// are known
+fn apply_reveal<'info>(
+    ctx: Context<'_, '_, '_, 'info, RevealMove<'info>>,
+    player_key: Pubkey,
+    original_move: u8,
+    salt: &str,
+) -> Result<()> {
+    let game_key = ctx.accounts.game_account.key();
+    let game_account = &mut ctx.accounts.game_account;
+    game_account.check_move(original_move)?;
+
+    // Recompute the commitment, bound to this game account and its nonce
+    let hashed_move =
+        commitment_hash(&game_key, game_account.nonce, original_move, salt.as_bytes());
+
+    let now = Clock::get()?.unix_timestamp;
+    if player_key == game_account.creator {
+        require!(game_account.creator_move.is_none(), ErrorCode::AlreadyRevealed);
+        require!(
+            game_account.reveal_order != RevealOrder::JoinerFirst
+                || game_account.joiner_move.is_some(),
+            ErrorCode::RevealOutOfOrder
+        );
+        require!(
+            game_account.creator_move_hashed == hashed_move,
+            ErrorCode::CommitmentMismatch
+        );
+        game_account.charge_clock(true, now)?;
+        game_account.creator_move = Some(original_move);
+        game_account.record_reveal(now);
+    } else if Some(player_key) == game_account.opponent {
+        require!(game_account.joiner_move.is_none(), ErrorCode::AlreadyRevealed);
+        require!(
+            game_account.reveal_order != RevealOrder::CreatorFirst
+                || game_account.creator_move.is_some(),
+            ErrorCode::RevealOutOfOrder
+        );
+        require!(
+            game_account.joiner_move_hashed == hashed_move,
+            ErrorCode::CommitmentMismatch
+        );
+        game_account.charge_clock(false, now)?;
+        game_account.joiner_move = Some(original_move);
+        game_account.record_reveal(now);
+    } else {
+        return err!(ErrorCode::NotAPlayer);
+    }
+
+    // Check if both players have revealed
+    if let (Some(creator_move), Some(joiner_move)) =
+        (game_account.creator_move, game_account.joiner_move)
+    {
+        // Both moves are known; determine the winner
+        let season_id = ctx.accounts.config.season_id;
+        let winner = game_account.handicap_result(
+            game_account.decide(creator_move, joiner_move)?,
+            ctx.accounts.creator_stats.rating_in(season_id),
+            ctx.accounts.joiner_stats.rating_in(season_id),
+        );
+        if winner == RPSResult::Tie && game_account.has_flag(GAME_FLAG_REPLAY_TIES) {
+            debug_msg!("Tie, replaying");
+            game_account.reset_round()?;
+            game_account.sudden_death_rounds = game_account.sudden_death_rounds.saturating_add(1);
+            let round = game_account.sudden_death_rounds;
+            emit_lifecycle_event!(ctx, TieReplayed { game: game_key, round });
+            return Ok(());
+        }
+        if winner == RPSResult::Tie && game_account.breaks_ties_at_random(&ctx.accounts.config) {
+            // Settled by fulfill_tie_break, or split if the VRF never answers.
+            // The revealer pays the request fee
+            let seed = tie_break_seed(&game_key, game_account.nonce);
+            game_account.tie_break_seed = seed;
+            game_account.reveal_deadline = Clock::get()?
+                .unix_timestamp
+                .checked_add(TIE_BREAK_WINDOW_SECONDS)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+            request_randomness(ctx.accounts, seed)?;
+            emit_lifecycle_event!(ctx, TieBreakRequested { game: game_key, seed });
+            return Ok(());
+        }
+        settle_revealed(ctx, creator_move, joiner_move, winner)?;
+    }
+
+    Ok(())
+}
+
+// Settles a game whose moves are both known, paying out according to
+// `winner`
+fn settle_revealed<'info>(
+    ctx: Context<'_, '_, '_, 'info, RevealMove<'info>>,
+    creator_move: u8,
+    joiner_move: u8,
+    winner: RPSResult,
+) -> Result<()> {
+    let game_key = ctx.accounts.game_account.key();
+    let game_account = &mut ctx.accounts.game_account;
+    let season_id = ctx.accounts.config.season_id;
+    ctx.accounts.creator_stats.sync_season(season_id);
+    ctx.accounts.joiner_stats.sync_season(season_id);
+    record_result(
+        &winner,
+        game_account.wager,
+        &ctx.accounts.config,
+        &mut ctx.accounts.creator_stats,
+        &mut ctx.accounts.joiner_stats,
+    )?;
+    let (creator_outcome, joiner_outcome) = winner.outcomes();
+    let slot = Clock::get()?.slot;
+    if let Some(history) = &ctx.accounts.creator_history {
+        history.load_mut()?.record(HistoryEntry::new(
+            ctx.accounts.joiner.key(),
+            game_account.wager,
+            slot,
+            creator_outcome,
+        ));
+    }
+    if let Some(history) = &ctx.accounts.joiner_history {
+        history.load_mut()?.record(HistoryEntry::new(
+            ctx.accounts.creator.key(),
+            game_account.wager,
+            slot,
+            joiner_outcome,
+        ));
+    }
+    // Borrow the account infos rather than cloning them
+    let creator_info: &AccountInfo = ctx.accounts.creator.as_ref();
+    let joiner_info: &AccountInfo = ctx.accounts.joiner.as_ref();
+    let jackpot_info: &AccountInfo = ctx.accounts.jackpot_vault.as_ref();
+    let settlement = handle_payout(
+        winner,
+        game_account,
+        &ctx.accounts.config,
+        payout_account(&ctx.accounts.creator_stats, creator_info, &ctx.accounts.creator_wsol)?,
+        payout_account(&ctx.accounts.joiner_stats, joiner_info, &ctx.accounts.joiner_wsol)?,
+        [
+            ctx.accounts.creator_sponsor.as_deref(),
+            ctx.accounts.joiner_sponsor.as_deref(),
+        ],
+        ctx.accounts.house.as_ref(),
+        ctx.remaining_accounts,
+        jackpot_info,
+    )?;
+    sync_wrapped_payouts(
+        &ctx.accounts.token_program,
+        [&ctx.accounts.creator_wsol, &ctx.accounts.joiner_wsol],
+    )?;
+    record_rake(
+        settlement.house_fee,
+        &mut ctx.accounts.creator_stats,
+        &mut ctx.accounts.joiner_stats,
+    )?;
+    award_points(
+        &ctx.accounts.config,
+        game_account.wager,
+        &mut ctx.accounts.creator_stats,
+        &mut ctx.accounts.joiner_stats,
+    )?;
+    // Both players revealed in time, so both get their bond back
+    pay_bonds(game_account, creator_info, 1)?;
+    pay_bonds(game_account, joiner_info, 1)?;
+    game_account.bond = 0;
+    game_account.tie_break_seed = [0u8; 32];
+    game_account.end(winner.into())?;
+    settle_insurance(
+        game_account,
+        winner,
+        ctx.accounts.insurance_vault.as_mut(),
+        creator_info,
+        joiner_info,
+    )?;
+    notify_callback(
+        game_account,
+        ctx.accounts.callback.as_ref(),
+        ctx.accounts.callback_program.as_ref(),
+        ctx.remaining_accounts,
+    )?;
+    let label = game_account.label;
+    emit_lifecycle_event!(
+        ctx,
+        GameSettled {
+            game: game_key,
+            creator_move,
+            joiner_move,
+            result: winner,
+            house_fee: settlement.house_fee,
+            creator_payout: settlement.creator_payout,
+            joiner_payout: settlement.joiner_payout,
+            label,
+        }
+    );
+
+    // The winner may also hit the progressive jackpot, unless both players
+    // are bots
+    let bots_only = ctx.accounts.creator_stats.bot && ctx.accounts.joiner_stats.bot;
+    let jackpot_candidate = match winner {
+        _ if bots_only => None,
+        RPSResult::CreatorWins => Some((creator_info, creator_move, joiner_move)),
+        RPSResult::JoinerWins => Some((joiner_info, joiner_move, creator_move)),
+        RPSResult::Tie => None,
+    };
+    if let Some((winner_info, winning_move, losing_move)) = jackpot_candidate {
+        if jackpot_triggered(
+            &ctx.accounts.config,
+            winning_move,
+            losing_move,
+            &ctx.accounts.slot_hashes,
+            ctx.accounts.game_account.reveal_slot,
+        )? {
+            pay_jackpot(
+                jackpot_info,
+                winner_info,
+                ctx.accounts.system_program.as_ref(),
+                ctx.bumps.jackpot_vault,
+            )?;
+        }
+    }
+
+    return_settlement(
+        &ctx.accounts.game_account,
+        settlement.creator_payout + settlement.joiner_payout,
+        settlement.house_fee,
+    )
+}
+
+// Checks that the instruction before the current one is an ed25519 program
+// instruction verifying `signer`'s signature over exactly `message`, and
+// returns the signature
+fn verify_ed25519_signature(
+    instructions: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<[u8; 64]> {
+    let current = load_current_index_checked(instructions)?;
+    require!(current > 0, ErrorCode::MissingSignatureVerification);
+    let ix = load_instruction_at_checked(current as usize - 1, instructions)?;
+    require!(
+        ix.program_id == ed25519_program::ID,
+        ErrorCode::MissingSignatureVerification
+    );
+
+    // One signature: count, padding, then a 14-byte offsets record
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::SignatureMismatch);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+
+    // Signature, key and message must all live in the ed25519 instruction
+    require!(
+        read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+        ErrorCode::SignatureMismatch
+    );
+    let signature_offset = read_u16(2) as usize;
+    let key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_len = read_u16(12) as usize;
+    let signed_key = data.get(key_offset..key_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_len);
+    require!(
+        signed_key == Some(signer.as_ref()) && signed_message == Some(message),
+        ErrorCode::SignatureMismatch
+    );
+
+    let signature = data
+        .get(signature_offset..signature_offset + 64)
+        .ok_or(ErrorCode::SignatureMismatch)?;
+    Ok(signature.try_into().unwrap())
+}
+
+// Seed of a tied game's VRF request, unique to the game and round
+fn tie_break_seed(game: &Pubkey, nonce: u64) -> [u8; 32] {
+    hashv(&[b"tie_break", game.as_ref(), &nonce.to_le_bytes()]).to_bytes()
+}
+
+// Requests ORAO VRF randomness for `seed`, with the revealing player paying
+// the network's fee
+fn request_randomness(accounts: &RevealMove, seed: [u8; 32]) -> Result<()> {
+    let (Some(vrf_program), Some(network_state), Some(treasury), Some(randomness)) = (
+        &accounts.vrf_program,
+        &accounts.vrf_network_state,
+        &accounts.vrf_treasury,
+        &accounts.vrf_randomness,
+    ) else {
+        return err!(ErrorCode::MissingVrfAccounts);
+    };
+    require!(
+        randomness.key() == find_tie_break_randomness_address(&seed).0,
+        ErrorCode::InvalidVrfAccount
+    );
+
+    // The network checks the treasury against its configuration
+    let mut data = hashv(&[b"global:request"]).to_bytes()[..8].to_vec();
+    data.extend_from_slice(&seed);
+    invoke(
+        &Instruction {
+            program_id: ORAO_VRF_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(accounts.player.key(), true),
+                AccountMeta::new(network_state.key(), false),
+                AccountMeta::new(treasury.key(), false),
+                AccountMeta::new(randomness.key(), false),
+                AccountMeta::new_readonly(System::id(), false),
+            ],
+            data,
+        },
+        &[
+            accounts.player.to_account_info(),
+            network_state.to_account_info(),
+            treasury.to_account_info(),
+            randomness.to_account_info(),
+            accounts.system_program.to_account_info(),
+            vrf_program.to_account_info(),
+        ],
+    )?;
+    Ok(())
+}
+
+// Randomness the VRF network fulfilled for `seed`. A Randomness account
+// holds the seed and then 64 bytes of randomness, zeroed until enough of
+// the network's oracles have answered
+fn fulfilled_randomness(account: Option<&UncheckedAccount>, seed: &[u8; 32]) -> Result<[u8; 64]> {
+    let account = account.ok_or(ErrorCode::MissingVrfAccounts)?;
+    require!(
+        account.owner == &ORAO_VRF_PROGRAM_ID
+            && account.key() == find_tie_break_randomness_address(seed).0,
+        ErrorCode::InvalidVrfAccount
+    );
+    let data = account.try_borrow_data()?;
+    require!(
+        data.len() >= 104
+            && data[..8] == hashv(&[b"account:Randomness"]).to_bytes()[..8]
+            && data[8..40] == seed[..],
+        ErrorCode::InvalidVrfAccount
+    );
+    let randomness: [u8; 64] = data[40..104].try_into().unwrap();
+    require!(randomness != [0u8; 64], ErrorCode::RandomnessNotFulfilled);
+    Ok(randomness)
+}
+
+// Closes out both players' loss insurance once a game ends: an insured loser
+// is refunded from the vault, and every other policy lapses. Either way the
+// vault's exposure is released
+fn settle_insurance<'info>(
+    game_account: &mut Account<'info, GameState>,
+    winner: RPSResult,
+    insurance_vault: Option<&mut Account<'info, InsuranceVault>>,
+    creator: &AccountInfo<'info>,
+    joiner: &AccountInfo<'info>,
+) -> Result<()> {
+    let creator_cover = std::mem::take(&mut game_account.creator_cover);
+    let joiner_cover = std::mem::take(&mut game_account.joiner_cover);
+    if creator_cover == 0 && joiner_cover == 0 {
+        return Ok(());
+    }
+    let vault = insurance_vault.ok_or(ErrorCode::InsuranceVaultMissing)?;
+    vault.exposure = vault.exposure.saturating_sub(creator_cover + joiner_cover);
+
+    let claim = match winner {
+        RPSResult::CreatorWins => Some((joiner, joiner_cover)),
+        RPSResult::JoinerWins => Some((creator, creator_cover)),
+        RPSResult::Tie => None,
+    };
+    if let Some((loser, refund)) = claim.filter(|(_, refund)| *refund > 0) {
+        move_lamports(&vault.to_account_info(), loser, refund)?;
+        vault.claims_paid = vault
+            .claims_paid
+            .checked_add(refund)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        msg!("Insurance refund: {}", refund);
+    }
+    Ok(())
+}
+
+// Invokes the game's callback program with the result just stored on the
+// game, when the settler passed the callback. It is left out otherwise,
+// so a callback that fails can only cost its own notification, never the
+// settlement. The callback's accounts are found among the remaining
+// accounts by key, next to any fee recipients. Nothing is passed as a
+// signer, so the callback can't act for whoever settled
+fn notify_callback<'info>(
+    game_account: &mut Account<'info, GameState>,
+    callback: Option<&Account<'info, GameCallback>>,
+    callback_program: Option<&UncheckedAccount<'info>>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let (Some(callback), Some(callback_program)) = (callback, callback_program) else {
+        return Ok(());
+    };
+    require_keys_eq!(
+        callback_program.key(),
+        callback.program,
+        ErrorCode::InvalidCallback
+    );
+
+    let mut metas = vec![AccountMeta::new_readonly(game_account.key(), false)];
+    let mut infos = vec![game_account.to_account_info()];
+    for account in &callback.accounts {
+        let info = remaining_accounts
+            .iter()
+            .find(|info| *info.key == account.pubkey)
+            .ok_or(ErrorCode::MissingCallback)?;
+        metas.push(if account.is_writable {
+            AccountMeta::new(account.pubkey, false)
+        } else {
+            AccountMeta::new_readonly(account.pubkey, false)
+        });
+        infos.push(info.clone());
+    }
+    infos.push(callback_program.to_account_info());
+    let mut data = hashv(&[b"global:on_game_settled"]).to_bytes()[..8].to_vec();
+    GameSettledCallback {
+        game: game_account.key(),
+        creator: game_account.creator,
+        opponent: game_account.opponent,
+        winner: game_account.winner,
+        result: game_account.result,
+    }
+    .serialize(&mut data)?;
+
+    // Written out first so the callback reads the game as it ended
+    game_account.exit(&ID)?;
+    invoke(
+        &Instruction {
+            program_id: callback.program,
+            accounts: metas,
+            data,
+        },
+        &infos,
+    )?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_payout(
+    winner: RPSResult,
+    game_account: &mut Account<GameState>,
+    config: &Config,
+    creator: &AccountInfo,
+    joiner: &AccountInfo,
+    sponsors: [Option<&AccountInfo>; 2],
+    house: &AccountInfo,
+    fee_recipients: &[AccountInfo],
+    jackpot_vault: &AccountInfo,
+) -> Result<SimulatedResult> {
+    let amounts = settlement_amounts(game_account, config, winner)?;
+    transfer_from_escrow(game_account, jackpot_vault, amounts.jackpot_cut)?;
+    distribute_house_fee(
+        game_account,
+        config,
+        house,
+        fee_recipients,
+        amounts.house_fee - amounts.jackpot_cut,
+    )?;
+
+    debug_msg!("Result: {:?}", winner);
+    let creator_sponsor = game_account.creator_sponsor.take();
+    let joiner_sponsor = game_account.joiner_sponsor.take();
+    // A rollover game keeps the payout of a winner who staked their own
+    // wager in escrow
+    let rollover = game_account.has_flag(GAME_FLAG_ROLLOVER);
+    if !(rollover && winner == RPSResult::CreatorWins && creator_sponsor.is_none()) {
+        pay_player(game_account, creator_sponsor, sponsors[0], creator, amounts.creator_payout)?;
+    }
+    if !(rollover && winner == RPSResult::JoinerWins && joiner_sponsor.is_none()) {
+        pay_player(game_account, joiner_sponsor, sponsors[1], joiner, amounts.joiner_payout)?;
+    }
+    Ok(amounts)
+}
+
+// Pays `count` posted reveal bonds out of the game account. Bonds sit next
+// to the escrow, not in it
+fn pay_bonds(game_account: &Account<GameState>, to: &AccountInfo, count: u64) -> Result<()> {
+    let amount = game_account
+        .bond
+        .checked_mul(count)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    if amount == 0 {
+        return Ok(());
+    }
+    move_lamports(game_account.as_ref(), to, amount)
+}
+
+// Splits an escrow between the house and the players. The house fee comes off
+// the top with a slice routed to the jackpot, unless the config waives it on
+// draws; on a tie the rest is split and an odd lamport left over goes to the
+// creator. Under FeeMode::WinnerProfit the fee is only charged on the loser's
+// half of the pot
+fn settlement_amounts(
+    game_account: &GameState,
+    config: &Config,
+    winner: RPSResult,
+) -> Result<SimulatedResult> {
+    let escrow = game_account.escrow;
+    let fee_bps = if winner == RPSResult::Tie && config.waive_draw_fees {
+        0
+    } else {
+        config.fee_bps_for(game_account.wager, Clock::get()?.unix_timestamp)
+    };
+    let fee_base = match (game_account.fee_mode, winner) {
+        (FeeMode::Pot, _) => escrow,
+        (FeeMode::WinnerProfit, RPSResult::Tie) => 0,
+        (FeeMode::WinnerProfit, _) => escrow / 2,
+    };
+    let house_fee = fee_base
+        .checked_mul(fee_bps as u64)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        / BPS_DENOMINATOR;
+    let jackpot_cut = house_fee
+        .checked_mul(config.jackpot_share_bps as u64)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        / BPS_DENOMINATOR;
+    let payout = escrow - house_fee;
+    let (creator_payout, joiner_payout) = match winner {
+        RPSResult::CreatorWins => (payout, 0),
+        RPSResult::JoinerWins => (0, payout),
+        RPSResult::Tie => (payout - payout / 2, payout / 2),
+    };
+    Ok(SimulatedResult {
+        result: winner,
+        replay: false,
+        house_fee,
+        jackpot_cut,
+        creator_payout,
+        joiner_payout,
+    })
+}
+
+// Pays the house fee to the configured recipients by weight, the last one
+// taking any rounding remainder, or to the house account if none are set
+fn distribute_house_fee(
+    game_account: &mut Account<GameState>,
+    config: &Config,
+    house: &AccountInfo,
+    fee_recipients: &[AccountInfo],
+    fee: u64,
+) -> Result<()> {
+    if config.fee_recipients.is_empty() {
+        return transfer_from_escrow(game_account, house, fee);
+    }
+
+    let mut remaining = fee;
+    for (i, recipient) in config.fee_recipients.iter().enumerate() {
+        let account = fee_recipients
+            .iter()
+            .find(|account| *account.key == recipient.recipient)
+            .ok_or(ErrorCode::MissingFeeRecipient)?;
+        let share = if i + 1 == config.fee_recipients.len() {
+            remaining
+        } else {
+            fee.checked_mul(recipient.weight_bps as u64)
+                .ok_or(ErrorCode::ArithmeticOverflow)?
+                / BPS_DENOMINATOR
+        };
+        remaining -= share;
+        transfer_from_escrow(game_account, account, share)?;
+    }
+    Ok(())
+}
+
+// Seats the joiner in an open game and escrows their wager
+fn apply_join(ctx: &mut Context<JoinGame>) -> Result<()> {
+    let config = &ctx.accounts.config;
+    init_player_stats(
+        &mut ctx.accounts.joiner_stats,
+        ctx.accounts.joiner.key(),
+        ctx.bumps.joiner_stats,
+        config.season_id,
+    );
+    let game_account = &mut ctx.accounts.game_account;
+    admit_joiner(
+        game_account,
+        config,
+        ctx.accounts.joiner.key,
+        &mut ctx.accounts.joiner_stats,
+        ctx.accounts.allowlist.as_deref(),
+    )?;
+    ctx.accounts.creator_stats.close_open_game();
+
+    // The game is no longer joinable
+    ctx.accounts.lobby.deregister(&game_account.key());
+
+    // Transfer the wager and any reveal bond from joiner to game_account. A
+    // sponsor may front the wager; the bond is always the joiner's
+    let joiner_info = ctx.accounts.joiner.to_account_info();
+    let deposits = match &ctx.accounts.sponsor {
+        Some(sponsor) => {
+            game_account.joiner_sponsor = Some(sponsor.key());
+            [
+                (sponsor.to_account_info(), game_account.wager),
+                (joiner_info, game_account.bond),
+            ]
+        }
+        None => {
+            let deposit = game_account
+                .wager
+                .checked_add(game_account.bond)
+                .ok_or(ErrorCode::ArithmeticOverflow)?;
+            [(joiner_info.clone(), deposit), (joiner_info, 0)]
+        }
+    };
+    for (funder, amount) in deposits {
+        if amount == 0 {
+            continue;
+        }
+        let ix = system_instruction::transfer(funder.key, &ctx.accounts.game_account.key(), amount);
+        invoke(
+            &ix,
+            &[
+                funder,
+                ctx.accounts.game_account.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+    }
+
+    emit_lifecycle_event!(
+        ctx,
+        GameJoined {
+            game: ctx.accounts.game_account.key(),
+            joiner: ctx.accounts.joiner.key(),
+            wager: ctx.accounts.game_account.wager,
+        }
+    );
+
+    Ok(())
+}
+
+// Runs every check a joiner must pass to take the open seat of a game, then
+// seats them and admits their wager against their daily cap
+fn admit_joiner(
+    game_account: &mut GameState,
+    config: &Config,
+    joiner: &Pubkey,
+    joiner_stats: &mut PlayerStats,
+    allowlist: Option<&Allowlist>,
+) -> Result<()> {
+    require!(!config.paused, ErrorCode::ProgramPaused);
+    config.check_not_banned(joiner)?;
+    check_joinable(game_account, joiner)?;
+    check_allowlist(game_account, allowlist, joiner)?;
+    check_reputation(game_account, joiner_stats)?;
+    check_humans_only(game_account, joiner_stats)?;
+    joiner_stats.admit_wager(
+        game_account.wager,
+        config.daily_wager_cap,
+        Clock::get()?.unix_timestamp,
+    )?;
+    seat_joiner(game_account, *joiner)
+}
+
+// Games that need an invite or the creator's approval can't be joined
+// straight away
+fn check_direct_join(game_account: &GameState) -> Result<()> {
+    require!(
+        !game_account.has_flag(GAME_FLAG_INVITE_ONLY),
+        ErrorCode::InviteRequired
+    );
+    require!(
+        !game_account.has_flag(GAME_FLAG_APPROVAL_REQUIRED),
+        ErrorCode::ApprovalRequired
+    );
+    Ok(())
+}
+
+// Checks that `joiner` may take the open seat of a game
+fn check_joinable(game_account: &GameState, joiner: &Pubkey) -> Result<()> {
+    require!(
+        game_account.status == GameStatus::Open,
+        ErrorCode::GameNotOpen
+    );
+    require!(
+        Clock::get()?.unix_timestamp <= game_account.join_deadline,
+        ErrorCode::JoinDeadlinePassed
+    );
+    require!(
+        game_account.reserved_for.is_none() || game_account.reserved_for == Some(*joiner),
+        ErrorCode::SeatReserved
+    );
+    Ok(())
+}
+
+// Checks `joiner` against the game's allowlist, if it has one
+fn check_allowlist(
+    game_account: &GameState,
+    allowlist: Option<&Allowlist>,
+    joiner: &Pubkey,
+) -> Result<()> {
+    if game_account.has_flag(GAME_FLAG_ALLOWLIST) {
+        require!(
+            allowlist.is_some_and(|allowlist| allowlist.players.contains(joiner)),
+            ErrorCode::NotOnAllowlist
+        );
+    }
+    Ok(())
+}
+
+// Keeps registered bots out of humans-only games
+fn check_humans_only(game_account: &GameState, joiner_stats: &PlayerStats) -> Result<()> {
+    require!(
+        !(game_account.has_flag(GAME_FLAG_HUMANS_ONLY) && joiner_stats.bot),
+        ErrorCode::BotsNotAllowed
+    );
+    Ok(())
+}
+
+// Checks the joiner's reputation against the game's minimum
+fn check_reputation(game_account: &GameState, joiner_stats: &PlayerStats) -> Result<()> {
+    require!(
+        joiner_stats.reputation >= game_account.min_reputation,
+        ErrorCode::ReputationTooLow
+    );
+    Ok(())
+}
+
+// Seats the joiner and adds their wager to the escrow; the caller moves the
+// lamports
+fn seat_joiner(game_account: &mut GameState, joiner: Pubkey) -> Result<()> {
+    game_account.opponent = Some(joiner);
+    game_account.status = GameStatus::Committed;
+    game_account.counter_offer = None;
+    let now = Clock::get()?.unix_timestamp;
+    game_account.phase_started_at = now;
+    game_account.joined_at = now;
+    game_account.escrow = game_account
+        .escrow
+        .checked_add(game_account.wager)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    Ok(())
+}
+
+// Moves an open game to `wager`, taking the difference from the creator or
+// refunding it, and keeps the lobby listing current
+fn change_wager(ctx: &mut Context<ChangeWager>, wager: u64) -> Result<()> {
+    let game_key = ctx.accounts.game_account.key();
+    let game_account = &mut ctx.accounts.game_account;
+    // A sponsor fronted the current wager and is owed exactly that
+    require!(game_account.creator_sponsor.is_none(), ErrorCode::WagerSponsored);
+    let old_wager = game_account.wager;
+    if wager > old_wager {
+        let amount = wager - old_wager;
+        game_account.escrow = game_account
+            .escrow
+            .checked_add(amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        let ix = system_instruction::transfer(&ctx.accounts.creator.key(), &game_key, amount);
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.creator.to_account_info(),
+                game_account.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+    } else {
+        transfer_from_escrow(game_account, ctx.accounts.creator.as_ref(), old_wager - wager)?;
+    }
+    game_account.wager = wager;
+    ctx.accounts.lobby.update_wager(&game_key, wager);
+
+    emit_lifecycle_event!(
+        ctx,
+        WagerChanged {
+            game: game_key,
+            old_wager,
+            wager,
+        }
+    );
+
+    Ok(())
+}
+
+// Runs the checks every game a creator opens must pass, the listing stake
+// included, admits the wager against their daily cap and creation cooldown
+// and counts the game against their open games
+fn admit_creation(
+    config: &Config,
+    creator: &Pubkey,
+    creator_stake: Option<&TokenAccount>,
+    creator_stats: &mut PlayerStats,
+    wager: u64,
+) -> Result<()> {
+    require!(!config.paused, ErrorCode::ProgramPaused);
+    config.check_not_banned(creator)?;
+    config.check_creation_stake(creator_stake)?;
+    require!(wager >= config.min_wager, ErrorCode::WagerTooLow);
+    require!(wager <= config.max_wager, ErrorCode::WagerTooHigh);
+    let now = Clock::get()?.unix_timestamp;
+    creator_stats.admit_wager(wager, config.daily_wager_cap, now)?;
+    creator_stats.record_creation(config.creation_cooldown, now)?;
+    creator_stats.open_game(config.max_open_games)
+}
+
+// Resets every field of a new game, which starts open with the creator's wager
+fn init_game(
+    game_account: &mut Account<GameState>,
+    creator: Pubkey,
+    wager: u64,
+    bump: u8,
+) -> Result<()> {
+    game_account.version = GAME_STATE_VERSION;
+    game_account.creator = creator;
+    game_account.opponent = None;
+    game_account.creator_move_hashed = [0u8; 32];
+    game_account.joiner_move_hashed = [0u8; 32];
+    game_account.creator_move = None;
+    game_account.joiner_move = None;
+    game_account.flags = 0;
+    game_account.wager = wager;
+    game_account.escrow = wager;
+    game_account.status = GameStatus::Open;
+    game_account.bump = bump;
+    let clock = Clock::get()?;
+    game_account.nonce = clock.slot;
+    game_account.created_at = clock.unix_timestamp;
+    game_account.join_deadline = clock
+        .unix_timestamp
+        .checked_add(JOIN_WINDOW_SECONDS)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    game_account.reveal_deadline = 0;
+    game_account.league = None;
+    game_account.reserved_for = None;
+    game_account.creator_nft = None;
+    game_account.joiner_nft = None;
+    game_account.assets = Vec::new();
+    game_account.reveal_order = RevealOrder::Any;
+    game_account.bond = 0;
+    game_account.min_reputation = 0;
+    game_account.tie_break_seed = [0u8; 32];
+    game_account.sudden_death_rounds = 0;
+    game_account.time_bank = 0;
+    game_account.creator_time_left = 0;
+    game_account.joiner_time_left = 0;
+    game_account.phase_started_at = 0;
+    game_account.counter_offer = None;
+    game_account.spectators = 0;
+    game_account.creator_sponsor = None;
+    game_account.joiner_sponsor = None;
+    game_account.fee_mode = FeeMode::Pot;
+    game_account.creator_cover = 0;
+    game_account.joiner_cover = 0;
+    game_account.move_count = CLASSIC_MOVE_COUNT;
+    game_account.ruleset = None;
+    game_account.beats = [0; MAX_MOVE_COUNT as usize];
+    game_account.label = [0u8; MAX_LABEL_LEN];
+    game_account.category = GameCategory::Casual;
+    game_account.index = 0;
+    game_account.prev_game = None;
+    game_account.joined_at = 0;
+    game_account.first_reveal_at = 0;
+    game_account.ended_at = 0;
+    game_account.winner = None;
+    game_account.result = None;
+    game_account.reveal_slot = 0;
+    Ok(())
+}
+
+// Numbers a new game and links it to the previously indexed one, so clients
+// can walk recent games back from Config::latest_game. A walk ends at the
+// first game, or early at one that has since been closed
+fn index_game(game_account: &mut Account<GameState>, config: &mut Config) -> Result<()> {
+    config.game_count = config
+        .game_count
+        .checked_add(1)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    game_account.index = config.game_count;
+    game_account.prev_game = config.latest_game.replace(game_account.key());
+    Ok(())
+}
+
+// Seats a player in an open elimination game
+fn take_seat(multi_game: &mut MultiGame, player: Pubkey) -> Result<()> {
+    require!(
+        multi_game.status()? == GameStatus::Open,
+        ErrorCode::GameNotOpen
+    );
+    require!(
+        multi_game.seated().iter().all(|seat| seat.player != player),
+        ErrorCode::AlreadySeated
+    );
+
+    let index = multi_game.seated_count as usize;
+    multi_game.players[index] = MultiSeat {
+        player,
+        commitment: [0u8; 32],
+        revealed: NO_MOVE,
+        eliminated: 0,
+    };
+    multi_game.seated_count += 1;
+    multi_game.escrow = multi_game
+        .escrow
+        .checked_add(multi_game.wager)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    if multi_game.seated_count == multi_game.seats {
+        multi_game.set_status(GameStatus::Committed);
+    }
+    Ok(())
+}
+
+// Escrows a seat's wager; runs after the account data borrow is released
+// since the transfer CPI needs the account writable
+fn escrow_seat_wager<'info>(
+    multi_game: &AccountLoader<'info, MultiGame>,
+    player: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    wager: u64,
+) -> Result<()> {
+    if wager > 0 {
+        let ix = system_instruction::transfer(&player.key(), &multi_game.key(), wager);
+        invoke(
+            &ix,
+            &[
+                player.to_account_info(),
+                multi_game.to_account_info(),
+                system_program.to_account_info(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+// Charges the config's creation fee into the treasury, which must be passed
+// while a fee is set
+fn charge_creation_fee<'info>(
+    config: &Config,
+    creator: &Signer<'info>,
+    treasury: &Option<Account<'info, Treasury>>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    if config.creation_fee == 0 {
+        return Ok(());
+    }
+    let treasury = treasury.as_ref().ok_or(ErrorCode::TreasuryMissing)?;
+    let ix = system_instruction::transfer(&creator.key(), &treasury.key(), config.creation_fee);
+    invoke(
+        &ix,
+        &[
+            creator.to_account_info(),
+            treasury.to_account_info(),
+            system_program.to_account_info(),
+        ],
+    )?;
+    Ok(())
+}
+
+// Pays a player's share of the escrow. A sponsor who fronted the player's
+// wager is repaid first, up to the wager, and must then be passed. In a
+// hidden game they fronted the whole deposit, the wager still unknown
+fn pay_player(
+    game_account: &mut Account<GameState>,
+    sponsor: Option<Pubkey>,
+    sponsor_info: Option<&AccountInfo>,
+    player: &AccountInfo,
+    amount: u64,
+) -> Result<()> {
+    let fronted = if game_account.status == GameStatus::Hidden {
+        game_account.escrow
+    } else {
+        game_account.wager
+    };
+    let repaid = if sponsor.is_some() {
+        amount.min(fronted)
+    } else {
+        0
+    };
+    if repaid > 0 {
+        let sponsor_info = sponsor_info
+            .filter(|info| Some(*info.key) == sponsor)
+            .ok_or(ErrorCode::SponsorMissing)?;
+        transfer_from_escrow(game_account, sponsor_info, repaid)?;
+    }
+    transfer_from_escrow(game_account, player, amount - repaid)
+}
+
+// Eliminates the players who threw the losing move when exactly two distinct
+// moves were thrown this round
+fn eliminate_round_losers(players: &mut [MultiSeat]) {
+    let mut thrown = [false; 3];
+    for seat in players.iter().filter(|seat| !seat.is_eliminated()) {
+        if let Some(original_move) = seat.revealed() {
+            thrown[original_move as usize] = true;
+        }
+    }
+    let losing_move = match thrown {
+        [true, true, false] => 0, // Paper beats Rock
+        [false, true, true] => 1, // Scissors beat Paper
+        [true, false, true] => 2, // Rock beats Scissors
+        _ => return,
+    };
+    for seat in players.iter_mut() {
+        if seat.revealed() == Some(losing_move) {
+            seat.eliminated = 1;
+        }
+    }
+}
+
+// Bit marking the fixture between two roster positions
+fn league_fixture_bit(a: usize, b: usize) -> Result<u64> {
+    require!(a != b, ErrorCode::InvalidLeagueRoster);
+    let (low, high) = (a.min(b), a.max(b));
+    Ok(1 << (low * MAX_LEAGUE_PLAYERS + high))
+}
+
+// Loser of a settled game, if it was not a tie
+fn settled_loser(game_account: &GameState) -> Option<Pubkey> {
+    match game_account.result? {
+        GameResult::CreatorWins => game_account.opponent,
+        GameResult::JoinerWins => Some(game_account.creator),
+        _ => None,
+    }
+}
+
+// Moves a rematch stake from the signing player into the game account
+fn deposit_stake(ctx: &Context<Rematch>, stake: u64) -> Result<()> {
+    let ix = system_instruction::transfer(
+        &ctx.accounts.player.key(),
+        &ctx.accounts.game_account.key(),
+        stake,
+    );
+    invoke(
+        &ix,
+        &[
+            ctx.accounts.player.to_account_info(),
+            ctx.accounts.game_account.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ],
+    )?;
+    Ok(())
+}
+
+// Lamports the bankroll holds above its rent-exempt minimum
+fn bankroll_assets(bankroll: &AccountInfo) -> Result<u64> {
+    let rent_floor = Rent::get()?.minimum_balance(bankroll.data_len());
+    Ok(bankroll.lamports().saturating_sub(rent_floor))
+}
+
+// Pot and player payout of a house game, or None while the house move is
+// still undecided. Once the deciding slot hash has aged out of the sysvar
+// the bankroll keeps the pot
+fn house_game_payout(
+    house_game: &HouseGame,
+    slot_hashes: &AccountInfo,
+    slot: u64,
+) -> Result<Option<(u64, u64)>> {
+    if slot <= house_game.commit_slot {
+        return Ok(None);
+    }
+    let house_move = deciding_slot_hash(slot_hashes, house_game.commit_slot)?
+        .map(|hash| hash[0] % CLASSIC_MOVE_COUNT);
+    let player_move = house_game.player_move;
+    let pot = house_game
+        .wager
+        .checked_mul(2)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let player_payout = match house_move {
+        Some(house_move) => match decide_winner(player_move, house_move, CLASSIC_MOVE_COUNT)? {
+            RPSResult::CreatorWins => pot,
+            RPSResult::JoinerWins => 0,
+            RPSResult::Tie => house_game.wager,
+        },
+        None if slot > house_game.commit_slot + SLOT_HASHES_MAX_ENTRIES => 0,
+        None => return Ok(None),
+    };
+    msg!("House move: {:?}", house_move);
+    Ok(Some((pot, player_payout)))
+}
+
+// What a parlay of `legs` winning picks returns on `stake`
+fn parlay_return(stake: u64, legs: usize) -> Result<u64> {
+    let mut payout = stake as u128;
+    for _ in 0..legs {
+        payout = payout * PARLAY_LEG_ODDS_BPS as u128 / BPS_DENOMINATOR as u128;
+    }
+    u64::try_from(payout).map_err(|_| error!(ErrorCode::ArithmeticOverflow))
+}
+
+// A game in its reveal phase that nobody revealed before the deadline
+fn is_stale(game_account: &GameState, now: i64) -> bool {
+    game_account.status == GameStatus::Revealing
+        && game_account.creator_move.is_none()
+        && game_account.joiner_move.is_none()
+        && !game_account.holds_escrowed_tokens()
+        && now > game_account.reveal_deadline
+}
+
+// Closes a program account outside of an accounts struct, sending its
+// lamports to `destination`
+fn close_account(account: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    move_lamports(account, destination, account.lamports())?;
+    account.assign(&System::id());
+    account.realloc(0, false)?;
+    Ok(())
+}
+
+// Hash of the first recorded slot after `commit_slot`, or None once the
+// SlotHashes sysvar no longer reaches back that far
+fn deciding_slot_hash(slot_hashes: &AccountInfo, commit_slot: u64) -> Result<Option<[u8; 32]>> {
+    // SlotHashes layout: u64 entry count, then (slot: u64, hash: [u8; 32])
+    // entries ordered newest first
+    let data = slot_hashes.try_borrow_data()?;
+    require!(data.len() >= 8, ErrorCode::InvalidSlotHashes);
+    let count = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+    let mut deciding = None;
+    for entry in data[8..].chunks_exact(40).take(count) {
+        let slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        if slot <= commit_slot {
+            return Ok(deciding);
+        }
+        deciding = Some(entry[8..].try_into().unwrap());
+    }
+    // History does not reach back to the commit slot
+    Ok(None)
+}
+
+// Brings a program account up to `current_version`: grows it to `space`,
+// tops up its rent and hands older layouts to `upgrade`, which rewrites the
+// account data from the given version. Each release that changes a layout
+// adds a step there converting the previous version
+fn migrate_account<'info>(
+    account: &UncheckedAccount<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    discriminator: [u8; 8],
+    current_version: u8,
+    space: usize,
+    upgrade: fn(u8, &mut [u8]) -> Result<()>,
+) -> Result<()> {
+    require!(account.owner == &ID, ErrorCode::InvalidMigrationAccount);
+    let version = {
+        let data = account.try_borrow_data()?;
+        require!(
+            data.len() > 8 && data[..8] == discriminator,
+            ErrorCode::InvalidMigrationAccount
+        );
+        data[8]
+    };
+    require!(
+        version > 0 && version <= current_version,
+        ErrorCode::UnsupportedAccountVersion
+    );
+    if version == current_version && account.data_len() >= space {
+        return Ok(());
+    }
+
+    grow_account(account, payer, system_program, space)?;
+    if version < current_version {
+        upgrade(version, &mut account.try_borrow_mut_data()?)?;
+    }
+    account.try_borrow_mut_data()?[8] = current_version;
+    msg!("Migrated from version {} to {}", version, current_version);
+    Ok(())
+}
+
+// Layouts that have only grown by appending fields that read as empty from
+// the zeroed bytes grow_account adds
+fn no_upgrade(_version: u8, _data: &mut [u8]) -> Result<()> {
+    Ok(())
+}
+
+// Rewrites a game from the layout the program first shipped with. Its
+// commitments used a hash that is not bound to the game, so they are cleared
+// and both players commit again; the deposits stay in the account
+fn upgrade_legacy_game(old: LegacyGameState) -> Result<GameState> {
+    let status = match old.status {
+        0 => GameStatus::Open,
+        1 => GameStatus::Committed,
+        2 => GameStatus::Ended,
+        _ => return err!(ErrorCode::InvalidMigrationAccount),
+    };
+    // The legacy program never paid out, so ended games still hold both
+    // wagers
+    let escrow = match status {
+        GameStatus::Open => old.wager,
+        _ => old
+            .wager
+            .checked_mul(2)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+    };
+    // The migration stands in for the unrecorded creation time
+    let clock = Clock::get()?;
+    Ok(GameState {
+        version: GAME_STATE_VERSION,
+        status,
+        creator: old.creator,
+        wager: old.wager,
+        opponent: old.opponent,
+        creator_move_hashed: [0u8; 32],
+        joiner_move_hashed: [0u8; 32],
+        creator_move: None,
+        joiner_move: None,
+        flags: 0,
+        escrow,
+        bump: old.bump,
+        nonce: clock.slot,
+        created_at: clock.unix_timestamp,
+        join_deadline: clock
+            .unix_timestamp
+            .checked_add(JOIN_WINDOW_SECONDS)
+            .ok_or(ErrorCode::ArithmeticOverflow)?,
+        reveal_deadline: 0,
+        league: None,
+        reserved_for: None,
+        creator_nft: None,
+        joiner_nft: None,
+        assets: Vec::new(),
+        reveal_order: RevealOrder::Any,
+        bond: 0,
+        min_reputation: 0,
+        tie_break_seed: [0u8; 32],
+        sudden_death_rounds: 0,
+        time_bank: 0,
+        creator_time_left: 0,
+        joiner_time_left: 0,
+        phase_started_at: clock.unix_timestamp,
+        counter_offer: None,
+        spectators: 0,
+        creator_sponsor: None,
+        joiner_sponsor: None,
+        fee_mode: FeeMode::Pot,
+        creator_cover: 0,
+        joiner_cover: 0,
+        move_count: CLASSIC_MOVE_COUNT,
+        ruleset: None,
+        beats: [0; MAX_MOVE_COUNT as usize],
+        label: [0u8; MAX_LABEL_LEN],
+        category: GameCategory::Casual,
+        index: 0,
+        prev_game: None,
+        joined_at: 0,
+        first_reveal_at: 0,
+        ended_at: 0,
+        winner: None,
+        result: None,
+        reveal_slot: 0,
+    })
+}
+
+// Grows a program account to `space` bytes with the payer covering the extra
+// rent. Lamports already in the account may be escrow, so only the rent
+// difference between the two sizes is charged
+fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    let current = account.data_len();
+    if current >= space {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?;
+    let extra_rent = rent
+        .minimum_balance(space)
+        .saturating_sub(rent.minimum_balance(current));
+    if extra_rent > 0 {
+        let ix = system_instruction::transfer(payer.key, account.key, extra_rent);
+        invoke(
+            &ix,
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    account.realloc(space, true)?;
+    Ok(())
+}
+
+// Lobby bucket an open game is listed in
+pub fn lobby_bucket(game: &Pubkey) -> u8 {
+    game.to_bytes()[0] % LOBBY_BUCKETS
+}
+
+// ------------------------------------
+// CPI Helpers
+// ------------------------------------
+// Used by programs that drive games through the `cpi` feature, e.g.
+// `rps_game::cpi::create_game` with a PDA of their own as the creator
+
+// Move commitment as stored by select_move, see COMMITMENT_HASH_SCHEME
+pub fn commitment_hash(game: &Pubkey, nonce: u64, original_move: u8, salt: &[u8]) -> [u8; 32] {
+    hashv(&[game.as_ref(), &nonce.to_le_bytes(), &[original_move], salt]).to_bytes()
+}
+
+// Game address under seed schema `seed_version`, see Config::seed_version
+pub fn find_game_address(seed_version: u8, creator: &Pubkey, wager: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            GAME_SEED,
+            seed_prefix(&seed_version),
+            creator.as_ref(),
+            &wager.to_le_bytes(),
+        ],
+        &ID,
+    )
+}
+
+// Only one spelling of each name is accepted, so lookups are exact
+fn is_valid_username(username: &str) -> bool {
+    (MIN_USERNAME_LEN..=MAX_USERNAME_LEN).contains(&username.len())
+        && username
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+// Schema 0 is the original layout without a version seed
+fn seed_prefix(seed_version: &u8) -> &[u8] {
+    if *seed_version == 0 {
+        &[]
+    } else {
+        std::slice::from_ref(seed_version)
+    }
+}
+
+// Commitment to a hidden wager: SHA-256 of wager (u64 LE) || salt
+pub fn wager_commitment(wager: u64, salt: &[u8]) -> [u8; 32] {
+    hashv(&[&wager.to_le_bytes(), salt]).to_bytes()
+}
+
+pub fn find_hidden_game_address(creator: &Pubkey, wager_commitment: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[HIDDEN_GAME_SEED, creator.as_ref(), wager_commitment], &ID)
+}
+
+pub fn find_profile_address(player: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PROFILE_SEED, player.as_ref()], &ID)
+}
+
+// Reverse lookup from a username to the record naming its wallet
+pub fn find_username_address(username: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[USERNAME_SEED, username.as_bytes()], &ID)
+}
+
+pub fn find_config_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], &ID)
+}
+
+pub fn find_lobby_address(game: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LOBBY_SEED, &[lobby_bucket(game)]], &ID)
+}
+
+pub fn find_player_stats_address(player: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PLAYER_STATS_SEED, player.as_ref()], &ID)
+}
+
+pub fn find_jackpot_vault_address() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[JACKPOT_VAULT_SEED], &ID)
+}
+
+// ORAO VRF randomness account requested for a tie, see GameState::tie_break_seed
+pub fn find_tie_break_randomness_address(seed: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ORAO_RANDOMNESS_SEED, seed], &ORAO_VRF_PROGRAM_ID)
+}
+
+// Checks the configured jackpot rule against the winning moves and the hash
+// of the first slot after the reveal phase began. Both commitments were
+// locked in before that hash existed, and revealing later can't change it.
+// Once it has aged out of the SlotHashes sysvar the jackpot can't trigger
+fn jackpot_triggered(
+    config: &Config,
+    winning_move: u8,
+    losing_move: u8,
+    slot_hashes: &AccountInfo,
+    reveal_slot: u64,
+) -> Result<bool> {
+    if winning_move != config.jackpot_winning_move || losing_move != config.jackpot_losing_move {
+        return Ok(false);
+    }
+    Ok(deciding_slot_hash(slot_hashes, reveal_slot)?
+        .is_some_and(|hash| hash[31] == config.jackpot_hash_byte))
+}
+
+// Pays out everything in the jackpot vault above its rent-exempt reserve
+fn pay_jackpot<'info>(
+    jackpot_vault: &AccountInfo<'info>,
+    winner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    bump: u8,
+) -> Result<()> {
+    let reserve = Rent::get()?.minimum_balance(0);
+    let jackpot = jackpot_vault.lamports().saturating_sub(reserve);
+    if jackpot == 0 {
+        return Ok(());
+    }
+    msg!("Jackpot! {} lamports", jackpot);
+
+    let ix = system_instruction::transfer(jackpot_vault.key, winner.key, jackpot);
+    invoke_signed(
+        &ix,
+        &[jackpot_vault.clone(), winner.clone(), system_program.clone()],
+        &[&[JACKPOT_VAULT_SEED, &[bump]]],
+    )?;
+    Ok(())
+}
+
+// Sets up a freshly created stats account and rolls existing ones into the
+// current season
+fn init_player_stats(
+    player_stats: &mut Account<PlayerStats>,
+    player: Pubkey,
+    bump: u8,
+    season_id: u32,
+) {
+    if player_stats.player == Pubkey::default() {
+        player_stats.version = PLAYER_STATS_VERSION;
+        player_stats.player = player;
+        player_stats.rating = INITIAL_RATING;
+        player_stats.reputation = INITIAL_REPUTATION;
+        player_stats.season.season_id = season_id;
+        player_stats.bump = bump;
+    }
+    player_stats.sync_season(season_id);
+}
+
+// Expected score (per mille) of the higher-rated player for rating gaps of
+// 0, 50, ..., 800 points
+const ELO_EXPECTED_PER_MILLE: [u32; 17] = [
+    500, 571, 640, 703, 760, 808, 849, 882, 909, 930, 947, 960, 969, 977, 983, 987, 990,
+];
+
+fn expected_score(rating: u32, opponent_rating: u32) -> u32 {
+    let gap = rating.abs_diff(opponent_rating).min(800);
+    let favored = ELO_EXPECTED_PER_MILLE[(gap / 50) as usize];
+    if rating >= opponent_rating {
+        favored
+    } else {
+        1000 - favored
+    }
+}
+
+fn updated_rating(rating: u32, opponent_rating: u32, outcome: Outcome) -> u32 {
+    let score: i64 = match outcome {
+        Outcome::Win => 1000,
+        Outcome::Tie => 500,
+        Outcome::Loss => 0,
+    };
+    let expected = expected_score(rating, opponent_rating) as i64;
+    let delta = ELO_K_FACTOR as i64 * (score - expected) / 1000;
+    (rating as i64 + delta).max(0) as u32
+}
+
+fn record_result(
+    winner: &RPSResult,
+    wager: u64,
+    config: &Config,
+    creator_stats: &mut Account<PlayerStats>,
+    joiner_stats: &mut Account<PlayerStats>,
+) -> Result<()> {
+    let (creator_outcome, joiner_outcome) = winner.outcomes();
+    let now = Clock::get()?.unix_timestamp;
+    // The loser's wager counts against their daily loss cap
+    match winner {
+        RPSResult::CreatorWins => joiner_stats.record_loss(wager, now),
+        RPSResult::JoinerWins => creator_stats.record_loss(wager, now),
+        RPSResult::Tie => {}
+    }
+    // Bot-vs-bot games are unrated and stay off the leaderboards
+    if creator_stats.bot && joiner_stats.bot {
+        return Ok(());
+    }
+    // Idle time before this game counts against both ratings first
+    for stats in [&mut **creator_stats, &mut **joiner_stats] {
+        stats.apply_decay(config.rating_decay_window, config.rating_decay_bps, now);
+        stats.rating_updated_at = now;
+    }
+    // Both updates use the ratings from before the game
+    let creator_rating = creator_stats.rating;
+    let joiner_rating = joiner_stats.rating;
+    creator_stats.record_game(creator_outcome, joiner_rating)?;
+    joiner_stats.record_game(joiner_outcome, creator_rating)?;
+    Ok(())
+}
+
+// Credits each player with half the house fee of their game, the odd
+// lamport going to the creator as in a tie split
+fn record_rake(
+    house_fee: u64,
+    creator_stats: &mut Account<PlayerStats>,
+    joiner_stats: &mut Account<PlayerStats>,
+) -> Result<()> {
+    let joiner_rake = house_fee / 2;
+    creator_stats.record_rake(house_fee - joiner_rake)?;
+    joiner_stats.record_rake(joiner_rake)?;
+    Ok(())
+}
+
+// Every CPI clears the return data, so this runs after a settling
+// instruction's last one, its event included
+fn return_settlement(game_account: &GameState, payout: u64, fee: u64) -> Result<()> {
+    let mut data = Vec::new();
+    SettlementResult {
+        winner: game_account.winner,
+        payout,
+        fee,
+    }
+    .serialize(&mut data)?;
+    set_return_data(&data);
+    Ok(())
+}
+
+// Where a player's winnings go: their wSOL account if they take payouts
+// wrapped, otherwise their wallet
+fn payout_account<'a, 'info>(
+    stats: &PlayerStats,
+    wallet: &'a AccountInfo<'info>,
+    wsol: &'a Option<Account<'info, TokenAccount>>,
+) -> Result<&'a AccountInfo<'info>> {
+    if !stats.wsol_payouts {
+        return Ok(wallet);
+    }
+    let wsol = wsol.as_ref().ok_or(ErrorCode::WsolAccountMissing)?;
+    Ok(wsol.as_ref())
+}
+
+// Lamports paid straight into a wSOL account only count as tokens once the
+// account is synced
+fn sync_wrapped_payouts<'info>(
+    token_program: &Option<Program<'info, Token>>,
+    wsol_accounts: [&Option<Account<'info, TokenAccount>>; 2],
+) -> Result<()> {
+    for wsol in wsol_accounts.into_iter().flatten() {
+        let token_program = token_program
+            .as_ref()
+            .ok_or(ErrorCode::WsolAccountMissing)?;
+        token::sync_native(CpiContext::new(
+            token_program.to_account_info(),
+            SyncNative {
+                account: wsol.to_account_info(),
+            },
+        ))?;
+    }
+    Ok(())
+}
+
+// Credits both players with the loyalty points a game of `wager` earns;
+// they are minted by claim_points
+fn award_points(
+    config: &Config,
+    wager: u64,
+    creator_stats: &mut Account<PlayerStats>,
+    joiner_stats: &mut Account<PlayerStats>,
+) -> Result<()> {
+    let points = (wager as u128 * config.points_per_sol as u128 / LAMPORTS_PER_SOL as u128) as u64;
+    creator_stats.award_points(points)?;
+    joiner_stats.award_points(points)?;
+    Ok(())
+}
+
+// Moves escrowed lamports out of the program-owned game account without
+// touching its rent-exempt reserve
+fn transfer_from_escrow(
+    game_account: &mut Account<GameState>,
+    to: &AccountInfo,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let game_info: &AccountInfo = game_account.as_ref();
+    let rent_floor = Rent::get()?.minimum_balance(game_info.data_len());
+
+    // The account must always hold the rent-exempt minimum plus the escrow
+    let required = rent_floor
+        .checked_add(game_account.escrow)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    require!(
+        game_info.lamports() >= required,
+        ErrorCode::EscrowInvariantViolated
+    );
+    move_lamports(game_info, to, amount)?;
+
+    game_account.escrow = game_account
+        .escrow
+        .checked_sub(amount)
+        .ok_or(ErrorCode::InsufficientEscrow)?;
+    Ok(())
+}
+
+// Moves lamports out of an account owned by this program
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let from_lamports = from.lamports();
+    let to_lamports = to.lamports();
+    **from.try_borrow_mut_lamports()? = from_lamports
+        .checked_sub(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    **to.try_borrow_mut_lamports()? = to_lamports
+        .checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    Ok(())
+}
+
+// ------------------------------------
+// Data Structures
+// ------------------------------------
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub enum GameStatus {
+    Open,
+    Committed,
+    Revealing,
+    Ended,
+    Hidden, // Open, with the wager still behind a commitment
+}
+
+// Zero-copy accounts store the status as its discriminant
+impl TryFrom<u8> for GameStatus {
+    type Error = Error;
+
+    fn try_from(status: u8) -> Result<Self> {
+        match status {
+            0 => Ok(GameStatus::Open),
+            1 => Ok(GameStatus::Committed),
+            2 => Ok(GameStatus::Revealing),
+            3 => Ok(GameStatus::Ended),
+            _ => err!(ErrorCode::InvalidGameStatus),
+        }
+    }
+}
+
+// Which player, if any, must reveal before the other may
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RevealOrder {
+    Any,
+    CreatorFirst,
+    JoinerFirst,
+}
+
+// What a tie does to the pot: split it, clear the moves and play again, or
+// award all of it to one player picked by the tie-break oracle
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TiePolicy {
+    Split,
+    Replay,
+    Random,
+}
+
+// What the house fee is taken from: the whole pot, or only the winner's profit
+// so the loser never pays rake on their own deposit and a tie pays none
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FeeMode {
+    Pot,
+    WinnerProfit,
+}
+
+// What kind of match an open game is advertised as, for lobby filtering
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameCategory {
+    Casual,
+    Ranked,
+    HighStakes,
+    Streamer,
+    TournamentQualifier,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Badge {
+    FirstWin,
+    WinStreak10,
+    HundredGames,
+}
+
+impl Badge {
+    pub fn flag(self) -> u8 {
+        1 << (self as u8)
+    }
+
+    pub fn is_earned(self, stats: &PlayerStats) -> bool {
+        match self {
+            Badge::FirstWin => stats.wins >= 1,
+            Badge::WinStreak10 => stats.best_win_streak >= 10,
+            Badge::HundredGames => stats.games_played >= 100,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Badge::FirstWin => "RPS First Win",
+            Badge::WinStreak10 => "RPS 10-Win Streak",
+            Badge::HundredGames => "RPS 100 Games",
+        }
+    }
+
+    pub fn slug(self) -> &'static str {
+        match self {
+            Badge::FirstWin => "first-win",
+            Badge::WinStreak10 => "win-streak-10",
+            Badge::HundredGames => "hundred-games",
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Achievement {
+    FirstWin,
+    UnderdogWin,   // Beat a higher-rated player
+    FiveWinStreak,
+    TenGames,
+}
+
+impl Achievement {
+    pub fn flag(self) -> u16 {
+        1 << (self as u16)
+    }
+}
+
+// A finished game from one player's point of view. The discriminants are
+// stored in history entries, where 0 marks an empty slot
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Outcome {
+    Win = 1,
+    Loss = 2,
+    Tie = 3,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RPSResult {
+    CreatorWins,
+    JoinerWins,
+    Tie,
+}
+
+impl RPSResult {
+    // Creator's and joiner's outcomes
+    fn outcomes(self) -> (Outcome, Outcome) {
+        match self {
+            RPSResult::CreatorWins => (Outcome::Win, Outcome::Loss),
+            RPSResult::JoinerWins => (Outcome::Loss, Outcome::Win),
+            RPSResult::Tie => (Outcome::Tie, Outcome::Tie),
+        }
+    }
+}
+
+// How a game ended: played out or forfeited, or called off with the
+// deposits returned
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    CreatorWins,
+    JoinerWins,
+    Tie,
+    Cancelled, // By the creator before anyone joined
+    Expired,   // Collected by gc_game after the join deadline
+    Refunded,  // Stale or flagged, both deposits returned
+}
+
+impl From<RPSResult> for GameResult {
+    fn from(result: RPSResult) -> Self {
+        match result {
+            RPSResult::CreatorWins => GameResult::CreatorWins,
+            RPSResult::JoinerWins => GameResult::JoinerWins,
+            RPSResult::Tie => GameResult::Tie,
+        }
+    }
+}
+
+// Game summary returned by get_game, see GAME_VIEW_VERSION
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct GameView {
+    pub view_version: u8,
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub opponent: Option<Pubkey>,
+    pub wager: u64,
+    pub escrow: u64,
+    pub status: GameStatus,
+    pub creator_move: Option<u8>, // Set once revealed
+    pub joiner_move: Option<u8>,
+    pub creator_ready: bool,
+    pub joiner_ready: bool,
+    pub created_at: i64,
+    pub join_deadline: i64,
+    pub reveal_deadline: i64,
+    pub league: Option<Pubkey>,
+    // Version 2
+    pub winner: Option<Pubkey>,
+    pub result: Option<GameResult>,
+}
+
+// Changes applied by update_config; `None` keeps the current value
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ConfigUpdate {
+    pub paused: Option<bool>,
+    pub min_wager: Option<u64>,
+    pub max_wager: Option<u64>,
+    pub house: Option<Pubkey>,
+    pub house_fee_bps: Option<u16>,
+    pub jackpot_share_bps: Option<u16>,
+}
+
+// Settings a game created from a preset starts with
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PresetSettings {
+    pub wager: u64,
+    pub join_window: i64, // Seconds until the join deadline
+    pub tie_policy: TiePolicy,
+    pub reveal_order: RevealOrder,
+    pub time_bank: i64,
+    pub min_reputation: u32,
+    pub invite_only: bool,
+    pub approval_required: bool,
+}
+
+impl PresetSettings {
+    pub const SIZE: usize = 8 // wager
+        + 8 // join_window
+        + 1 // tie_policy
+        + 1 // reveal_order
+        + 8 // time_bank
+        + 4 // min_reputation
+        + 1 // invite_only
+        + 1; // approval_required
+}
+
+// Outcome of a settled game, written to return data so a program that CPIs
+// into a settling instruction can read it with get_return_data
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SettlementResult {
+    pub winner: Option<Pubkey>, // None on a tie
+    pub payout: u64,            // Lamports paid to the players, both shares on a tie
+    pub fee: u64,               // House fee, including the jackpot cut
+}
+
+// Projected settlement returned by simulate_result
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SimulatedResult {
+    pub result: RPSResult,
+    pub replay: bool,   // A tie the game replays: nothing is paid out
+    pub house_fee: u64, // Including the jackpot cut
+    pub jackpot_cut: u64,
+    pub creator_payout: u64,
+    pub joiner_payout: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeeRecipient {
+    pub recipient: Pubkey,
+    pub weight_bps: u16,
+}
+
+impl FeeRecipient {
+    pub const SIZE: usize = 32 // recipient
+        + 2; // weight_bps
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeeTier {
+    pub max_wager: u64, // Largest wager (in lamports) the tier covers
+    pub fee_bps: u16,
+}
+
+impl FeeTier {
+    pub const SIZE: usize = 8 // max_wager
+        + 2; // fee_bps
+}
+
+#[account]
+#[derive(Debug)]
+pub struct Config {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub paused: bool,
+    pub min_wager: u64,
+    pub max_wager: u64,
+    pub badge_tree: Pubkey,
+    pub badge_uri_prefix: String,
+    pub achievement_rewards: [u64; ACHIEVEMENT_SLOTS],
+    pub season_id: u32,
+    pub house: Pubkey,
+    pub house_fee_bps: u16,
+    pub jackpot_share_bps: u16,
+    pub jackpot_winning_move: u8,
+    pub jackpot_losing_move: u8,
+    pub jackpot_hash_byte: u8,
+    pub nft_fee: u64, // Lamports charged per staked NFT
+    pub fee_recipients: Vec<FeeRecipient>,
+    pub bump: u8,
+    pub banned: Vec<Pubkey>, // Players blocked from creating or joining games
+    pub fee_holiday_start: i64,
+    pub fee_holiday_end: i64,
+    pub fee_holiday_bps: u16, // House fee charged during the holiday
+    pub fee_tiers: Vec<FeeTier>,
+    pub rakeback_bps: u16, // Share of the fees paid that players can claim back
+    pub rakeback_threshold: u64, // Fees a player must have paid before claiming
+    pub points_per_sol: u64,     // Loyalty points per SOL wagered; 0 disables them
+    pub points_tradeable: bool,
+    pub tie_break_vrf: bool, // Ties broken at random use ORAO VRF; while off they are split
+    pub surrender_refund_bps: u16, // Share of their stake a surrendering player gets back
+    pub seed_version: u8, // Seed schema of new game addresses, see game_seed_prefix
+    pub previous_seed_version: u8,
+    pub seed_migration_ends: i64, // Until then the previous schema is accepted too
+    pub rating_decay_window: i64, // Idle seconds per decay step; 0 disables decay
+    pub rating_decay_bps: u16,    // Share of the gap to the mean lost per step
+    pub weekly_prize_pool: u64,   // Lamports the treasury pays each leaderboard week
+    pub daily_wager_cap: u64,     // Lamports a wallet may wager per 24h; 0 for no cap
+    pub creation_cooldown: i64,   // Seconds between game creations per wallet
+    pub creation_stake_mint: Pubkey, // Token lobby creators must hold; default for none
+    pub creation_stake_min: u64,  // Least of it they must hold
+    pub creation_fee: u64,        // Flat lamports lobby creators pay the treasury
+    pub waive_draw_fees: bool,    // Split ties refund both stakes in full
+    pub insurance_premium_bps: u16, // Of the wager, paid to insure it; 0 disables insurance
+    pub insurance_refund_bps: u16,  // Of the wager, refunded to an insured loser
+    pub max_open_games: u16,        // Unjoined games per wallet; 0 for no cap
+    pub game_count: u64,            // Games indexed so far, see index_game
+    pub latest_game: Option<Pubkey>, // Head of the game index
+}
+
+impl Config {
+    pub const MAX_SIZE: usize = 1 // version
+        + 32 // admin pubkey
+        + 1 // paused
+        + 8 // min_wager
+        + 8 // max_wager
+        + 32 // badge_tree
+        + 4 + MAX_BADGE_URI_PREFIX_LEN // badge_uri_prefix
+        + 8 * ACHIEVEMENT_SLOTS // achievement_rewards
+        + 4 // season_id
+        + 32 // house
+        + 2 // house_fee_bps
+        + 2 // jackpot_share_bps
+        + 1 // jackpot_winning_move
+        + 1 // jackpot_losing_move
+        + 1 // jackpot_hash_byte
+        + 8 // nft_fee
+        + 4 + FeeRecipient::SIZE * MAX_FEE_RECIPIENTS // fee_recipients
+        + 1 // bump
+        + 4 + 32 * MAX_BANNED_PLAYERS // banned
+        + 8 // fee_holiday_start
+        + 8 // fee_holiday_end
+        + 2 // fee_holiday_bps
+        + 4 + FeeTier::SIZE * MAX_FEE_TIERS // fee_tiers
+        + 2 // rakeback_bps
+        + 8 // rakeback_threshold
+        + 8 // points_per_sol
+        + 1 // points_tradeable
+        + 1 // tie_break_vrf
+        + 2 // surrender_refund_bps
+        + 1 // seed_version
+        + 1 // previous_seed_version
+        + 8 // seed_migration_ends
+        + 8 // rating_decay_window
+        + 2 // rating_decay_bps
+        + 8 // weekly_prize_pool
+        + 8 // daily_wager_cap
+        + 8 // creation_cooldown
+        + 32 // creation_stake_mint
+        + 8 // creation_stake_min
+        + 8 // creation_fee
+        + 1 // waive_draw_fees
+        + 2 // insurance_premium_bps
+        + 2 // insurance_refund_bps
+        + 2 // max_open_games
+        + 8 // game_count
+        + 1 + 32; // optional latest_game
+
+    // Seed the schema version adds to a game address. `game` may be derived
+    // with the previous schema while the migration window is open
+    fn game_seed_prefix(&self, game: &Pubkey, creator: &Pubkey, wager: u64) -> &[u8] {
+        let previous = seed_prefix(&self.previous_seed_version);
+        let migrating = Clock::get()
+            .map(|clock| clock.unix_timestamp < self.seed_migration_ends)
+            .unwrap_or(false);
+        if migrating && find_game_address(self.previous_seed_version, creator, wager).0 == *game {
+            previous
+        } else {
+            seed_prefix(&self.seed_version)
+        }
+    }
+
+    // House fee for a pot of `wager` per player settled at `now`; a fee
+    // holiday overrides the wager tiers
+    fn fee_bps_for(&self, wager: u64, now: i64) -> u16 {
+        if (self.fee_holiday_start..self.fee_holiday_end).contains(&now) {
+            return self.fee_holiday_bps;
+        }
+        self.fee_tiers
+            .iter()
+            .find(|tier| wager <= tier.max_wager)
+            .map_or(self.house_fee_bps, |tier| tier.fee_bps)
+    }
+
+    // Fails if `player` is on the ban list
+    fn check_not_banned(&self, player: &Pubkey) -> Result<()> {
+        require!(!self.banned.contains(player), ErrorCode::PlayerBanned);
+        Ok(())
+    }
+
+    // Whether the creator's token account holds the stake required to list
+    // a game in the lobby; its owner is checked by the accounts struct
+    fn check_creation_stake(&self, stake: Option<&TokenAccount>) -> Result<()> {
+        if self.creation_stake_mint == Pubkey::default() {
+            return Ok(());
+        }
+        require!(
+            stake.is_some_and(|stake| {
+                stake.mint == self.creation_stake_mint && stake.amount >= self.creation_stake_min
+            }),
+            ErrorCode::CreationStakeRequired
+        );
+        Ok(())
+    }
+}
+
+// A prospective joiner's proposed wager for an open game
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CounterOffer {
+    pub joiner: Pubkey,
+    pub wager: u64,
+}
+
+impl CounterOffer {
+    pub const SIZE: usize = 32 // joiner pubkey
+        + 8; // wager
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct LobbyEntry {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub wager: u64,
+    pub category: GameCategory,
+}
+
+impl LobbyEntry {
+    pub const SIZE: usize = 32 // game pubkey
+        + 32 // creator pubkey
+        + 8 // wager
+        + 1; // category
+}
+
+#[account]
+#[derive(Debug)]
+pub struct Lobby {
+    pub bucket: u8,
+    pub games: Vec<LobbyEntry>,
+    pub bump: u8,
+}
+
+impl Lobby {
+    pub const MAX_SIZE: usize = 1 // bucket
+        + 4 + LobbyEntry::SIZE * LOBBY_BUCKET_CAPACITY // games
+        + 1; // bump
+
+    // A full bucket leaves the game unlisted rather than blocking creation
+    fn register(&mut self, entry: LobbyEntry) {
+        if self.games.len() < LOBBY_BUCKET_CAPACITY {
+            self.games.push(entry);
+        } else {
+            msg!("Lobby bucket {} is full; game not listed", self.bucket);
+        }
+    }
+
+    fn deregister(&mut self, game: &Pubkey) {
+        self.games.retain(|entry| entry.game != *game);
+    }
+
+    // Keeps a listed game's stake current
+    fn update_wager(&mut self, game: &Pubkey, wager: u64) {
+        if let Some(entry) = self.games.iter_mut().find(|entry| entry.game == *game) {
+            entry.wager = wager;
+        }
+    }
+
+    fn update_category(&mut self, game: &Pubkey, category: GameCategory) {
+        if let Some(entry) = self.games.iter_mut().find(|entry| entry.game == *game) {
+            entry.category = category;
+        }
+    }
+}
+
+// Shard map of the leaderboard, see initialize_leaderboard
+#[account]
+#[derive(Debug)]
+pub struct Leaderboard {
+    pub shard_count: u8,
+    pub base_rating: u32, // Where shard 0's band starts
+    pub band_width: u32,  // Rating points per shard
+    pub bump: u8,
+}
+
+impl Leaderboard {
+    pub const MAX_SIZE: usize = 1 // shard_count
+        + 4 // base_rating
+        + 4 // band_width
+        + 1; // bump
+
+    fn shard_for(&self, rating: u32) -> u8 {
+        let band = rating.saturating_sub(self.base_rating) / self.band_width;
+        band.min(self.shard_count as u32 - 1) as u8
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct LeaderboardEntry {
+    pub player: Pubkey,
+    pub rating: u32, // As of the player's last update_standing
+}
+
+impl LeaderboardEntry {
+    pub const SIZE: usize = 32 // player pubkey
+        + 4; // rating
+}
+
+// One rating band of the leaderboard, best rating first
+#[account]
+#[derive(Debug)]
+pub struct LeaderboardShard {
+    pub index: u8,
+    pub entries: Vec<LeaderboardEntry>,
+    pub bump: u8,
+}
+
+impl LeaderboardShard {
+    pub const MAX_SIZE: usize = 1 // index
+        + 4 + LeaderboardEntry::SIZE * LEADERBOARD_SHARD_CAPACITY // entries
+        + 1; // bump
+
+    // Keeps the entries sorted, dropping the lowest when the shard overflows
+    fn insert(&mut self, entry: LeaderboardEntry) {
+        let position = self.entries.partition_point(|e| e.rating >= entry.rating);
+        if position >= LEADERBOARD_SHARD_CAPACITY {
+            msg!("Leaderboard shard {} is full; player not listed", self.index);
+            return;
+        }
+        self.entries.insert(position, entry);
+        self.entries.truncate(LEADERBOARD_SHARD_CAPACITY);
+    }
+
+    // Whether the player was listed
+    fn remove(&mut self, player: &Pubkey) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.player != *player);
+        self.entries.len() < len
+    }
+}
+
+// Final standings of a leaderboard week; only the claimed bits change
+#[account]
+#[derive(Debug)]
+pub struct LeaderboardSnapshot {
+    pub week: u64, // Weeks since the Unix epoch
+    pub winners: Vec<LeaderboardEntry>, // Best first
+    pub prizes: Vec<u64>, // Lamports per place, held by this account
+    pub claimed: u8,      // Bit per place
+    pub bump: u8,
+}
+
+impl LeaderboardSnapshot {
+    pub const MAX_SIZE: usize = 8 // week
+        + 4 + LeaderboardEntry::SIZE * WEEKLY_PRIZE_SPLIT_BPS.len() // winners
+        + 4 + 8 * WEEKLY_PRIZE_SPLIT_BPS.len() // prizes
+        + 1 // claimed
+        + 1; // bump
+}
+
+#[account]
+#[derive(Debug)]
+pub struct MatchQueue {
+    pub band: u8,
+    pub wager: u64,
+    pub waiting: Vec<Pubkey>,
+    pub matches: u64,
+    pub bump: u8,
+}
+
+impl MatchQueue {
+    pub const MAX_SIZE: usize = 1 // band
+        + 8 // wager
+        + 4 + 32 * QUEUE_CAPACITY // waiting
+        + 8 // matches
+        + 1; // bump
+}
+
+// Per-season standings kept alongside the lifetime totals
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct SeasonRecord {
+    pub season_id: u32,
+    pub games_played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    pub rating: u32, // Final rating; only set on archived seasons
+}
+
+impl SeasonRecord {
+    pub const SIZE: usize = 4 // season_id
+        + 4 // games_played
+        + 4 // wins
+        + 4 // losses
+        + 4 // ties
+        + 4; // rating
+}
+
+#[account]
+#[derive(Debug)]
+pub struct PlayerStats {
+    pub version: u8,
+    pub player: Pubkey,
+    pub games_played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    pub win_streak: u32,
+    pub best_win_streak: u32,
+    pub rating: u32, // Current-season rating
+    pub season: SeasonRecord,
+    pub previous_season: SeasonRecord,
+    pub badges_minted: u8,
+    pub achievements: u16,
+    pub achievements_claimed: u16,
+    pub session_key: Pubkey,
+    pub session_expires_at_slot: u64,
+    pub session_scope: u8,
+    pub bump: u8,
+    pub reputation: u32, // Gates games with a minimum reputation
+    pub rake_paid: u64,        // This player's share of the house fees paid
+    pub rakeback_claimed: u64, // Rakeback already paid out from the treasury
+    pub points_unclaimed: u64, // Loyalty points earned but not yet minted
+    pub wsol_payouts: bool,    // Winnings are paid as wSOL, see set_wsol_payouts
+    pub rating_updated_at: i64, // Last rated game or decay step
+    pub max_wager_limit: u64,   // Self-imposed wager limit, 0 for none
+    pub daily_loss_limit: u64,  // Self-imposed daily loss cap, 0 for none
+    pub excluded_until: i64,    // No new games before this timestamp
+    pub limits_locked_until: i64, // Limits can only be tightened until then
+    pub loss_day: i64,          // Day since the epoch that day_losses counts
+    pub day_losses: u64,        // Wagers lost on loss_day
+    pub volume_window_start: i64, // Start of the 24h window wager_volume counts
+    pub wager_volume: u64,      // Lamports wagered since volume_window_start
+    pub last_action_ts: i64,    // When the player last created a game
+    pub bot: bool,              // Registered as a bot, see register_bot
+    pub open_games: u16,        // Games of theirs nobody has joined yet
+}
+
+impl PlayerStats {
+    pub const MAX_SIZE: usize = 1 // version
+        + 32 // player pubkey
+        + 4 // games_played
+        + 4 // wins
+        + 4 // losses
+        + 4 // ties
+        + 4 // win_streak
+        + 4 // best_win_streak
+        + 4 // rating
+        + SeasonRecord::SIZE // season
+        + SeasonRecord::SIZE // previous_season
+        + 1 // badges_minted
+        + 2 // achievements
+        + 2 // achievements_claimed
+        + 32 // session_key
+        + 8 // session_expires_at_slot
+        + 1 // session_scope
+        + 1 // bump
+        + 4 // reputation
+        + 8 // rake_paid
+        + 8 // rakeback_claimed
+        + 8 // points_unclaimed
+        + 1 // wsol_payouts
+        + 8 // rating_updated_at
+        + 8 // max_wager_limit
+        + 8 // daily_loss_limit
+        + 8 // excluded_until
+        + 8 // limits_locked_until
+        + 8 // loss_day
+        + 8 // day_losses
+        + 8 // volume_window_start
+        + 8 // wager_volume
+        + 8 // last_action_ts
+        + 1 // bot
+        + 2; // open_games
+
+    // Whether `signer` is a live session key granted `scope`
+    fn session_allows(&self, signer: &Pubkey, scope: u8, slot: u64) -> bool {
+        self.session_key != Pubkey::default()
+            && self.session_key == *signer
+            && self.session_scope & scope == scope
+            && slot <= self.session_expires_at_slot
+    }
+
+    fn award_points(&mut self, points: u64) -> Result<()> {
+        self.points_unclaimed = self
+            .points_unclaimed
+            .checked_add(points)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    // Refuses a new game the player has ruled out for themselves: any game
+    // while self-excluded, or a wager over their limit or that could take
+    // today's losses past their cap
+    fn check_limits(&self, wager: u64, now: i64) -> Result<()> {
+        require!(now >= self.excluded_until, ErrorCode::SelfExcluded);
+        require!(
+            self.max_wager_limit == 0 || wager <= self.max_wager_limit,
+            ErrorCode::OverPlayerLimit
+        );
+        if self.daily_loss_limit > 0 {
+            let losses = if self.loss_day == now / DAY_SECONDS {
+                self.day_losses
+            } else {
+                0
+            };
+            require!(
+                losses.saturating_add(wager) <= self.daily_loss_limit,
+                ErrorCode::OverPlayerLimit
+            );
+        }
+        Ok(())
+    }
+
+    // Applies the player's own limits and the operator's daily cap to a
+    // new game at `wager`, then counts it towards the player's volume. A
+    // window opens with the first wager after the previous one closed and
+    // lasts a day
+    fn admit_wager(&mut self, wager: u64, daily_cap: u64, now: i64) -> Result<()> {
+        self.check_limits(wager, now)?;
+        if now.saturating_sub(self.volume_window_start) >= DAY_SECONDS {
+            self.volume_window_start = now;
+            self.wager_volume = 0;
+        }
+        let volume = self
+            .wager_volume
+            .checked_add(wager)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(
+            daily_cap == 0 || volume <= daily_cap,
+            ErrorCode::DailyWagerCapReached
+        );
+        self.wager_volume = volume;
+        Ok(())
+    }
+
+    // Takes back part of a wager admitted at `admitted_at`, unless the
+    // window that counted it has already rolled over
+    fn release_wager(&mut self, amount: u64, admitted_at: i64) {
+        if admitted_at >= self.volume_window_start {
+            self.wager_volume = self.wager_volume.saturating_sub(amount);
+        }
+    }
+
+    // Throttles game creation to one per `cooldown` seconds
+    fn record_creation(&mut self, cooldown: i64, now: i64) -> Result<()> {
+        require!(
+            now >= self.last_action_ts.saturating_add(cooldown),
+            ErrorCode::CreationCooldown
+        );
+        self.last_action_ts = now;
+        Ok(())
+    }
+
+    fn open_game(&mut self, max_open_games: u16) -> Result<()> {
+        require!(
+            max_open_games == 0 || self.open_games < max_open_games,
+            ErrorCode::TooManyOpenGames
+        );
+        self.open_games = self
+            .open_games
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    // Saturates since migrated legacy games were never counted
+    fn close_open_game(&mut self) {
+        self.open_games = self.open_games.saturating_sub(1);
+    }
+
+    fn record_loss(&mut self, wager: u64, now: i64) {
+        let day = now / DAY_SECONDS;
+        if self.loss_day != day {
+            self.loss_day = day;
+            self.day_losses = 0;
+        }
+        self.day_losses = self.day_losses.saturating_add(wager);
+    }
+
+    fn record_rake(&mut self, rake: u64) -> Result<()> {
+        self.rake_paid = self
+            .rake_paid
+            .checked_add(rake)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    // Takes back the point record_game gave the game and applies the
+    // timeout penalty on top
+    fn record_timeout(&mut self) {
+        self.reputation = self
+            .reputation
+            .saturating_sub(REPUTATION_GAIN + REPUTATION_TIMEOUT_PENALTY);
+    }
+
+    // Moves a rating above the mean `decay_bps` of the way back to it for
+    // each full `window` since the rating last changed, at most all the way.
+    // Ratings below the mean are left alone so sitting out never pays.
+    // Returns whether any idle window was charged
+    fn apply_decay(&mut self, window: i64, decay_bps: u16, now: i64) -> bool {
+        if window == 0 || decay_bps == 0 {
+            return false;
+        }
+        let periods = now.saturating_sub(self.rating_updated_at) / window;
+        if periods <= 0 {
+            return false;
+        }
+        let share = (periods as u64)
+            .saturating_mul(decay_bps as u64)
+            .min(BPS_DENOMINATOR);
+        let gap = self.rating.saturating_sub(INITIAL_RATING) as u64;
+        self.rating -= (gap * share / BPS_DENOMINATOR) as u32;
+        self.rating_updated_at = self
+            .rating_updated_at
+            .saturating_add(periods.saturating_mul(window));
+        true
+    }
+
+    // Archives the finished season and starts fresh standings; lifetime
+    // totals are kept
+    fn sync_season(&mut self, season_id: u32) {
+        if self.season.season_id == season_id {
+            return;
+        }
+        self.previous_season = SeasonRecord {
+            rating: self.rating,
+            ..self.season.clone()
+        };
+        self.season = SeasonRecord {
+            season_id,
+            ..SeasonRecord::default()
+        };
+        self.rating = INITIAL_RATING;
+    }
+
+    // Rating as of `season_id`; a new season starts over from INITIAL_RATING
+    fn rating_in(&self, season_id: u32) -> u32 {
+        if self.season.season_id == season_id {
+            self.rating
+        } else {
+            INITIAL_RATING
+        }
+    }
+
+    // Applies a finished game: counters, ELO rating and achievements
+    fn record_game(&mut self, outcome: Outcome, opponent_rating: u32) -> Result<()> {
+        let rating_before = self.rating;
+
+        self.games_played = self
+            .games_played
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.season.games_played = self
+            .season
+            .games_played
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        match outcome {
+            Outcome::Win => {
+                self.wins = self
+                    .wins
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.season.wins = self
+                    .season
+                    .wins
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.win_streak = self
+                    .win_streak
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.best_win_streak = self.best_win_streak.max(self.win_streak);
+            }
+            Outcome::Loss => {
+                self.losses = self
+                    .losses
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.season.losses = self
+                    .season
+                    .losses
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.win_streak = 0;
+            }
+            Outcome::Tie => {
+                self.ties = self
+                    .ties
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.season.ties = self
+                    .season
+                    .ties
+                    .checked_add(1)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?;
+                self.win_streak = 0;
+            }
+        }
+        self.rating = updated_rating(rating_before, opponent_rating, outcome);
+        self.reputation = self.reputation.saturating_add(REPUTATION_GAIN);
+
+        // Evaluate achievements against the post-game record
+        if outcome == Outcome::Win {
+            self.unlock(Achievement::FirstWin);
+            if opponent_rating > rating_before {
+                self.unlock(Achievement::UnderdogWin);
+            }
+        }
+        if self.win_streak >= 5 {
+            self.unlock(Achievement::FiveWinStreak);
+        }
+        if self.games_played >= 10 {
+            self.unlock(Achievement::TenGames);
+        }
+        Ok(())
+    }
+
+    fn unlock(&mut self, achievement: Achievement) {
+        if self.achievements & achievement.flag() == 0 {
+            self.achievements |= achievement.flag();
+            debug_msg!("Achievement unlocked: {:?}", achievement);
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PotAsset {
+    pub mint: Pubkey,
+    pub amount: u64, // Per side
+    pub joiner_deposited: bool,
+    pub settled: bool,
+}
+
+impl PotAsset {
+    pub const SIZE: usize = 32 // mint
+        + 8 // amount
+        + 1 // joiner_deposited
+        + 1; // settled
+}
+
+#[account]
+#[derive(Debug)]
+pub struct GameState {
+    pub version: u8,
+    pub status: GameStatus,       // At GAME_STATUS_OFFSET
+    pub creator: Pubkey,          // At GAME_CREATOR_OFFSET
+    pub wager: u64,               // At GAME_WAGER_OFFSET
+    pub opponent: Option<Pubkey>, // At GAME_OPPONENT_OFFSET
+    pub creator_move_hashed: [u8; 32],
+    pub joiner_move_hashed: [u8; 32],
+    pub creator_move: Option<u8>,
+    pub joiner_move: Option<u8>,
+    pub flags: u16, // GAME_FLAG_* bits
+    pub escrow: u64,
+    pub bump: u8,
+    pub nonce: u64,
+    pub created_at: i64,
+    pub join_deadline: i64,
+    pub reveal_deadline: i64,
+    pub league: Option<Pubkey>,
+    pub reserved_for: Option<Pubkey>, // Only this player may join
+    pub creator_nft: Option<Pubkey>,  // Mint of the creator's staked NFT
+    pub joiner_nft: Option<Pubkey>,   // Mint of the joiner's staked NFT
+    pub assets: Vec<PotAsset>,        // SPL tokens in the pot besides SOL
+    pub reveal_order: RevealOrder,
+    pub bond: u64, // Reveal bond each player has posted, returned at settlement
+    pub min_reputation: u32, // Reputation a joiner needs
+    pub tie_break_seed: [u8; 32], // Seed of the pending VRF request, zeroed when none
+    pub sudden_death_rounds: u8,  // Rounds replayed after ties
+    pub time_bank: i64,           // Seconds each player gets per match; 0 disables the clocks
+    pub creator_time_left: i64,
+    pub joiner_time_left: i64,
+    pub phase_started_at: i64, // When the current commit or reveal phase began
+    pub counter_offer: Option<CounterOffer>, // Pending wager negotiation
+    pub spectators: u32, // Times spectate was called, a popularity signal
+    pub creator_sponsor: Option<Pubkey>, // Fronted the creator's wager, repaid first
+    pub joiner_sponsor: Option<Pubkey>,  // Fronted the joiner's wager, repaid first
+    pub fee_mode: FeeMode,
+    pub creator_cover: u64, // Insured refund owed to the creator on a loss
+    pub joiner_cover: u64,  // Insured refund owed to the joiner on a loss
+    pub move_count: u8,     // Odd, CLASSIC_MOVE_COUNT up to MAX_MOVE_COUNT, unless set by a ruleset
+    pub ruleset: Option<Pubkey>, // Ruleset the game was created from
+    pub beats: [u16; MAX_MOVE_COUNT as usize], // Copied from the ruleset; unused without one
+    pub label: [u8; MAX_LABEL_LEN], // Display name, zero-padded UTF-8
+    pub category: GameCategory,     // Mirrored into the lobby entry
+    pub index: u64, // Position in the game index, from 1; 0 for migrated games
+    pub prev_game: Option<Pubkey>, // The game indexed before this one
+    // Unix timestamps of the later lifecycle steps, zero until they happen
+    // and for games migrated past them
+    pub joined_at: i64,
+    pub first_reveal_at: i64,
+    pub ended_at: i64,
+    pub winner: Option<Pubkey>,     // Set when the game ends with one
+    pub result: Option<GameResult>, // How the game ended; None until it has
+    pub reveal_slot: u64, // When the reveal phase began, for the jackpot's slot hash
+}
+
+impl GameState {
+    pub const MAX_SIZE: usize = 1 // version
+        + 1 // status
+        + 32 // creator pubkey
+        + 8 // wager
+        + 1 + 32 // optional opponent pubkey
+        + 32 // creator_move_hashed
+        + 32 // joiner_move_hashed
+        + 1 + 1 // optional creator_move
+        + 1 + 1 // optional joiner_move
+        + 2 // flags
+        + 8 // escrow
+        + 1 // bump
+        + 8 // nonce
+        + 8 // created_at
+        + 8 // join_deadline
+        + 8 // reveal_deadline
+        + 1 + 32 // optional league
+        + 1 + 32 // optional reserved_for
+        + 1 + 32 // optional creator_nft
+        + 1 + 32 // optional joiner_nft
+        + 4 // assets, grown by add_pot_asset
+        + 1 // reveal_order
+        + 8 // bond
+        + 4 // min_reputation
+        + 32 // tie_break_seed
+        + 1 // sudden_death_rounds
+        + 8 // time_bank
+        + 8 // creator_time_left
+        + 8 // joiner_time_left
+        + 8 // phase_started_at
+        + 1 + CounterOffer::SIZE // optional counter_offer
+        + 4 // spectators
+        + 1 + 32 // optional creator_sponsor
+        + 1 + 32 // optional joiner_sponsor
+        + 1 // fee_mode
+        + 8 // creator_cover
+        + 8 // joiner_cover
+        + 1 // move_count
+        + 1 + 32 // optional ruleset
+        + 2 * MAX_MOVE_COUNT as usize // beats
+        + MAX_LABEL_LEN // label
+        + 1 // category
+        + 8 // index
+        + 1 + 32 // optional prev_game
+        + 8 // joined_at
+        + 8 // first_reveal_at
+        + 8 // ended_at
+        + 1 + 32 // optional winner
+        + 1 + 1 // optional result
+        + 8; // reveal_slot
+
+    // Records the final result so readers don't have to re-derive it
+    fn end(&mut self, result: GameResult) -> Result<()> {
+        self.status = GameStatus::Ended;
+        self.ended_at = Clock::get()?.unix_timestamp;
+        self.winner = match result {
+            GameResult::CreatorWins => Some(self.creator),
+            GameResult::JoinerWins => self.opponent,
+            _ => None,
+        };
+        self.result = Some(result);
+        Ok(())
+    }
+
+    // Stamps the first reveal of the game; later rounds keep it
+    fn record_reveal(&mut self, now: i64) {
+        if self.first_reveal_at == 0 {
+            self.first_reveal_at = now;
+        }
+    }
+
+    // Clears both commitments for a fresh round with the deposits kept
+    fn reset_round(&mut self) -> Result<()> {
+        self.creator_move_hashed = [0u8; 32];
+        self.joiner_move_hashed = [0u8; 32];
+        self.creator_move = None;
+        self.joiner_move = None;
+        self.set_flag(GAME_FLAG_CREATOR_READY | GAME_FLAG_JOINER_READY, false);
+        self.status = GameStatus::Committed;
+        let clock = Clock::get()?;
+        self.nonce = clock.slot;
+        self.phase_started_at = clock.unix_timestamp;
+        self.reveal_deadline = 0;
+        // A rematch plays on from an ended game
+        self.ended_at = 0;
+        self.winner = None;
+        self.result = None;
+        Ok(())
+    }
+
+    // Sudden-death rounds give the players less time to reveal
+    fn reveal_window(&self) -> i64 {
+        if self.sudden_death_rounds > 0 {
+            SUDDEN_DEATH_REVEAL_WINDOW_SECONDS
+        } else {
+            REVEAL_WINDOW_SECONDS
+        }
+    }
+
+    // Seconds left on a player's clock at `now`. The current phase counts
+    // against each player until they have acted in it
+    fn time_left(&self, creator: bool, now: i64) -> i64 {
+        let (banked, acted) = match (&self.status, creator) {
+            (GameStatus::Committed, true) => {
+                (self.creator_time_left, self.has_flag(GAME_FLAG_CREATOR_READY))
+            }
+            (GameStatus::Committed, false) => {
+                (self.joiner_time_left, self.has_flag(GAME_FLAG_JOINER_READY))
+            }
+            (GameStatus::Revealing, true) => (self.creator_time_left, self.creator_move.is_some()),
+            (GameStatus::Revealing, false) => (self.joiner_time_left, self.joiner_move.is_some()),
+            (_, true) => (self.creator_time_left, true),
+            (_, false) => (self.joiner_time_left, true),
+        };
+        if acted {
+            banked
+        } else {
+            banked.saturating_sub(now.saturating_sub(self.phase_started_at))
+        }
+    }
+
+    // Charges the current phase to a player acting in it. A player whose
+    // clock ran out can't act anymore and forfeits through claim_time_forfeit
+    fn charge_clock(&mut self, creator: bool, now: i64) -> Result<()> {
+        if self.time_bank == 0 {
+            return Ok(());
+        }
+        let left = self.time_left(creator, now);
+        require!(left > 0, ErrorCode::TimeBankExhausted);
+        if creator {
+            self.creator_time_left = left;
+        } else {
+            self.joiner_time_left = left;
+        }
+        Ok(())
+    }
+
+    // Rejects a move outside the game's move set
+    fn check_move(&self, player_move: u8) -> Result<()> {
+        require!(player_move < self.move_count, ErrorCode::InvalidMove);
+        Ok(())
+    }
+
+    // Result of two moves under the game's ruleset, or the cyclic rule for
+    // its move count
+    fn decide(&self, creator_move: u8, joiner_move: u8) -> Result<RPSResult> {
+        if self.ruleset.is_none() {
+            return decide_winner(creator_move, joiner_move, self.move_count);
+        }
+        self.check_move(creator_move)?;
+        self.check_move(joiner_move)?;
+        Ok(if self.beats[creator_move as usize] & (1 << joiner_move) != 0 {
+            RPSResult::CreatorWins
+        } else if self.beats[joiner_move as usize] & (1 << creator_move) != 0 {
+            RPSResult::JoinerWins
+        } else {
+            RPSResult::Tie
+        })
+    }
+
+    // Copies a ruleset's moves, tie policy and timing into a new game
+    fn apply_ruleset(&mut self, ruleset: &Account<Ruleset>) -> Result<()> {
+        self.ruleset = Some(ruleset.key());
+        self.move_count = ruleset.move_count;
+        self.beats = ruleset.beats;
+        self.set_tie_policy(ruleset.tie_policy);
+        self.join_deadline = self
+            .created_at
+            .checked_add(ruleset.join_window)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.time_bank = ruleset.time_bank;
+        self.creator_time_left = ruleset.time_bank;
+        self.joiner_time_left = ruleset.time_bank;
+        Ok(())
+    }
+
+    // Awards a tie in a handicap game to the lower-rated player
+    fn handicap_result(
+        &self,
+        winner: RPSResult,
+        creator_rating: u32,
+        joiner_rating: u32,
+    ) -> RPSResult {
+        if winner != RPSResult::Tie || !self.has_flag(GAME_FLAG_HANDICAP) {
+            return winner;
+        }
+        match creator_rating.cmp(&joiner_rating) {
+            Ordering::Less => RPSResult::CreatorWins,
+            Ordering::Greater => RPSResult::JoinerWins,
+            Ordering::Equal => RPSResult::Tie,
+        }
+    }
+
+    // Ties go to the VRF only while the config enables it
+    fn breaks_ties_at_random(&self, config: &Config) -> bool {
+        self.has_flag(GAME_FLAG_RANDOM_TIES) && config.tie_break_vrf
+    }
+
+    // A tie is waiting on the VRF's randomness
+    fn tie_break_pending(&self) -> bool {
+        self.tie_break_seed != [0u8; 32]
+    }
+
+    // The creator or the seated opponent
+    fn is_player(&self, key: &Pubkey) -> bool {
+        *key == self.creator || Some(*key) == self.opponent
+    }
+
+    // Split ties leave both tie flags clear
+    fn set_tie_policy(&mut self, tie_policy: TiePolicy) {
+        self.set_flag(GAME_FLAG_REPLAY_TIES, tie_policy == TiePolicy::Replay);
+        self.set_flag(GAME_FLAG_RANDOM_TIES, tie_policy == TiePolicy::Random);
+    }
+
+    // True when every bit of `flag` is set
+    fn has_flag(&self, flag: u16) -> bool {
+        self.flags & flag == flag
+    }
+
+    fn set_flag(&mut self, flag: u16, value: bool) {
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+
+    // NFTs and pot tokens must be released before the account can be closed
+    fn holds_escrowed_tokens(&self) -> bool {
+        self.creator_nft.is_some()
+            || self.joiner_nft.is_some()
+            || self.assets.iter().any(|asset| !asset.settled)
+    }
+}
+
+// GameState as the program first shipped it, before accounts carried a
+// version byte; read by migrate_game. Its status had no Revealing variant,
+// so it is read as the raw discriminant
+#[derive(AnchorDeserialize)]
+struct LegacyGameState {
+    creator: Pubkey,
+    opponent: Option<Pubkey>,
+    _creator_move_hashed: [u8; 32],
+    _joiner_move_hashed: [u8; 32],
+    _creator_ready: bool,
+    _joiner_ready: bool,
+    wager: u64,
+    status: u8,
+    bump: u8,
+}
+
+impl LegacyGameState {
+    const SIZE: usize = 32 // creator pubkey
+        + 1 + 32 // optional opponent pubkey
+        + 32 // creator_move_hashed
+        + 32 // joiner_move_hashed
+        + 1 // creator_ready
+        + 1 // joiner_ready
+        + 8 // wager
+        + 1 // status
+        + 1; // bump
+}
+
+// Zero-copy layouts: fixed-size arrays and plain integers so the accounts are
+// read in place instead of Borsh-decoded. Fields are ordered largest
+// alignment first to avoid implicit padding
+#[zero_copy]
+#[derive(Debug)]
+pub struct MultiSeat {
+    pub player: Pubkey,
+    pub commitment: [u8; 32], // Current round's commitment
+    pub revealed: u8,         // Current round's move, NO_MOVE until revealed
+    pub eliminated: u8,       // 0 or 1
+}
+
+impl MultiSeat {
+    fn revealed(&self) -> Option<u8> {
+        (self.revealed != NO_MOVE).then_some(self.revealed)
+    }
+
+    fn is_eliminated(&self) -> bool {
+        self.eliminated != 0
+    }
+}
+
+#[account(zero_copy)]
+#[derive(Debug)]
+pub struct MultiGame {
+    pub wager: u64, // Per seat
+    pub escrow: u64,
+    pub reveal_deadline: i64,
+    pub round: u32,
+    pub seats: u8,
+    pub seated_count: u8,
+    pub status: u8, // GameStatus as u8
+    pub bump: u8,
+    pub creator: Pubkey,
+    pub winner: Pubkey, // Default until the game ends
+    pub players: [MultiSeat; MAX_MULTI_PLAYERS as usize],
+}
+
+impl MultiGame {
+    // Seated players, in seating order
+    fn seated(&self) -> &[MultiSeat] {
+        &self.players[..self.seated_count as usize]
+    }
+
+    fn seated_mut(&mut self) -> &mut [MultiSeat] {
+        &mut self.players[..self.seated_count as usize]
+    }
+
+    fn status(&self) -> Result<GameStatus> {
+        GameStatus::try_from(self.status)
+    }
+
+    fn set_status(&mut self, status: GameStatus) {
+        self.status = status as u8;
+    }
+
+    // The seat of a player still in the game
+    fn seat_mut(&mut self, player: &Pubkey) -> Result<&mut MultiSeat> {
+        self.seated_mut()
+            .iter_mut()
+            .find(|seat| seat.player == *player && !seat.is_eliminated())
+            .ok_or(error!(ErrorCode::NotAPlayer))
+    }
+}
+
+#[zero_copy]
+#[derive(Debug)]
+pub struct LeagueStanding {
+    pub player: Pubkey,
+    pub points: u16,
+    pub wins: u16,
+    pub ties: u16,
+    pub losses: u16,
+    pub entered: u8, // 0 or 1
+    pub claimed: u8, // 0 or 1
+}
+
+impl LeagueStanding {
+    fn record(&mut self, outcome: Outcome) -> Result<()> {
+        let (counter, points) = match outcome {
+            Outcome::Win => (&mut self.wins, LEAGUE_WIN_POINTS),
+            Outcome::Tie => (&mut self.ties, LEAGUE_TIE_POINTS),
+            Outcome::Loss => (&mut self.losses, 0),
+        };
+        *counter = counter.checked_add(1).ok_or(ErrorCode::ArithmeticOverflow)?;
+        self.points = self
+            .points
+            .checked_add(points)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    // Ordering key: points, then wins
+    fn ranks_above(&self, other: &LeagueStanding) -> bool {
+        (self.points, self.wins) > (other.points, other.wins)
+    }
+}
+
+#[account(zero_copy)]
+#[derive(Debug)]
+pub struct League {
+    pub league_id: u64,
+    pub entry_fee: u64,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub scheduled: u64, // Fixture bits, see league_fixture_bit
+    pub played: u64,
+    pub prize_pool: u64,
+    pub organizer: Pubkey,
+    pub standings: [LeagueStanding; MAX_LEAGUE_PLAYERS], // In roster order
+    pub roster_len: u8,
+    pub finalized: u8, // 0 or 1
+    pub bump: u8,
+    pub seeded: u8, // 0 or 1, see seed_league
+    pub padding: [u8; 4],
+    pub registration_opens_at: i64,
+    pub registration_closes_at: i64, // Entries are rejected from here on
+}
+
+impl League {
+    // Rostered standings
+    fn roster(&self) -> &[LeagueStanding] {
+        &self.standings[..self.roster_len as usize]
+    }
+
+    fn position(&self, player: &Pubkey) -> Result<usize> {
+        self.roster()
+            .iter()
+            .position(|standing| standing.player == *player)
+            .ok_or(error!(ErrorCode::NotInLeague))
+    }
+
+    // Share of the prize pool for a roster position. The top three split
+    // the pool; shares for missing places go to the champion
+    fn prize(&self, index: usize) -> u64 {
+        let places = self.roster().len().min(LEAGUE_PRIZE_SPLIT_BPS.len());
+        let pool = self.prize_pool as u128;
+        let share = |place: usize| {
+            (pool * LEAGUE_PRIZE_SPLIT_BPS[place] as u128 / BPS_DENOMINATOR as u128) as u64
+        };
+        match self.rank(index) {
+            0 => self.prize_pool - (1..places).map(share).sum::<u64>(),
+            place if place < places => share(place),
+            _ => 0,
+        }
+    }
+
+    // Final place of a roster position; ties go to the earlier roster spot
+    fn rank(&self, index: usize) -> usize {
+        let standing = &self.standings[index];
+        self.roster()
+            .iter()
+            .enumerate()
+            .filter(|(i, other)| {
+                other.ranks_above(standing) || (*i < index && !standing.ranks_above(other))
+            })
+            .count()
+    }
+}
+
+#[zero_copy]
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub opponent: Pubkey,
+    pub wager: u64,
+    pub slot: u64,   // Settlement slot
+    pub outcome: u8, // Outcome discriminant, 0 for an empty slot
+    pub padding: [u8; 7],
+}
+
+impl HistoryEntry {
+    fn new(opponent: Pubkey, wager: u64, slot: u64, outcome: Outcome) -> Self {
+        HistoryEntry {
+            opponent,
+            wager,
+            slot,
+            outcome: outcome as u8,
+            padding: [0; 7],
+        }
+    }
+}
+
+// Ring buffer of a player's latest results, kept after game accounts close
+#[account(zero_copy)]
+#[derive(Debug)]
+pub struct PlayerHistory {
+    pub player: Pubkey,
+    pub total: u64, // Results ever recorded; the next one goes to total % PLAYER_HISTORY_LEN
+    pub entries: [HistoryEntry; PLAYER_HISTORY_LEN],
+    pub bump: u8,
+    pub padding: [u8; 7],
+}
+
+impl PlayerHistory {
+    fn record(&mut self, entry: HistoryEntry) {
+        self.entries[(self.total % PLAYER_HISTORY_LEN as u64) as usize] = entry;
+        self.total += 1;
+    }
+}
+
+#[account]
+#[derive(Debug)]
+pub struct Bankroll {
+    pub total_shares: u64,
+    pub bump: u8,
+}
+
+impl Bankroll {
+    pub const MAX_SIZE: usize = 8 // total_shares
+        + 1; // bump
+}
+
+// Program-owned account holding loss insurance premiums; see insure_wager
+#[account]
+#[derive(Debug)]
+pub struct InsuranceVault {
+    pub exposure: u64,           // Refunds owed if every outstanding policy pays out
+    pub premiums_collected: u64, // Over the vault's lifetime
+    pub claims_paid: u64,        // Over the vault's lifetime
+    pub bump: u8,
+}
+
+impl InsuranceVault {
+    pub const MAX_SIZE: usize = 8 // exposure
+        + 8 // premiums_collected
+        + 8 // claims_paid
+        + 1; // bump
+}
+
+// Program-owned account collecting house fees; see withdraw_fees
+#[account]
+#[derive(Debug)]
+pub struct Treasury {
+    pub total_withdrawn: u64, // Lamports withdrawn over the treasury's lifetime
+    pub bump: u8,
+}
+
+impl Treasury {
+    pub const MAX_SIZE: usize = 8 // total_withdrawn
+        + 1; // bump
+}
+
+#[account]
+#[derive(Debug)]
+pub struct LpPosition {
+    pub owner: Pubkey,
+    pub shares: u64,
+    pub bump: u8,
+}
+
+impl LpPosition {
+    pub const MAX_SIZE: usize = 32 // owner
+        + 8 // shares
+        + 1; // bump
+}
+
+#[account]
+#[derive(Debug)]
+pub struct HouseGame {
+    pub player: Pubkey,
+    pub wager: u64,
+    pub player_move: u8,
+    pub commit_slot: u64,
+    pub bump: u8,
+}
+
+impl HouseGame {
+    pub const MAX_SIZE: usize = 32 // player
+        + 8 // wager
+        + 1 // player_move
+        + 8 // commit_slot
+        + 1; // bump
+}
+
+// A spectator's combined bet on several games; see place_parlay
+#[account]
+#[derive(Debug)]
+pub struct Parlay {
+    pub bettor: Pubkey,
+    pub stake: u64,
+    pub payout: u64, // Return if every pick wins, held next to the stake
+    pub legs: Vec<ParlayLeg>,
+    pub bump: u8,
+}
+
+impl Parlay {
+    pub const MAX_SIZE: usize = 32 // bettor
+        + 8 // stake
+        + 8 // payout
+        + 4 + ParlayLeg::SIZE * MAX_PARLAY_LEGS // legs
+        + 1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ParlayLeg {
+    pub game: Pubkey,
+    pub nonce: u64, // Round the pick is on; a replay or rematch voids it
+    pub pick: RPSResult,
+    pub outcome: LegOutcome,
+}
+
+impl ParlayLeg {
+    pub const SIZE: usize = 32 // game pubkey
+        + 8 // nonce
+        + 1 // pick
+        + 1; // outcome
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LegOutcome {
+    Pending,
+    Won,
+    Lost,
+    Void,
+}
+
+// Public identity of a wallet
+#[account]
+#[derive(Debug)]
+pub struct PlayerProfile {
+    pub player: Pubkey,
+    pub username: String, // Empty until one is registered
+    pub bump: u8,
+    pub avatar: Option<Pubkey>, // NFT mint, see set_avatar
+}
+
+impl PlayerProfile {
+    pub const MAX_SIZE: usize = 32 // player
+        + 4 + MAX_USERNAME_LEN // username
+        + 1 // bump
+        + 1 + 32; // optional avatar
+}
+
+// Reverse lookup of a registered username
+#[account]
+#[derive(Debug)]
+pub struct UsernameRecord {
+    pub player: Pubkey,
+    pub bump: u8,
+}
+
+impl UsernameRecord {
+    pub const MAX_SIZE: usize = 32 // player
+        + 1; // bump
+}
+
+// A creator's saved game settings, see create_game_from_preset
+#[account]
+#[derive(Debug)]
+pub struct GamePreset {
+    pub creator: Pubkey,
+    pub settings: PresetSettings,
+    pub bump: u8,
+}
+
+impl GamePreset {
+    pub const MAX_SIZE: usize = 32 // creator
+        + PresetSettings::SIZE // settings
+        + 1; // bump
+}
+
+// A published game variant, see publish_ruleset
+#[account]
+#[derive(Debug)]
+pub struct Ruleset {
+    pub author: Pubkey,
+    pub move_count: u8,
+    pub beats: [u16; MAX_MOVE_COUNT as usize], // Bitmask of the moves each move beats
+    pub tie_policy: TiePolicy,
+    pub join_window: i64, // Seconds until the join deadline
+    pub time_bank: i64,
+    pub bump: u8,
+}
+
+impl Ruleset {
+    pub const MAX_SIZE: usize = 32 // author
+        + 1 // move_count
+        + 2 * MAX_MOVE_COUNT as usize // beats
+        + 1 // tie_policy
+        + 8 // join_window
+        + 8 // time_bank
+        + 1; // bump
+}
+
+// An account a settlement callback asks for
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CallbackAccount {
+    pub pubkey: Pubkey,
+    pub is_writable: bool,
+}
+
+impl CallbackAccount {
+    pub const SIZE: usize = 32 // pubkey
+        + 1; // is_writable
+}
+
+// Program a game notifies when it is settled by play, timeout, forfeit or
+// surrender, see notify_callback. Refunds, cancellations and collections
+// don't notify
+#[account]
+#[derive(Debug)]
+pub struct GameCallback {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub program: Pubkey,
+    pub accounts: Vec<CallbackAccount>,
+    pub bump: u8,
+}
+
+impl GameCallback {
+    pub const MAX_SIZE: usize = 32 // game
+        + 32 // creator
+        + 32 // program
+        + 4 + CallbackAccount::SIZE * MAX_CALLBACK_ACCOUNTS // accounts
+        + 1; // bump
+}
+
+// Arguments of the on_game_settled instruction notify_callback sends, after
+// Anchor's discriminator for that name. The winner and result are the ones
+// stored on the game, so timeouts, forfeits and surrenders report theirs
+// too
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct GameSettledCallback {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub opponent: Option<Pubkey>,
+    pub winner: Option<Pubkey>,
+    pub result: Option<GameResult>,
+}
+
+// Players allowed to join a challenge
+#[account]
+#[derive(Debug)]
+pub struct Allowlist {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub players: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl Allowlist {
+    pub const MAX_SIZE: usize = 32 // game
+        + 32 // creator
+        + 4 + 32 * MAX_ALLOWLIST_LEN // players
+        + 1; // bump
+}
+
+// A pending request to join a game that requires approval; holds the wager
+#[account]
+#[derive(Debug)]
+pub struct JoinRequest {
+    pub game: Pubkey,
+    pub joiner: Pubkey,
+    pub wager: u64,
+    pub bond: u64, // Reveal bond escrowed next to the wager
+    pub bump: u8,
+}
+
+impl JoinRequest {
+    pub const MAX_SIZE: usize = 32 // game
+        + 32 // joiner
+        + 8 // wager
+        + 8 // bond
+        + 1; // bump
+}
+
+// ------------------------------------
+// Events
+// ------------------------------------
+
+#[event]
+pub struct GameCreated {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub opponent: Option<Pubkey>, // Set when both players start together
+    pub wager: u64,
+}
+
+#[event]
+pub struct GameJoined {
+    pub game: Pubkey,
+    pub joiner: Pubkey,
+    pub wager: u64,
+}
+
+#[event]
+pub struct CounterOffered {
+    pub game: Pubkey,
+    pub joiner: Pubkey,
+    pub wager: u64,
+}
+
+#[event]
+pub struct CounterOfferDeclined {
+    pub game: Pubkey,
+    pub joiner: Pubkey,
+    pub wager: u64,
+}
+
+#[event]
+pub struct ParlaySettled {
+    pub parlay: Pubkey,
+    pub bettor: Pubkey,
+    pub payout: u64, // 0 when a pick lost
+}
+
+#[event]
+pub struct LeaderboardSnapshotted {
+    pub week: u64,
+    pub prize_pool: u64,
+}
+
+#[event]
+pub struct WagerChanged {
+    pub game: Pubkey,
+    pub old_wager: u64,
+    pub wager: u64,
+}
+
+#[event]
+pub struct GameCancelled {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub refund: u64,
+}
+
+#[event]
+pub struct GameCollected {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub refund: u64,
+    pub collector: Pubkey,
+    pub bounty: u64,
+}
+
+#[event]
+pub struct TieReplayed {
+    pub game: Pubkey,
+    pub round: u8, // Sudden-death round about to be played
+}
+
+#[event]
+pub struct Surrendered {
+    pub game: Pubkey,
+    pub player: Pubkey, // The player who surrendered
+    pub refund: u64,
+    pub opponent_payout: u64,
+}
+
+#[event]
+pub struct TimeForfeited {
+    pub game: Pubkey,
+    pub result: RPSResult,
+}
+
+#[event]
+pub struct TieBreakRequested {
+    pub game: Pubkey,
+    pub seed: [u8; 32], // Seed of the VRF request, see find_tie_break_randomness_address
+}
+
+#[event]
+pub struct TieBroken {
+    pub game: Pubkey,
+    pub randomness: [u8; 64],
+    pub result: RPSResult,
+}
+
+#[event]
+pub struct GameSettled {
+    pub game: Pubkey,
+    pub creator_move: u8,
+    pub joiner_move: u8,
+    pub result: RPSResult,
+    pub house_fee: u64, // Including the jackpot cut
+    pub creator_payout: u64,
+    pub joiner_payout: u64,
+    pub label: [u8; MAX_LABEL_LEN], // Zero-padded; all zeros when unlabelled
+}
+
+#[event]
+pub struct StaleGameRefunded {
+    pub game: Pubkey,
+    pub creator_refund: u64,
+    pub joiner_refund: u64,
+}
+
+#[event]
+pub struct FeesWithdrawn {
+    pub admin: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub total_withdrawn: u64,
+}
+
+#[event]
+pub struct EmergencyRefunded {
+    pub game: Pubkey,
+    pub admin: Pubkey,
+    pub creator: Pubkey,
+    pub creator_refund: u64,
+    pub joiner: Option<Pubkey>,
+    pub joiner_refund: u64,
+}
+
+#[event]
+pub struct RevealTimedOut {
+    pub game: Pubkey,
+    pub result: RPSResult,
+    pub forfeited_bond: u64, // Paid to the winner on top of their own bond
+}
+
+#[event]
+pub struct JoinRequested {
+    pub game: Pubkey,
+    pub joiner: Pubkey,
+    pub wager: u64,
+}
+
+#[event]
+pub struct GameMessage {
+    pub game: Pubkey,
+    pub sender: Pubkey,
+    pub message: String,
+}
+
+#[event]
+pub struct GameLabelled {
+    pub game: Pubkey,
+    pub label: String, // Empty when cleared
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        seeds = [CONFIG_SEED],
+        bump,
+        space = 8 + Config::MAX_SIZE
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [TREASURY_SEED],
+        bump,
+        space = 8 + Treasury::MAX_SIZE
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    // Pays the rent, so the admin itself can be a PDA with no lamports
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(mut, seeds = [TREASURY_SEED], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    /// CHECK: Any account chosen by the admin to receive the fees
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeInsuranceVault<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [INSURANCE_VAULT_SEED],
+        bump,
+        space = 8 + InsuranceVault::MAX_SIZE
+    )]
+    pub insurance_vault: Account<'info, InsuranceVault>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePointsMint<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [POINTS_MINT_SEED],
+        bump,
+        mint::decimals = 0,
+        mint::authority = points_mint,
+        mint::freeze_authority = points_mint
+    )]
+    pub points_mint: Account<'info, Mint>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    // Pays the rent, so the admin itself can be a PDA with no lamports
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPoints<'info> {
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [POINTS_MINT_SEED], bump)]
+    pub points_mint: Account<'info, Mint>,
+
+    #[account(mut, token::mint = points_mint, token::authority = player)]
+    pub player_token: Account<'info, TokenAccount>,
+
+    pub player: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRakeback<'info> {
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    #[account(mut, seeds = [TREASURY_SEED], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSessionKey<'info> {
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayoutPreference<'info> {
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterBot<'info> {
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [PLAYER_STATS_SEED, bot.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub bot_stats: Account<'info, PlayerStats>,
+
+    /// CHECK: The wallet being registered
+    pub bot: UncheckedAccount<'info>,
+
+    // The admin or the bot itself
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnregisterBot<'info> {
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, bot_stats.player.as_ref()],
+        bump = bot_stats.bump
+    )]
+    pub bot_stats: Account<'info, PlayerStats>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPlayerLimits<'info> {
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitPlayerHistory<'info> {
+    #[account(
+        init,
+        payer = player,
+        seeds = [PLAYER_HISTORY_SEED, player.key().as_ref()],
+        bump,
+        space = 8 + std::mem::size_of::<PlayerHistory>()
+    )]
+    pub player_history: AccountLoader<'info, PlayerHistory>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(username: String)]
+pub struct RegisterUsername<'info> {
+    #[account(
+        init_if_needed,
+        payer = player,
+        seeds = [PROFILE_SEED, player.key().as_ref()],
+        bump,
+        space = 8 + PlayerProfile::MAX_SIZE
+    )]
+    pub profile: Account<'info, PlayerProfile>,
+
+    // Fails to initialize if someone already holds the name
+    #[account(
+        init,
+        payer = player,
+        seeds = [USERNAME_SEED, username.as_bytes()],
+        bump,
+        space = 8 + UsernameRecord::MAX_SIZE
+    )]
+    pub username_record: Account<'info, UsernameRecord>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseUsername<'info> {
+    #[account(
+        mut,
+        seeds = [PROFILE_SEED, player.key().as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, PlayerProfile>,
+
+    #[account(
+        mut,
+        close = player,
+        seeds = [USERNAME_SEED, profile.username.as_bytes()],
+        bump = username_record.bump
+    )]
+    pub username_record: Account<'info, UsernameRecord>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAvatar<'info> {
+    #[account(
+        init_if_needed,
+        payer = player,
+        seeds = [PROFILE_SEED, player.key().as_ref()],
+        bump,
+        space = 8 + PlayerProfile::MAX_SIZE
+    )]
+    pub profile: Account<'info, PlayerProfile>,
+
+    #[account(constraint = mint.decimals == 0 && mint.supply == 1 @ ErrorCode::NotAnNft)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        token::mint = mint,
+        token::authority = player,
+        constraint = player_token.amount == 1 @ ErrorCode::AvatarNotOwned
+    )]
+    pub player_token: Account<'info, TokenAccount>,
+
+    /// CHECK: The mint's Metaplex metadata account, parsed in set_avatar
+    #[account(
+        seeds = [b"metadata", TOKEN_METADATA_PROGRAM_ID.as_ref(), mint.key().as_ref()],
+        bump,
+        seeds::program = TOKEN_METADATA_PROGRAM_ID,
+        owner = TOKEN_METADATA_PROGRAM_ID @ ErrorCode::InvalidMetadata
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearAvatar<'info> {
+    #[account(
+        mut,
+        seeds = [PROFILE_SEED, player.key().as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, PlayerProfile>,
+
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLeaderboard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [LEADERBOARD_SEED],
+        bump,
+        space = 8 + Leaderboard::MAX_SIZE
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u8)]
+pub struct InitializeLeaderboardShard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [LEADERBOARD_SEED, &[index]],
+        bump,
+        space = 8 + LeaderboardShard::MAX_SIZE
+    )]
+    pub shard: Account<'info, LeaderboardShard>,
+
+    #[account(seeds = [LEADERBOARD_SEED], bump = leaderboard.bump)]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(week: u64)]
+pub struct SnapshotLeaderboard<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [SNAPSHOT_SEED, &week.to_le_bytes()],
+        bump,
+        space = 8 + LeaderboardSnapshot::MAX_SIZE
+    )]
+    pub snapshot: Account<'info, LeaderboardSnapshot>,
+
+    #[account(seeds = [LEADERBOARD_SEED], bump = leaderboard.bump)]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(mut, seeds = [TREASURY_SEED], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimWeeklyPrize<'info> {
+    #[account(mut, seeds = [SNAPSHOT_SEED, &snapshot.week.to_le_bytes()], bump = snapshot.bump)]
+    pub snapshot: Account<'info, LeaderboardSnapshot>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateStanding<'info> {
+    #[account(seeds = [LEADERBOARD_SEED], bump = leaderboard.bump)]
+    pub leaderboard: Account<'info, Leaderboard>,
+
+    #[account(
+        mut,
+        seeds = [LEADERBOARD_SEED, &[shard.index]],
+        bump = shard.bump
+    )]
+    pub shard: Account<'info, LeaderboardShard>,
+
+    #[account(
+        mut,
+        seeds = [LEADERBOARD_SEED, &[previous_shard.index]],
+        bump = previous_shard.bump
+    )]
+    pub previous_shard: Option<Account<'info, LeaderboardShard>>,
+
+    #[account(
+        seeds = [PLAYER_STATS_SEED, player_stats.player.as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+}
+
+#[derive(Accounts)]
+#[instruction(bucket: u8)]
+pub struct InitializeLobby<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [LOBBY_SEED, &[bucket]],
+        bump,
+        space = 8 + Lobby::MAX_SIZE
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    // Pays the rent, so the admin itself can be a PDA with no lamports
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wager: u64)]
+pub struct CreateGame<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [
+            GAME_SEED,
+            config.game_seed_prefix(game_account.key, creator.key, wager),
+            creator.key().as_ref(),
+            &wager.to_le_bytes()
+        ],
+        bump,
+        space = 8 + GameState::MAX_SIZE
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    // Only needed while the config requires a creation stake
+    #[account(
+        constraint = creator_stake.owner == creator.key() @ ErrorCode::CreationStakeRequired
+    )]
+    pub creator_stake: Option<Account<'info, TokenAccount>>,
+
+    // Only needed while the config charges a creation fee
+    #[account(mut, seeds = [TREASURY_SEED], bump = treasury.bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    // Pays the rent of the new accounts, so a platform can sponsor a new
+    // player's first games. It has no authority over the game; the creator
+    // still funds the wager and gets the rent back when the game closes
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // Fronts the wager for a new player and is repaid first out of their
+    // payout
+    #[account(mut)]
+    pub sponsor: Option<Signer<'info>>,
+
+    // Custom rules the game is played by
+    pub ruleset: Option<Account<'info, Ruleset>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct SavePreset<'info> {
+    #[account(
+        init_if_needed,
+        payer = creator,
+        seeds = [PRESET_SEED, creator.key().as_ref(), &id.to_le_bytes()],
+        bump,
+        space = 8 + GamePreset::MAX_SIZE
+    )]
+    pub preset: Account<'info, GamePreset>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePreset<'info> {
+    #[account(mut, has_one = creator @ ErrorCode::WrongCreator, close = creator)]
+    pub preset: Account<'info, GamePreset>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct PublishRuleset<'info> {
+    #[account(
+        init,
+        payer = author,
+        seeds = [RULESET_SEED, author.key().as_ref(), &id.to_le_bytes()],
+        bump,
+        space = 8 + Ruleset::MAX_SIZE
+    )]
+    pub ruleset: Account<'info, Ruleset>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseRuleset<'info> {
+    #[account(mut, has_one = author @ ErrorCode::Unauthorized, close = author)]
+    pub ruleset: Account<'info, Ruleset>,
+
+    #[account(mut)]
+    pub author: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CreateGameFromPreset<'info> {
+    #[account(has_one = creator @ ErrorCode::WrongCreator)]
+    pub preset: Account<'info, GamePreset>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [
+            GAME_SEED,
+            config.game_seed_prefix(game_account.key, creator.key, preset.settings.wager),
+            creator.key().as_ref(),
+            &preset.settings.wager.to_le_bytes()
+        ],
+        bump,
+        space = 8 + GameState::MAX_SIZE
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    // Only needed while the config requires a creation stake
+    #[account(
+        constraint = creator_stake.owner == creator.key() @ ErrorCode::CreationStakeRequired
+    )]
+    pub creator_stake: Option<Account<'info, TokenAccount>>,
+
+    // Only needed while the config charges a creation fee
+    #[account(mut, seeds = [TREASURY_SEED], bump = treasury.bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    // Fronts the wager for a new player and is repaid first out of their
+    // payout
+    #[account(mut)]
+    pub sponsor: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wager_commitment: [u8; 32])]
+pub struct CreateHiddenGame<'info> {
+    #[account(
+        init,
+        payer = creator,
+        seeds = [HIDDEN_GAME_SEED, creator.key().as_ref(), &wager_commitment],
+        bump,
+        space = 8 + GameState::MAX_SIZE
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    // Only needed while the config requires a creation stake
+    #[account(
+        constraint = creator_stake.owner == creator.key() @ ErrorCode::CreationStakeRequired
+    )]
+    pub creator_stake: Option<Account<'info, TokenAccount>>,
+
+    // Only needed while the config charges a creation fee
+    #[account(mut, seeds = [TREASURY_SEED], bump = treasury.bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    // Fronts the deposit for a new player and is repaid first out of their
+    // payout
+    #[account(mut)]
+    pub sponsor: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wager: u64, salt: String)]
+pub struct JoinHiddenGame<'info> {
+    // The seeds check that wager and salt open the commitment
+    #[account(
+        mut,
+        seeds = [
+            HIDDEN_GAME_SEED,
+            game_account.creator.as_ref(),
+            &wager_commitment(wager, salt.as_bytes()),
+        ],
+        bump = game_account.bump,
+        constraint = game_account.status == GameStatus::Hidden @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Refund destination for the excess deposit
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    // Counts the creator's open games, which this releases
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    #[account(
+        init_if_needed,
+        payer = joiner,
+        seeds = [PLAYER_STATS_SEED, joiner.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub joiner_stats: Account<'info, PlayerStats>,
+
+    #[account(mut)]
+    pub joiner: Signer<'info>,
+
+    // Required when the game is restricted to an allowlist
+    #[account(seeds = [ALLOWLIST_SEED, game_account.key().as_ref()], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    // The creator's sponsor, refunded the excess deposit and checked
+    // against the game in the handler
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+
+    // Fronts the wager for a new player and is repaid first out of their
+    // payout
+    #[account(mut)]
+    pub sponsor: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(wager: u64)]
+pub struct CreateAndJoin<'info> {
+    #[account(
+        init,
+        payer = creator,
+        seeds = [
+            GAME_SEED,
+            config.game_seed_prefix(game_account.key, creator.key, wager),
+            creator.key().as_ref(),
+            &wager.to_le_bytes()
+        ],
+        bump,
+        space = 8 + GameState::MAX_SIZE
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    #[account(
+        init_if_needed,
+        payer = joiner,
+        seeds = [PLAYER_STATS_SEED, joiner.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub joiner_stats: Account<'info, PlayerStats>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(mut)]
+    pub joiner: Signer<'info>,
+
+    // Front the players' wagers and are repaid first out of their payouts
+    #[account(mut)]
+    pub creator_sponsor: Option<Signer<'info>>,
+
+    #[account(mut)]
+    pub joiner_sponsor: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(band: u8)]
+pub struct InitializeQueue<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [QUEUE_SEED, &[band]],
+        bump,
+        space = 8 + MatchQueue::MAX_SIZE
+    )]
+    pub queue: Account<'info, MatchQueue>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    // Pays the rent, so the admin itself can be a PDA with no lamports
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(band: u8)]
+pub struct JoinQueue<'info> {
+    #[account(mut, seeds = [QUEUE_SEED, &[band]], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = player,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(band: u8)]
+pub struct LeaveQueue<'info> {
+    #[account(mut, seeds = [QUEUE_SEED, &[band]], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(band: u8)]
+pub struct MatchPlayers<'info> {
+    #[account(mut, seeds = [QUEUE_SEED, &[band]], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [QUEUE_GAME_SEED, queue.key().as_ref(), &queue.matches.to_le_bytes()],
+        bump,
+        space = 8 + GameState::MAX_SIZE
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelGame<'info> {
+    #[account(
+        mut,
+        close = creator,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open
+            || game_account.status == GameStatus::Hidden @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    // Counts the creator's open games, which this releases
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, game_account.creator.as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    // The sponsor who fronted the wager, checked against the game in the
+    // handler
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct GcGame<'info> {
+    #[account(
+        mut,
+        close = creator,
+        constraint = game_account.status == GameStatus::Open
+            || game_account.status == GameStatus::Hidden @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    // Anyone can collect an expired game and takes the bounty
+    #[account(mut)]
+    pub caller: Signer<'info>,
+
+    /// CHECK: Refund destination, must match the game's creator
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    // Counts the creator's open games, which this releases
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, game_account.creator.as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    // The sponsor who fronted the wager, checked against the game in the
+    // handler
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct Surrender<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status == GameStatus::Committed
+            || game_account.status == GameStatus::Revealing @ ErrorCode::GameNotCommitted
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(constraint = game_account.is_player(&player.key()) @ ErrorCode::NotAPlayer)]
+    pub player: Signer<'info>,
+
+    /// CHECK: Payout destination, must match the game's creator
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Payout destination, must match the game's opponent
+    #[account(
+        mut,
+        constraint = game_account.opponent == Some(joiner.key()) @ ErrorCode::WrongOpponent
+    )]
+    pub joiner: UncheckedAccount<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, joiner.key().as_ref()],
+        bump = joiner_stats.bump
+    )]
+    pub joiner_stats: Account<'info, PlayerStats>,
+
+    // Sponsors who fronted the players' wagers, checked against the game in
+    // the handler; only needed when a sponsor is owed part of the payout
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub joiner_sponsor: Option<SystemAccount<'info>>,
+
+    // Only needed when either player insured their wager
+    #[account(mut, seeds = [INSURANCE_VAULT_SEED], bump = insurance_vault.bump)]
+    pub insurance_vault: Option<Account<'info, InsuranceVault>>,
+
+    // The game's callback, notified when passed; settlement goes ahead
+    // without it
+    #[account(seeds = [CALLBACK_SEED, game_account.key().as_ref()], bump = callback.bump)]
+    pub callback: Option<Account<'info, GameCallback>>,
+
+    /// CHECK: The callback's program, checked against it in the handler
+    #[account(executable)]
+    pub callback_program: Option<UncheckedAccount<'info>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct JoinGame<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(
+        init_if_needed,
+        payer = joiner,
+        seeds = [PLAYER_STATS_SEED, joiner.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub joiner_stats: Account<'info, PlayerStats>,
+
+    // Counts the creator's open games, which this releases
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, game_account.creator.as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    #[account(mut)]
+    pub joiner: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, read for join permits
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    // Required when the game is restricted to an allowlist
+    #[account(seeds = [ALLOWLIST_SEED, game_account.key().as_ref()], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    // Fronts the wager for a new player and is repaid first out of their
+    // payout
+    #[account(mut)]
+    pub sponsor: Option<Signer<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestJoin<'info> {
+    #[account(
+        constraint = game_account.has_flag(GAME_FLAG_APPROVAL_REQUIRED) @ ErrorCode::ApprovalNotRequired
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = joiner,
+        seeds = [JOIN_REQUEST_SEED, game_account.key().as_ref(), joiner.key().as_ref()],
+        bump,
+        space = 8 + JoinRequest::MAX_SIZE
+    )]
+    pub join_request: Account<'info, JoinRequest>,
+
+    #[account(mut)]
+    pub joiner: Signer<'info>,
+
+    // Required when the game is restricted to an allowlist
+    #[account(seeds = [ALLOWLIST_SEED, game_account.key().as_ref()], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAllowlist<'info> {
+    #[account(
+        mut,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [ALLOWLIST_SEED, game_account.key().as_ref()],
+        bump,
+        space = 8 + Allowlist::MAX_SIZE
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCallback<'info> {
+    #[account(
+        mut,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [CALLBACK_SEED, game_account.key().as_ref()],
+        bump,
+        space = 8 + GameCallback::MAX_SIZE
+    )]
+    pub callback: Account<'info, GameCallback>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCallback<'info> {
+    #[account(
+        mut,
+        close = creator,
+        has_one = creator @ ErrorCode::WrongCreator,
+        seeds = [CALLBACK_SEED, callback.game.as_ref()],
+        bump = callback.bump
+    )]
+    pub callback: Account<'info, GameCallback>,
+
+    /// CHECK: The callback's game, rewritten only if it still exists
+    #[account(mut, address = callback.game)]
+    pub game_account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseAllowlist<'info> {
+    #[account(
+        mut,
+        close = creator,
+        has_one = creator @ ErrorCode::WrongCreator,
+        seeds = [ALLOWLIST_SEED, allowlist.game.as_ref()],
+        bump = allowlist.bump
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+
+    /// CHECK: The allowlist's game, read only if it still exists
+    #[account(address = allowlist.game)]
+    pub game_account: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct AcceptJoinRequest<'info> {
+    #[account(mut, has_one = creator @ ErrorCode::WrongCreator)]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(
+        mut,
+        close = joiner,
+        seeds = [JOIN_REQUEST_SEED, game_account.key().as_ref(), joiner.key().as_ref()],
+        bump = join_request.bump
+    )]
+    pub join_request: Account<'info, JoinRequest>,
+
+    /// CHECK: The requesting player, checked by the join request seeds
+    #[account(mut)]
+    pub joiner: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        seeds = [PLAYER_STATS_SEED, joiner.key().as_ref()],
+        bump,
+        space = 8 + PlayerStats::MAX_SIZE
+    )]
+    pub joiner_stats: Account<'info, PlayerStats>,
+
+    // Counts the creator's open games, which this releases
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, game_account.creator.as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawJoinRequest<'info> {
+    #[account(
+        mut,
+        close = joiner,
+        seeds = [JOIN_REQUEST_SEED, join_request.game.as_ref(), joiner.key().as_ref()],
+        bump = join_request.bump
+    )]
+    pub join_request: Account<'info, JoinRequest>,
+
+    #[account(mut)]
+    pub joiner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRevealBond<'info> {
+    #[account(
+        mut,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ChangeWager<'info> {
+    #[account(
+        mut,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OfferWager<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    pub joiner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOpenGame<'info> {
+    #[account(
+        mut,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCategory<'info> {
+    #[account(
+        mut,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    pub creator: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SelectMove<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, constraint = game_account.is_player(&player.key()) @ ErrorCode::NotAPlayer)]
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InsureWager<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status == GameStatus::Committed @ ErrorCode::GameNotCommitted
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, constraint = game_account.is_player(&player.key()) @ ErrorCode::NotAPlayer)]
+    pub player: Signer<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut, seeds = [INSURANCE_VAULT_SEED], bump = insurance_vault.bump)]
+    pub insurance_vault: Account<'info, InsuranceVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Spectate<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+}
+
+#[derive(Accounts)]
+pub struct SendMessage<'info> {
+    #[account(
+        constraint = game_account.status != GameStatus::Ended @ ErrorCode::GameAlreadyEnded
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(constraint = game_account.is_player(&sender.key()) @ ErrorCode::NotAPlayer)]
+    pub sender: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReadyUp<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status != GameStatus::Ended @ ErrorCode::GameAlreadyEnded
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut, constraint = game_account.is_player(&player.key()) @ ErrorCode::NotAPlayer)]
+    pub player: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RevealMove<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status == GameStatus::Revealing @ ErrorCode::NotRevealPhase
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    // The player, their session key, or a relayer
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    /// CHECK: Payout destination, must match the game's creator
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Payout destination, must match the game's opponent
+    #[account(
+        mut,
+        constraint = game_account.opponent == Some(joiner.key()) @ ErrorCode::WrongOpponent
+    )]
+    pub joiner: UncheckedAccount<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Fee destination, must match the config
+    #[account(mut, address = config.house @ ErrorCode::WrongHouse)]
+    pub house: UncheckedAccount<'info>,
+
+    /// CHECK: System-owned PDA holding the progressive jackpot
+    #[account(mut, seeds = [JACKPOT_VAULT_SEED], bump)]
+    pub jackpot_vault: UncheckedAccount<'info>,
+
+    /// CHECK: SlotHashes sysvar, read raw for the jackpot rule
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, read for relayed reveals
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, joiner.key().as_ref()],
+        bump = joiner_stats.bump
+    )]
+    pub joiner_stats: Account<'info, PlayerStats>,
+
+    // Written at settlement when the players have opened one
+    #[account(
+        mut,
+        seeds = [PLAYER_HISTORY_SEED, creator.key().as_ref()],
+        bump
+    )]
+    pub creator_history: Option<AccountLoader<'info, PlayerHistory>>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_HISTORY_SEED, joiner.key().as_ref()],
+        bump
+    )]
+    pub joiner_history: Option<AccountLoader<'info, PlayerHistory>>,
+
+    // Required for players who take payouts as wSOL
+    #[account(
+        mut,
+        constraint = creator_wsol.owner == creator.key() && creator_wsol.is_native()
+            @ ErrorCode::InvalidWsolAccount
+    )]
+    pub creator_wsol: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = joiner_wsol.owner == joiner.key() && joiner_wsol.is_native()
+            @ ErrorCode::InvalidWsolAccount
+    )]
+    pub joiner_wsol: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    // Sponsors who fronted the players' wagers, checked against the game in
+    // the handler; only needed when a sponsor is owed part of the payout
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub joiner_sponsor: Option<SystemAccount<'info>>,
+
+    // Only needed when either player insured their wager
+    #[account(mut, seeds = [INSURANCE_VAULT_SEED], bump = insurance_vault.bump)]
+    pub insurance_vault: Option<Account<'info, InsuranceVault>>,
+
+    // The game's callback, notified when passed; settlement goes ahead
+    // without it
+    #[account(seeds = [CALLBACK_SEED, game_account.key().as_ref()], bump = callback.bump)]
+    pub callback: Option<Account<'info, GameCallback>>,
+
+    /// CHECK: The callback's program, checked against it in the handler
+    #[account(executable)]
+    pub callback_program: Option<UncheckedAccount<'info>>,
+
+    // ORAO VRF accounts, needed by the reveal that ties a game breaking ties
+    // at random and, for the randomness, by fulfill_tie_break
+    /// CHECK: The ORAO VRF program
+    #[account(address = ORAO_VRF_PROGRAM_ID)]
+    pub vrf_program: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The VRF network's configuration
+    #[account(mut, seeds = [ORAO_NETWORK_STATE_SEED], bump, seeds::program = ORAO_VRF_PROGRAM_ID)]
+    pub vrf_network_state: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: The VRF network's fee treasury, checked by the VRF program
+    #[account(mut)]
+    pub vrf_treasury: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Randomness account for the game's tie-break seed, checked in
+    /// the handler
+    #[account(mut)]
+    pub vrf_randomness: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimTimeForfeit<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status == GameStatus::Committed
+            || game_account.status == GameStatus::Revealing @ ErrorCode::GameNotCommitted
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    /// CHECK: Payout destination, must match the game's creator
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Payout destination, must match the game's opponent
+    #[account(
+        mut,
+        constraint = game_account.opponent == Some(joiner.key()) @ ErrorCode::WrongOpponent
+    )]
+    pub joiner: UncheckedAccount<'info>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Fee destination, must match the config
+    #[account(mut, address = config.house @ ErrorCode::WrongHouse)]
+    pub house: UncheckedAccount<'info>,
+
+    /// CHECK: System-owned PDA holding the progressive jackpot
+    #[account(mut, seeds = [JACKPOT_VAULT_SEED], bump)]
+    pub jackpot_vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, creator.key().as_ref()],
+        bump = creator_stats.bump
+    )]
+    pub creator_stats: Account<'info, PlayerStats>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, joiner.key().as_ref()],
+        bump = joiner_stats.bump
+    )]
+    pub joiner_stats: Account<'info, PlayerStats>,
+
+    // Required for players who take payouts as wSOL
+    #[account(
+        mut,
+        constraint = creator_wsol.owner == creator.key() && creator_wsol.is_native()
+            @ ErrorCode::InvalidWsolAccount
+    )]
+    pub creator_wsol: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = joiner_wsol.owner == joiner.key() && joiner_wsol.is_native()
+            @ ErrorCode::InvalidWsolAccount
+    )]
+    pub joiner_wsol: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    // Sponsors who fronted the players' wagers, checked against the game in
+    // the handler; only needed when a sponsor is owed part of the payout
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub joiner_sponsor: Option<SystemAccount<'info>>,
+
+    // Only needed when either player insured their wager
+    #[account(mut, seeds = [INSURANCE_VAULT_SEED], bump = insurance_vault.bump)]
+    pub insurance_vault: Option<Account<'info, InsuranceVault>>,
+
+    // The game's callback, notified when passed; settlement goes ahead
+    // without it
+    #[account(seeds = [CALLBACK_SEED, game_account.key().as_ref()], bump = callback.bump)]
+    pub callback: Option<Account<'info, GameCallback>>,
+
+    /// CHECK: The callback's program, checked against it in the handler
+    #[account(executable)]
+    pub callback_program: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct Rematch<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRollover<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status == GameStatus::Ended @ ErrorCode::GameNotEnded,
+        constraint = game_account.winner == Some(winner.key()) @ ErrorCode::NotWinner
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(mut)]
+    pub winner: Signer<'info>,
+
+    /// CHECK: Refund destination for a pending rematch stake, must be the
+    /// game's loser
+    #[account(
+        mut,
+        constraint = settled_loser(&game_account) == Some(loser.key()) @ ErrorCode::NotRematchLoser
+    )]
+    pub loser: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct StakeNft<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Fee destination, must match the config
+    #[account(mut, address = config.house @ ErrorCode::WrongHouse)]
+    pub house: UncheckedAccount<'info>,
+
+    #[account(constraint = mint.decimals == 0 && mint.supply == 1 @ ErrorCode::NotAnNft)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = player,
+        seeds = [NFT_ESCROW_SEED, game_account.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = nft_authority
+    )]
+    pub escrow_token: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = mint, token::authority = player)]
+    pub player_token: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA that owns every NFT escrow token account
+    #[account(seeds = [NFT_AUTHORITY_SEED], bump)]
+    pub nft_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseNft<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [NFT_ESCROW_SEED, game_account.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_token: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = mint, token::authority = recipient)]
+    pub recipient_token: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA that owns every NFT escrow token account
+    #[account(seeds = [NFT_AUTHORITY_SEED], bump)]
+    pub nft_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AddPotAsset<'info> {
+    #[account(
+        mut,
+        has_one = creator @ ErrorCode::WrongCreator,
+        constraint = game_account.status == GameStatus::Open @ ErrorCode::GameNotOpen
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [POT_ESCROW_SEED, game_account.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = token_authority
+    )]
+    pub escrow_token: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = mint, token::authority = creator)]
+    pub creator_token: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA that owns every token escrow account
+    #[account(seeds = [NFT_AUTHORITY_SEED], bump)]
+    pub token_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositPotAsset<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status == GameStatus::Committed @ ErrorCode::GameNotCommitted
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [POT_ESCROW_SEED, game_account.key().as_ref(), escrow_token.mint.as_ref()],
+        bump
+    )]
+    pub escrow_token: Account<'info, TokenAccount>,
+
+    #[account(mut, token::mint = escrow_token.mint, token::authority = joiner)]
+    pub joiner_token: Account<'info, TokenAccount>,
+
+    #[account(constraint = game_account.opponent == Some(joiner.key()) @ ErrorCode::WrongOpponent)]
+    pub joiner: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SettlePotAsset<'info> {
+    #[account(mut)]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [POT_ESCROW_SEED, game_account.key().as_ref(), escrow_token.mint.as_ref()],
+        bump
+    )]
+    pub escrow_token: Account<'info, TokenAccount>,
+
+    #[account(address = escrow_token.mint)]
+    pub mint: Account<'info, Mint>,
+
+    // Payouts go to associated token accounts, created here if missing so
+    // a settlement never fails on an account nobody opened
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = creator
+    )]
+    pub creator_token: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = joiner
+    )]
+    pub joiner_token: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = house
+    )]
+    pub house_token: Account<'info, TokenAccount>,
+
+    /// CHECK: Receives the escrow account's rent, must match the game's creator
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Must match the game's opponent; any wallet while there is none
+    #[account(
+        constraint = game_account.opponent.is_none()
+            || game_account.opponent == Some(joiner.key()) @ ErrorCode::WrongOpponent
+    )]
+    pub joiner: UncheckedAccount<'info>,
+
+    /// CHECK: Fee destination, must match the config
+    #[account(address = config.house @ ErrorCode::WrongHouse)]
+    pub house: UncheckedAccount<'info>,
+
+    /// CHECK: PDA that owns every token escrow account
+    #[account(seeds = [NFT_AUTHORITY_SEED], bump)]
+    pub token_authority: UncheckedAccount<'info>,
+
+    // The cranker, paying the rent of any token account created
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct RefundStaleGame<'info> {
+    #[account(
+        mut,
+        close = creator,
+        constraint = game_account.status == GameStatus::Revealing @ ErrorCode::NotRevealPhase,
+        constraint = game_account.creator_move.is_none()
+            && game_account.joiner_move.is_none() @ ErrorCode::GameHasReveals
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    // Anyone can trigger the refund
+    pub caller: Signer<'info>,
+
+    /// CHECK: Refund destination, must match the game's creator
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Refund destination, must match the game's opponent
+    #[account(
+        mut,
+        constraint = game_account.opponent == Some(joiner.key()) @ ErrorCode::WrongOpponent
+    )]
+    pub joiner: UncheckedAccount<'info>,
+
+    // Sponsors who fronted the players' wagers, checked against the game in
+    // the handler; only needed when a sponsor is owed part of the payout
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub joiner_sponsor: Option<SystemAccount<'info>>,
+
+    // Only needed when either player insured their wager
+    #[account(mut, seeds = [INSURANCE_VAULT_SEED], bump = insurance_vault.bump)]
+    pub insurance_vault: Option<Account<'info, InsuranceVault>>,
+}
+
+#[derive(Accounts)]
+pub struct FlagGame<'info> {
+    #[account(
+        mut,
+        constraint = game_account.status != GameStatus::Ended @ ErrorCode::GameAlreadyEnded
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct EmergencyRefund<'info> {
+    #[account(
+        mut,
+        close = creator,
+        constraint = game_account.has_flag(GAME_FLAG_FLAGGED) @ ErrorCode::GameNotFlagged,
+        constraint = game_account.status != GameStatus::Ended @ ErrorCode::GameAlreadyEnded
+    )]
+    pub game_account: Account<'info, GameState>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [LOBBY_SEED, &[lobby_bucket(&game_account.key())]],
+        bump = lobby.bump
+    )]
+    pub lobby: Account<'info, Lobby>,
+
+    /// CHECK: Refund destination, must match the game's creator
+    #[account(mut, address = game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator: UncheckedAccount<'info>,
+
+    /// CHECK: Refund destination, checked against the game's opponent in the handler
+    #[account(mut)]
+    pub joiner: Option<UncheckedAccount<'info>>,
+
+    // Sponsors who fronted the players' wagers, checked against the game in
+    // the handler; only needed when a sponsor is owed part of the payout
+    #[account(mut)]
+    pub creator_sponsor: Option<SystemAccount<'info>>,
+
+    #[account(mut)]
+    pub joiner_sponsor: Option<SystemAccount<'info>>,
+
+    // Only needed when either player insured their wager
+    #[account(mut, seeds = [INSURANCE_VAULT_SEED], bump = insurance_vault.bump)]
+    pub insurance_vault: Option<Account<'info, InsuranceVault>>,
+}
+
+#[derive(Accounts)]
+pub struct MintBadge<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = config.badge_tree == merkle_tree.key() @ ErrorCode::InvalidBadgeTree
+    )]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: PDA that signs badge mints as the tree creator or delegate
+    #[account(seeds = [BADGE_AUTHORITY_SEED], bump)]
+    pub badge_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum tree config, validated by the Bubblegum program
+    #[account(mut)]
+    pub tree_config: UncheckedAccount<'info>,
+
+    /// CHECK: Badge Merkle tree, checked against the config
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Noop program
+    #[account(address = SPL_NOOP_ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+
+    /// CHECK: SPL Account Compression program
+    #[account(address = SPL_ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+
+    /// CHECK: Bubblegum program
+    #[account(address = mpl_bubblegum::ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAchievementReward<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player.key().as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: System-owned PDA holding lamports for achievement rewards
+    #[account(mut, seeds = [ACHIEVEMENT_VAULT_SEED], bump)]
+    pub achievement_vault: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wager: u64)]
+pub struct CreateMultiGame<'info> {
+    #[account(
+        init,
+        payer = creator,
+        seeds = [MULTI_GAME_SEED, creator.key().as_ref(), &wager.to_le_bytes()],
+        bump,
+        space = 8 + std::mem::size_of::<MultiGame>()
+    )]
+    pub multi_game: AccountLoader<'info, MultiGame>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinMultiGame<'info> {
+    #[account(mut)]
+    pub multi_game: AccountLoader<'info, MultiGame>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PlayMultiMove<'info> {
+    #[account(mut)]
+    pub multi_game: AccountLoader<'info, MultiGame>,
+
+    pub player: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMultiPot<'info> {
+    #[account(
+        mut,
+        close = winner,
+        constraint = multi_game.load()?.winner == winner.key() @ ErrorCode::NotWinner
+    )]
+    pub multi_game: AccountLoader<'info, MultiGame>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: Fee destination, must match the config
+    #[account(mut, address = config.house @ ErrorCode::WrongHouse)]
+    pub house: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub winner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(league_id: u64)]
+pub struct CreateLeague<'info> {
+    #[account(
+        init,
+        payer = organizer,
+        seeds = [LEAGUE_SEED, organizer.key().as_ref(), &league_id.to_le_bytes()],
+        bump,
+        space = 8 + std::mem::size_of::<League>()
+    )]
+    pub league: AccountLoader<'info, League>,
 
-        require!(
-            game_account.status == GameStatus::Open,
-            ErrorCode::GameNotOpen
-        );
+    #[account(mut)]
+    pub organizer: Signer<'info>,
 
-        game_account.opponent = Some(*ctx.accounts.joiner.key);
-        game_account.status = GameStatus::Committed;
+    pub system_program: Program<'info, System>,
+}
 
-        // Transfer wager lamports from joiner to game_account
-        let wager = game_account.wager;
-        if wager > 0 {
-            let ix = system_instruction::transfer(
-                &ctx.accounts.joiner.key(),
-                &ctx.accounts.game_account.key(),
-                wager,
-            );
-            invoke(
-                &ix,
-                &[
-                    ctx.accounts.joiner.to_account_info(),
-                    ctx.accounts.game_account.to_account_info(),
-                    ctx.accounts.system_program.to_account_info(),
-                ],
-            )?;
-        }
+#[derive(Accounts)]
+pub struct EnterLeague<'info> {
+    #[account(mut)]
+    pub league: AccountLoader<'info, League>,
 
-        Ok(())
-    }
+    #[account(mut)]
+    pub player: Signer<'info>,
 
-    // ------------------------------------
-    // Instruction: Select a move
-    // ------------------------------------
-    pub fn select_move(
-        ctx: Context<SelectMove>,
-        original_move: u8, // 0=Rock, 1=Paper, 2=Scissors
-        salt: String,
-    ) -> Result<()> {
-        let game_account = &mut ctx.accounts.game_account;
-        let player_key = ctx.accounts.player.key();
+    pub system_program: Program<'info, System>,
+}
 
-        // Hash the move with the salt
-        let mut hasher = Sha256::new();
-        hasher.update([original_move]);
-        hasher.update(salt.as_bytes());
-        let result = hasher.finalize();
-        let mut hashed_move = [0u8; 32];
-        hashed_move.copy_from_slice(&result[..32]);
+#[derive(Accounts)]
+pub struct SeedLeague<'info> {
+    #[account(mut)]
+    pub league: AccountLoader<'info, League>,
+}
 
-        // Update the appropriate player's hashed move
-        if player_key == game_account.creator {
-            game_account.creator_move_hashed = hashed_move;
-        } else if Some(player_key) == game_account.opponent {
-            game_account.joiner_move_hashed = hashed_move;
-        } else {
-            return err!(ErrorCode::Unauthorized);
-        }
+#[derive(Accounts)]
+pub struct ScheduleLeagueGame<'info> {
+    #[account(mut)]
+    pub league: AccountLoader<'info, League>,
 
-        Ok(())
-    }
+    #[account(mut, has_one = creator @ ErrorCode::WrongCreator)]
+    pub game_account: Account<'info, GameState>,
 
-    // ------------------------------------
-    // Instruction: Ready up
-    // ------------------------------------
-    pub fn ready_up(ctx: Context<ReadyUp>) -> Result<()> {
-        let game_account = &mut ctx.accounts.game_account;
-        let player_key = ctx.accounts.player.key();
-        
-        if player_key != game_account.creator && Some(player_key) != game_account.opponent {
-            return err!(ErrorCode::Unauthorized);
-        }
-        
-        // Prevent ready_up if the game has already ended
-        match game_account.status {
-            GameStatus::Ended => return err!(ErrorCode::GameAlreadyEnded),
-            _ => {}
-        }
-        
-        // Check if the player has selected a move
-        if player_key == game_account.creator {
-            require!(
-                game_account.creator_move_hashed != [0u8; 32],
-                ErrorCode::MoveNotSelected
-            );
-            game_account.creator_ready = true;
-        } else if Some(player_key) == game_account.opponent {
-            require!(
-                game_account.joiner_move_hashed != [0u8; 32],
-                ErrorCode::MoveNotSelected
-            );
-            game_account.joiner_ready = true;
-        } else {
-            return err!(ErrorCode::Unauthorized);
-        }
-    
-        // Check if both players are ready
-        if game_account.creator_ready && game_account.joiner_ready {
-            // Both players are ready; determine the winner
-            let winner = decide_winner(
-                game_account.creator_move_hashed,
-                game_account.joiner_move_hashed,
-            )?;
-            handle_payout(winner, game_account)?;
-            game_account.status = GameStatus::Ended;
-        }
-    
-        Ok(())
-    }
+    pub creator: Signer<'info>,
 }
 
-// ------------------------------------
-// Helper Functions
-// ------------------------------------
-fn decide_winner(creator_move_hashed: [u8; 32], joiner_move_hashed: [u8; 32]) -> Result<RPSResult> {
-    match (creator_move_hashed[0] % 3, joiner_move_hashed[0] % 3) {
-        (x, y) if x == y => Ok(RPSResult::Tie),
-        (0, 2) | (1, 0) | (2, 1) => Ok(RPSResult::CreatorWins),
-        _ => Ok(RPSResult::JoinerWins),
-    }
+#[derive(Accounts)]
+pub struct ReportLeagueResult<'info> {
+    #[account(mut)]
+    pub league: AccountLoader<'info, League>,
+
+    pub game_account: Account<'info, GameState>,
 }
 
-fn handle_payout(winner: RPSResult, game_account: &mut Account<GameState>) -> Result<()> {
-    match winner {
-        RPSResult::CreatorWins => msg!("Creator wins!"),
-        RPSResult::JoinerWins => msg!("Joiner wins!"),
-        RPSResult::Tie => msg!("It's a tie!"),
-    }
-    Ok(())
+#[derive(Accounts)]
+pub struct FinalizeLeague<'info> {
+    #[account(mut)]
+    pub league: AccountLoader<'info, League>,
 }
 
-// ------------------------------------
-// Data Structures
-// ------------------------------------
+#[derive(Accounts)]
+pub struct ClaimLeaguePrize<'info> {
+    #[account(mut)]
+    pub league: AccountLoader<'info, League>,
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
-pub enum GameStatus {
-    Open,
-    Committed,
-    Ended,
+    #[account(mut)]
+    pub player: Signer<'info>,
 }
 
-#[derive(Debug)]
-pub enum RPSResult {
-    CreatorWins,
-    JoinerWins,
-    Tie,
+#[derive(Accounts)]
+pub struct DistributeLeaguePrizes<'info> {
+    #[account(mut, has_one = organizer @ ErrorCode::Unauthorized, close = organizer)]
+    pub league: AccountLoader<'info, League>,
+
+    /// CHECK: Receives the league account's rent, must match the league
+    #[account(mut)]
+    pub organizer: UncheckedAccount<'info>,
 }
 
-#[account]
-#[derive(Debug)]
-pub struct GameState {
-    pub creator: Pubkey,
-    pub opponent: Option<Pubkey>,
-    pub creator_move_hashed: [u8; 32],
-    pub joiner_move_hashed: [u8; 32],
-    pub creator_ready: bool,
-    pub joiner_ready: bool,
-    pub wager: u64,
-    pub status: GameStatus,
-    pub bump: u8,
+#[derive(Accounts)]
+pub struct InitializeBankroll<'info> {
+    #[account(
+        init,
+        payer = payer,
+        seeds = [BANKROLL_SEED],
+        bump,
+        space = 8 + Bankroll::MAX_SIZE
+    )]
+    pub bankroll: Account<'info, Bankroll>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    // Pays the rent, so the admin itself can be a PDA with no lamports
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-impl GameState {
-    pub const MAX_SIZE: usize = 32 // creator pubkey
-        + 1 + 32 // optional opponent pubkey
-        + 32 // creator_move_hashed
-        + 32 // joiner_move_hashed
-        + 1 // creator_ready
-        + 1 // joiner_ready
-        + 8 // wager
-        + 1 // status
-        + 1; // bump
+#[derive(Accounts)]
+pub struct DepositBankroll<'info> {
+    #[account(mut, seeds = [BANKROLL_SEED], bump = bankroll.bump)]
+    pub bankroll: Account<'info, Bankroll>,
+
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        seeds = [LP_POSITION_SEED, depositor.key().as_ref()],
+        bump,
+        space = 8 + LpPosition::MAX_SIZE
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(wager: u64)]
-pub struct CreateGame<'info> {
+pub struct WithdrawBankroll<'info> {
+    #[account(mut, seeds = [BANKROLL_SEED], bump = bankroll.bump)]
+    pub bankroll: Account<'info, Bankroll>,
+
+    #[account(
+        mut,
+        seeds = [LP_POSITION_SEED, depositor.key().as_ref()],
+        bump = lp_position.bump
+    )]
+    pub lp_position: Account<'info, LpPosition>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateHouseGame<'info> {
     #[account(
         init,
-        payer = creator,
-        seeds = [GAME_SEED, creator.key().as_ref(), &wager.to_le_bytes()],
+        payer = player,
+        seeds = [HOUSE_GAME_SEED, player.key().as_ref()],
         bump,
-        space = 8 + GameState::MAX_SIZE
+        space = 8 + HouseGame::MAX_SIZE
     )]
-    pub game_account: Account<'info, GameState>,
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(mut, seeds = [BANKROLL_SEED], bump = bankroll.bump)]
+    pub bankroll: Account<'info, Bankroll>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
 
     #[account(mut)]
-    pub creator: Signer<'info>,
+    pub player: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct JoinGame<'info> {
-    #[account(mut)]
+pub struct SettleHouseGame<'info> {
+    #[account(
+        mut,
+        close = player,
+        seeds = [HOUSE_GAME_SEED, player.key().as_ref()],
+        bump = house_game.bump
+    )]
+    pub house_game: Account<'info, HouseGame>,
+
+    #[account(mut, seeds = [BANKROLL_SEED], bump = bankroll.bump)]
+    pub bankroll: Account<'info, Bankroll>,
+
+    /// CHECK: Payout destination, must match the house game's player
+    #[account(mut, address = house_game.player @ ErrorCode::WrongPlayer)]
+    pub player: UncheckedAccount<'info>,
+
+    /// CHECK: SlotHashes sysvar, read raw for the house move
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetGame<'info> {
+    pub game_account: Account<'info, GameState>,
+}
+
+#[derive(Accounts)]
+pub struct SimulateResult<'info> {
     pub game_account: Account<'info, GameState>,
 
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // Both are needed to preview a tie in a handicap game
+    #[account(constraint = creator_stats.player == game_account.creator @ ErrorCode::WrongCreator)]
+    pub creator_stats: Option<Account<'info, PlayerStats>>,
+
+    #[account(
+        constraint = Some(joiner_stats.player) == game_account.opponent @ ErrorCode::WrongOpponent
+    )]
+    pub joiner_stats: Option<Account<'info, PlayerStats>>,
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct SettleMany<'info> {
+    // Receives the pots of house games the player lost
+    #[account(mut, seeds = [BANKROLL_SEED], bump = bankroll.bump)]
+    pub bankroll: Account<'info, Bankroll>,
+
+    /// CHECK: SlotHashes sysvar, read raw for house moves
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    // Only needed when a stale game in the batch is insured
+    #[account(mut, seeds = [INSURANCE_VAULT_SEED], bump = insurance_vault.bump)]
+    pub insurance_vault: Option<Account<'info, InsuranceVault>>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct PlaceParlay<'info> {
+    #[account(
+        init,
+        payer = bettor,
+        seeds = [PARLAY_SEED, bettor.key().as_ref(), &id.to_le_bytes()],
+        bump,
+        space = 8 + Parlay::MAX_SIZE
+    )]
+    pub parlay: Account<'info, Parlay>,
+
+    #[account(mut, seeds = [BANKROLL_SEED], bump = bankroll.bump)]
+    pub bankroll: Account<'info, Bankroll>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
     #[account(mut)]
-    pub joiner: Signer<'info>,
+    pub bettor: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SelectMove<'info> {
-    #[account(mut)]
-    pub game_account: Account<'info, GameState>,
+pub struct SettleParlay<'info> {
+    #[account(mut, has_one = bettor @ ErrorCode::WrongPlayer)]
+    pub parlay: Account<'info, Parlay>,
+
+    #[account(mut, seeds = [BANKROLL_SEED], bump = bankroll.bump)]
+    pub bankroll: Account<'info, Bankroll>,
 
+    /// CHECK: Receives the return and the rent, must match the parlay's bettor
     #[account(mut)]
-    pub player: Signer<'info>,
+    pub bettor: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct ReadyUp<'info> {
+pub struct ApplyDecay<'info> {
+    #[account(
+        mut,
+        seeds = [PLAYER_STATS_SEED, player_stats.player.as_ref()],
+        bump = player_stats.bump
+    )]
+    pub player_stats: Account<'info, PlayerStats>,
+
+    #[account(seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    /// CHECK: Any program account; its owner, discriminator and version are
+    /// checked by the handler since older layouts can't be deserialized
     #[account(mut)]
-    pub game_account: Account<'info, GameState>,
+    pub account: UncheckedAccount<'info>,
 
     #[account(mut)]
-    pub player: Signer<'info>,
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[error_code]
@@ -301,4 +10979,342 @@ pub enum ErrorCode {
 
     #[msg("Game has already ended.")]
     GameAlreadyEnded,
+
+    #[msg("Moves can only be selected while the game is committed.")]
+    GameNotCommitted,
+
+    #[msg("The game is not in the reveal phase.")]
+    NotRevealPhase,
+
+    #[msg("Invalid move; expected 0 (Rock), 1 (Paper) or 2 (Scissors).")]
+    InvalidMove,
+
+    #[msg("The revealed move does not match the commitment.")]
+    CommitmentMismatch,
+
+    #[msg("The player has already revealed their move.")]
+    AlreadyRevealed,
+
+    #[msg("Arithmetic overflow or underflow.")]
+    ArithmeticOverflow,
+
+    #[msg("The payout exceeds the escrowed amount.")]
+    InsufficientEscrow,
+
+    #[msg("The game account does not hold its escrow plus the rent-exempt minimum.")]
+    EscrowInvariantViolated,
+
+    #[msg("A player has already revealed; the game cannot be refunded.")]
+    GameHasReveals,
+
+    #[msg("The reveal deadline has not passed yet.")]
+    RevealDeadlineNotReached,
+
+    #[msg("New games are paused by the admin.")]
+    ProgramPaused,
+
+    #[msg("The game has not been flagged for emergency refund.")]
+    GameNotFlagged,
+
+    #[msg("The minimum wager must not exceed the maximum wager.")]
+    InvalidWagerLimits,
+
+    #[msg("The wager is below the configured minimum.")]
+    WagerTooLow,
+
+    #[msg("The wager is above the configured maximum.")]
+    WagerTooHigh,
+
+    #[msg("The badge URI prefix is too long.")]
+    BadgeUriTooLong,
+
+    #[msg("The player has not reached this badge's milestone.")]
+    BadgeNotEarned,
+
+    #[msg("This badge has already been minted for the player.")]
+    BadgeAlreadyMinted,
+
+    #[msg("The Merkle tree does not match the configured badge tree.")]
+    InvalidBadgeTree,
+
+    #[msg("The player has not unlocked this achievement.")]
+    AchievementLocked,
+
+    #[msg("The reward for this achievement has already been claimed.")]
+    AchievementAlreadyClaimed,
+
+    #[msg("Basis points must not exceed 10000.")]
+    InvalidFeeBps,
+
+    #[msg("The SlotHashes sysvar could not be read.")]
+    InvalidSlotHashes,
+
+    #[msg("The lobby bucket index is out of range.")]
+    InvalidLobbyBucket,
+
+    #[msg("The player is already waiting in this queue.")]
+    AlreadyQueued,
+
+    #[msg("The player is not waiting in this queue.")]
+    NotQueued,
+
+    #[msg("The matchmaking queue is full.")]
+    QueueFull,
+
+    #[msg("At least two players must be queued to make a match.")]
+    NotEnoughQueuedPlayers,
+    #[msg("Session keys must expire in the future and within the maximum session length.")]
+    InvalidSessionExpiry,
+    #[msg("Expected an ed25519 signature verification instruction before this one.")]
+    MissingSignatureVerification,
+    #[msg("The ed25519 signature does not cover the expected signer and message.")]
+    SignatureMismatch,
+    #[msg("This game is invite only; join with a permit from the creator.")]
+    InviteRequired,
+    #[msg("The join permit has expired.")]
+    PermitExpired,
+    #[msg("The creator must be ready before the joiner can reveal on join.")]
+    CreatorNotReady,
+    #[msg("A ready player's commitment is locked.")]
+    AlreadyReady,
+    #[msg("Elimination games seat between 3 and 8 players.")]
+    InvalidSeatCount,
+    #[msg("The player already has a seat in this game.")]
+    AlreadySeated,
+    #[msg("The game has not ended yet.")]
+    GameNotEnded,
+    #[msg("The seat in this game is reserved for another player.")]
+    SeatReserved,
+    #[msg("League rosters need 2 to 8 distinct players.")]
+    InvalidLeagueRoster,
+    #[msg("League registration must close by the start, and the league must start before it ends.")]
+    InvalidLeagueWindow,
+    #[msg("The player is not on the league roster.")]
+    NotInLeague,
+    #[msg("The player has already entered the league.")]
+    AlreadyEntered,
+    #[msg("Both players must have paid the league entry fee.")]
+    NotEntered,
+    #[msg("League games can only be scheduled during the league window.")]
+    LeagueNotActive,
+    #[msg("This pairing has already been scheduled.")]
+    FixtureAlreadyScheduled,
+    #[msg("This pairing has already been reported.")]
+    FixtureAlreadyPlayed,
+    #[msg("The game is not a scheduled league fixture.")]
+    NotLeagueGame,
+    #[msg("The league window has not closed yet.")]
+    LeagueNotOver,
+    #[msg("The league has been finalized.")]
+    LeagueFinalized,
+    #[msg("The league prize has already been claimed.")]
+    PrizeAlreadyClaimed,
+    #[msg("A rematch has already been offered for this game.")]
+    RematchAlreadyOffered,
+    #[msg("No rematch has been offered for this game.")]
+    NoRematchOffered,
+    #[msg("The mint is not an NFT (supply 1, no decimals).")]
+    NotAnNft,
+    #[msg("The player has already staked an NFT in this game.")]
+    NftAlreadyStaked,
+    #[msg("That NFT is not staked in this game.")]
+    NftNotStaked,
+    #[msg("Escrowed NFTs and tokens stay locked until the game is settled or refundable.")]
+    EscrowLocked,
+    #[msg("Escrowed NFTs and tokens must be released first.")]
+    TokensStillEscrowed,
+    #[msg("The pot already holds the maximum number of token assets.")]
+    TooManyPotAssets,
+    #[msg("Pot asset amounts must be positive.")]
+    InvalidPotAmount,
+    #[msg("No matching pot asset at that index.")]
+    InvalidPotAsset,
+    #[msg("The joiner has already matched this pot asset.")]
+    PotAlreadyDeposited,
+    #[msg("The pot asset has already been settled.")]
+    PotAlreadySettled,
+    #[msg("The joiner must match every pot asset before readying up.")]
+    PotDepositMissing,
+    #[msg("Bankroll amounts must be positive and within the position.")]
+    InvalidBankrollAmount,
+    #[msg("The bankroll cannot cover this wager.")]
+    InsufficientBankroll,
+    #[msg("The house move is decided by a later slot; try again shortly.")]
+    HouseMoveNotReady,
+    #[msg("Fee recipients are limited in number and their weights must total 100%.")]
+    InvalidFeeRecipients,
+    #[msg("A configured fee recipient is missing from the remaining accounts.")]
+    MissingFeeRecipient,
+    #[msg("The game is past its join deadline.")]
+    JoinDeadlinePassed,
+    #[msg("The account is not a program account of the expected type.")]
+    InvalidMigrationAccount,
+    #[msg("The account version is not supported by this program.")]
+    UnsupportedAccountVersion,
+    #[msg("The account holds an unknown game status.")]
+    InvalidGameStatus,
+    #[msg("Too many games in one batch settlement.")]
+    TooManySettlements,
+    #[msg("The batch settlement accounts are malformed.")]
+    InvalidSettlementAccount,
+    #[msg("The signer is not a player in this game.")]
+    NotAPlayer,
+    #[msg("The account does not match the game's creator.")]
+    WrongCreator,
+    #[msg("The account does not match the game's opponent.")]
+    WrongOpponent,
+    #[msg("The account does not match the house game's player.")]
+    WrongPlayer,
+    #[msg("The account does not match the configured house.")]
+    WrongHouse,
+    #[msg("The recipient is not entitled to this escrowed asset.")]
+    WrongRecipient,
+    #[msg("Only the loser of a settled game can offer a rematch.")]
+    NotRematchLoser,
+    #[msg("The player who offered the rematch cannot accept it.")]
+    OwnRematchOffer,
+    #[msg("Only the winner can claim this pot.")]
+    NotWinner,
+    #[msg("Messages must be between 1 and MAX_MESSAGE_LEN bytes.")]
+    InvalidMessage,
+    #[msg("This game requires the creator's approval; submit a join request.")]
+    ApprovalRequired,
+    #[msg("This game does not take join requests.")]
+    ApprovalNotRequired,
+    #[msg("Allowlists hold between 1 and MAX_ALLOWLIST_LEN players.")]
+    InvalidAllowlist,
+    #[msg("The player is not on this game's allowlist.")]
+    NotOnAllowlist,
+    #[msg("The game can still be joined.")]
+    GameStillOpen,
+    #[msg("The other player has to reveal first in this game.")]
+    RevealOutOfOrder,
+    #[msg("The join request's bond does not match the game's reveal bond.")]
+    BondMismatch,
+    #[msg("Only a game with exactly one reveal can be claimed; refund it instead.")]
+    NothingToClaim,
+    #[msg("The joiner's reputation is below the game's minimum.")]
+    ReputationTooLow,
+    #[msg("This player is banned from creating or joining games.")]
+    PlayerBanned,
+    #[msg("The ban list is full.")]
+    BanListFull,
+    #[msg("The treasury does not hold that many withdrawable lamports.")]
+    InsufficientTreasury,
+    #[msg("A fee holiday can't end before it starts.")]
+    InvalidFeeHoliday,
+    #[msg("Fee tiers must be sorted by ascending wager, at most four of them.")]
+    InvalidFeeTiers,
+    #[msg("Not enough fees paid yet to claim rakeback.")]
+    RakebackThresholdNotMet,
+    #[msg("No rakeback is owed.")]
+    NoRakebackOwed,
+    #[msg("No loyalty points are waiting to be minted.")]
+    NoPointsOwed,
+    #[msg("This player takes payouts as wSOL; pass their wSOL account and the token program.")]
+    WsolAccountMissing,
+    #[msg("The account is not a wSOL account of the player.")]
+    InvalidWsolAccount,
+    #[msg("The game is not waiting on a tie-break.")]
+    NoTieBreakPending,
+    #[msg("A time bank can't be negative.")]
+    InvalidTimeBank,
+    #[msg("This player's time bank has run out.")]
+    TimeBankExhausted,
+    #[msg("Neither player has run out of time.")]
+    NoTimeForfeit,
+    #[msg("The join request was made at a different wager.")]
+    WagerMismatch,
+    #[msg("The game has no such counter-offer pending.")]
+    CounterOfferMismatch,
+    #[msg("A parlay needs two to five distinct games, passed in the order of its picks.")]
+    InvalidParlayLegs,
+    #[msg("Parlays can only pick games that have not reached their reveal phase.")]
+    GameNotInFlight,
+    #[msg("The join window must be positive and at most seven days.")]
+    InvalidJoinWindow,
+    #[msg("The seed schema version must increase and the migration window can't be negative.")]
+    InvalidSeedVersion,
+    #[msg("Usernames are 3 to 32 lowercase letters, digits or underscores.")]
+    InvalidUsername,
+    #[msg("Release the current username before registering another.")]
+    UsernameAlreadySet,
+    #[msg("The player doesn't hold this NFT.")]
+    AvatarNotOwned,
+    #[msg("The metadata account doesn't describe this mint.")]
+    InvalidMetadata,
+    #[msg("The rating decay window can't be negative.")]
+    InvalidRatingDecay,
+    #[msg("No rating decay is due for this player.")]
+    NoRatingDecay,
+    #[msg("Leaderboards have 1 to 16 shards of a positive rating band each.")]
+    InvalidShardMap,
+    #[msg("The player's rating or listing belongs to another leaderboard shard.")]
+    WrongLeaderboardShard,
+    #[msg("Only the week that just ended can be snapshotted.")]
+    InvalidSnapshotWeek,
+    #[msg("The player didn't place in this week's snapshot.")]
+    NotASnapshotWinner,
+    #[msg("League registration is not open.")]
+    RegistrationClosed,
+    #[msg("The league has already been seeded.")]
+    LeagueAlreadySeeded,
+    #[msg("Seed the league before scheduling fixtures.")]
+    LeagueNotSeeded,
+    #[msg("The player has excluded themselves from new games for now.")]
+    SelfExcluded,
+    #[msg("The game is over the player's self-imposed limits.")]
+    OverPlayerLimit,
+    #[msg("The player's limits are locked; they can only be tightened until the lock expires.")]
+    LimitsLocked,
+    #[msg("The limit lock can't be negative.")]
+    InvalidLimitLock,
+    #[msg("The player has reached the daily wager cap.")]
+    DailyWagerCapReached,
+    #[msg("The cooldown can't be negative.")]
+    InvalidCooldown,
+    #[msg("Wait for the cooldown before creating another game.")]
+    CreationCooldown,
+    #[msg("Creating games needs a token account holding the required stake.")]
+    CreationStakeRequired,
+    #[msg("Pass the treasury to pay the creation fee.")]
+    TreasuryMissing,
+    #[msg("Pass the sponsor who fronted this wager so they can be repaid.")]
+    SponsorMissing,
+    #[msg("A sponsored wager can't be changed.")]
+    WagerSponsored,
+    #[msg("Registered bots can't join this game.")]
+    BotsNotAllowed,
+    #[msg("The callback program or its accounts don't match the registration.")]
+    InvalidCallback,
+    #[msg("Loss insurance is not on offer.")]
+    InsuranceDisabled,
+    #[msg("This wager is already insured.")]
+    AlreadyInsured,
+    #[msg("The insurance vault can't cover another policy.")]
+    InsuranceVaultInsolvent,
+    #[msg("The insurance vault account is required for an insured game.")]
+    InsuranceVaultMissing,
+    #[msg("Games are played with an odd number of moves from 3 to 9.")]
+    InvalidMoveCount,
+    #[msg("A move can't beat itself, a move outside the set or a move that beats it.")]
+    InvalidRuleset,
+    #[msg("The game's moves are set by its ruleset.")]
+    RulesetGame,
+    #[msg("Labels are at most MAX_LABEL_LEN bytes and may not contain NUL.")]
+    InvalidLabel,
+    #[msg("The join deadline has not passed yet.")]
+    JoinDeadlineNotReached,
+    #[msg("The wallet has reached the cap on open games.")]
+    TooManyOpenGames,
+    #[msg("Breaking this tie needs the ORAO VRF accounts.")]
+    MissingVrfAccounts,
+    #[msg("The VRF account does not match the game's tie-break request.")]
+    InvalidVrfAccount,
+    #[msg("The VRF network has not fulfilled the randomness yet.")]
+    RandomnessNotFulfilled,
+    #[msg("The game holds no winner's payout to play for.")]
+    NoRolloverPot,
+    #[msg("One of the game callback's accounts is missing.")]
+    MissingCallback,
 }
@@ -4,9 +4,26 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { RpsGame } from "../target/types/rps_game"; // Ensure this path is correct
 import { assert } from "chai";
-import { Keypair, PublicKey, SystemProgram } from "@solana/web3.js";
+import {
+  Ed25519Program,
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  SYSVAR_SLOT_HASHES_PUBKEY,
+} from "@solana/web3.js";
 import { BN } from "bn.js";
 import * as crypto from "crypto"; // Import crypto for hashing
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  createAccount,
+  createMint,
+  createWrappedNativeAccount,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  mintTo,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
 
 describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
   // Configure the client to use the local cluster.
@@ -20,6 +37,77 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
   const joiner = Keypair.generate();
   const house = Keypair.generate(); // House account can be a Keypair or a predefined account
 
+  // Global config PDA
+  const [configPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+
+  // Jackpot vault PDA, funded from the house fee
+  const [jackpotVaultPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("jackpot_vault")],
+    program.programId
+  );
+
+  // Helper function to derive the lobby bucket PDA an open game is listed in
+  const lobbyPda = (gameAccountPda: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("lobby"), Buffer.from([gameAccountPda.toBuffer()[0] % 4])],
+      program.programId
+    )[0];
+
+  // Wager limits stored in the config
+  const minWager = 1_000_000;
+  const maxWager = anchor.web3.LAMPORTS_PER_SOL;
+
+  // Signer of the self-CPIs that carry lifecycle events
+  const [eventAuthorityPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("__event_authority")],
+    program.programId
+  );
+
+  // Decodes the events a transaction emitted through self-CPIs
+  const cpiEvents = async (signature: string) => {
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const accountKeys = tx.transaction.message.staticAccountKeys;
+    return tx.meta.innerInstructions
+      .flatMap((inner) => inner.instructions)
+      .filter((ix) => accountKeys[ix.programIdIndex].equals(program.programId))
+      .map((ix) =>
+        program.coder.events.decode(
+          anchor.utils.bytes.base64.encode(anchor.utils.bytes.bs58.decode(ix.data).subarray(8))
+        )
+      )
+      .filter((event) => event !== null);
+  };
+
+  // GameState flag bits, see GAME_FLAG_* in the program
+  const CREATOR_READY = 1 << 0;
+  const JOINER_READY = 1 << 1;
+  const HANDICAP = 1 << 10;
+  const CALLBACK = 1 << 12;
+  const hasFlag = (game: { flags: number }, flag: number): boolean => (game.flags & flag) !== 0;
+
+  // Mirrors REVEAL_MOVE_CU_BUDGET in the program
+  const REVEAL_MOVE_CU_BUDGET = 60_000;
+
+  // Helper function to derive a player's stats PDA
+  const playerStatsPda = (player: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("player_stats"), player.toBuffer()],
+      program.programId
+    )[0];
+
+  // Helper function to derive a player's match history PDA
+  const playerHistoryPda = (player: PublicKey): PublicKey =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("player_history"), player.toBuffer()],
+      program.programId
+    )[0];
+
   // Helper function to derive PDA
   const findGameAccountPda = async (
     creator: Keypair,
@@ -35,9 +123,16 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
     );
   };
 
-  // Helper function to compute SHA-256 hash
-  const hashMove = (original_move: number, salt: string): Buffer => {
+  // Helper function to compute the SHA-256 move commitment, bound to the game
+  const hashMove = async (
+    gameAccountPda: PublicKey,
+    original_move: number,
+    salt: string
+  ): Promise<Buffer> => {
+    const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
     const hash = crypto.createHash("sha256");
+    hash.update(gameAccountPda.toBuffer());
+    hash.update(gameAccountData.nonce.toArrayLike(Buffer, "le", 8));
     hash.update(Buffer.from([original_move]));
     hash.update(Buffer.from(salt));
     return hash.digest();
@@ -65,6 +160,340 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       airdropAmountHouse
     );
     await provider.connection.confirmTransaction(airdropSignatureHouse, "confirmed");
+
+    // Initialize the global config with the provider wallet as admin
+    await program.rpc.initializeConfig(new BN(minWager), new BN(maxWager), {
+      accounts: {
+        config: configPda,
+        admin: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      },
+    });
+
+    // Create every lobby bucket
+    for (let bucket = 0; bucket < 4; bucket++) {
+      const [lobby] = PublicKey.findProgramAddressSync(
+        [Buffer.from("lobby"), Buffer.from([bucket])],
+        program.programId
+      );
+      await program.rpc.initializeLobby(bucket, {
+        accounts: {
+          lobby,
+          config: configPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+      });
+    }
+  });
+
+  describe("Config", () => {
+    it("Blocks new games while paused", async () => {
+      const wager = 1_000_000;
+
+      await program.rpc.setPaused(true, {
+        accounts: { config: configPda, admin: provider.wallet.publicKey },
+      });
+
+      const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
+      try {
+        await program.rpc.createGame(new BN(wager), {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            creator: creator.publicKey,
+            payer: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [creator],
+        });
+        assert.fail("The transaction should have failed because the program is paused");
+      } catch (err: any) {
+        assert.include(
+          err.message,
+          "ProgramPaused",
+          "The error message should contain 'ProgramPaused'"
+        );
+      } finally {
+        await program.rpc.setPaused(false, {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+      }
+    });
+
+    it("Rejects wagers outside the configured limits", async () => {
+      for (const wager of [minWager - 1, maxWager + 1]) {
+        const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
+        try {
+          await program.rpc.createGame(new BN(wager), {
+            accounts: {
+              gameAccount: gameAccountPda,
+              config: configPda,
+              lobby: lobbyPda(gameAccountPda),
+              creator: creator.publicKey,
+              payer: creator.publicKey,
+              creatorStats: playerStatsPda(creator.publicKey),
+              systemProgram: SystemProgram.programId,
+              eventAuthority: eventAuthorityPda,
+              program: program.programId,
+            },
+            signers: [creator],
+          });
+          assert.fail("The transaction should have failed because the wager is out of bounds");
+        } catch (err: any) {
+          assert.match(
+            err.message,
+            /WagerTooLow|WagerTooHigh/,
+            "The error message should mention the wager limits"
+          );
+        }
+      }
+    });
+
+    it("Rejects fees above 100%", async () => {
+      try {
+        await program.rpc.setFees(house.publicKey, 10_001, 0, {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+        assert.fail("The transaction should have failed because the fee is above 10000 bps");
+      } catch (err: any) {
+        assert.include(
+          err.message,
+          "InvalidFeeBps",
+          "The error message should contain 'InvalidFeeBps'"
+        );
+      }
+    });
+
+    it("Keeps banned players from creating games", async () => {
+      const wager = 1_000_000;
+      const bot = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(bot.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+        "confirmed"
+      );
+      const [gameAccountPda] = await findGameAccountPda(bot, wager, program.programId);
+      const createGame = () =>
+        program.rpc.createGame(new BN(wager), {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            creator: bot.publicKey,
+            payer: bot.publicKey,
+            creatorStats: playerStatsPda(bot.publicKey),
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [bot],
+        });
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+
+      await program.rpc.setBanned(bot.publicKey, true, { accounts: updateAccounts });
+      try {
+        await createGame();
+        assert.fail("A banned player should not be able to create a game");
+      } catch (err: any) {
+        assert.include(err.message, "PlayerBanned");
+      }
+
+      await program.rpc.setBanned(bot.publicKey, false, { accounts: updateAccounts });
+      await createGame();
+      const configData = await program.account.config.fetch(configPda);
+      assert.isEmpty(configData.banned);
+    });
+
+    it("Accepts an admin that only signs, as a multisig vault would", async () => {
+      // Stands in for a vault PDA: it never holds lamports, so the provider
+      // wallet pays every fee while the vault only signs
+      const vault = Keypair.generate();
+      await program.rpc.setAdmin(vault.publicKey, {
+        accounts: { config: configPda, admin: provider.wallet.publicKey },
+      });
+      try {
+        await program.rpc.setPaused(false, {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+        assert.fail("The previous admin should have lost its authority");
+      } catch (err: any) {
+        assert.include(err.message, "Unauthorized");
+      }
+
+      await program.rpc.setPaused(false, {
+        accounts: { config: configPda, admin: vault.publicKey },
+        signers: [vault],
+      });
+      await program.rpc.setAdmin(provider.wallet.publicKey, {
+        accounts: { config: configPda, admin: vault.publicKey },
+        signers: [vault],
+      });
+      assert.equal(await provider.connection.getBalance(vault.publicKey), 0);
+
+      const configData = await program.account.config.fetch(configPda);
+      assert.isTrue(configData.admin.equals(provider.wallet.publicKey));
+    });
+
+    it("Accrues fees in the treasury until the admin withdraws them", async () => {
+      const [treasuryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury")],
+        program.programId
+      );
+      const configBefore = await program.account.config.fetch(configPda);
+      await program.rpc.initializeTreasury({
+        accounts: {
+          treasury: treasuryPda,
+          config: configPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+      });
+      let configData = await program.account.config.fetch(configPda);
+      assert.isTrue(configData.house.equals(treasuryPda));
+
+      // Stand in for fees accrued at settlement
+      const fees = 3_000_000;
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: provider.wallet.publicKey,
+            toPubkey: treasuryPda,
+            lamports: fees,
+          })
+        )
+      );
+
+      const recipient = Keypair.generate();
+      const withdrawFees = (amount: number) =>
+        program.rpc.withdrawFees(new BN(amount), {
+          accounts: {
+            treasury: treasuryPda,
+            config: configPda,
+            admin: provider.wallet.publicKey,
+            recipient: recipient.publicKey,
+          },
+        });
+      try {
+        await withdrawFees(fees + 1);
+        assert.fail("Withdrawing into the treasury's rent should fail");
+      } catch (err: any) {
+        assert.include(err.message, "InsufficientTreasury");
+      }
+      await withdrawFees(fees);
+      assert.equal(await provider.connection.getBalance(recipient.publicKey), fees);
+      const treasuryData = await program.account.treasury.fetch(treasuryPda);
+      assert.equal(treasuryData.totalWithdrawn.toNumber(), fees);
+
+      // Later tests pay the house directly
+      await program.rpc.setFees(
+        configBefore.house,
+        configBefore.houseFeeBps,
+        configBefore.jackpotShareBps,
+        { accounts: { config: configPda, admin: provider.wallet.publicKey } }
+      );
+      configData = await program.account.config.fetch(configPda);
+      assert.isTrue(configData.house.equals(configBefore.house));
+    });
+
+    it("Schedules a fee holiday", async () => {
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const now = Math.floor(Date.now() / 1000);
+      try {
+        await program.rpc.setFeeHoliday(new BN(now + 60), new BN(now), 0, {
+          accounts: updateAccounts,
+        });
+        assert.fail("A window ending before it starts should be rejected");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidFeeHoliday");
+      }
+
+      await program.rpc.setFeeHoliday(new BN(now), new BN(now + 3600), 25, {
+        accounts: updateAccounts,
+      });
+      let configData = await program.account.config.fetch(configPda);
+      assert.equal(configData.feeHolidayStart.toNumber(), now);
+      assert.equal(configData.feeHolidayEnd.toNumber(), now + 3600);
+      assert.equal(configData.feeHolidayBps, 25);
+
+      // An empty window switches the holiday off again
+      await program.rpc.setFeeHoliday(new BN(0), new BN(0), 0, { accounts: updateAccounts });
+      configData = await program.account.config.fetch(configPda);
+      assert.equal(configData.feeHolidayEnd.toNumber(), 0);
+    });
+
+    it("Applies a batch of config changes in one instruction", async () => {
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const unchanged = {
+        paused: null,
+        minWager: null,
+        maxWager: null,
+        house: null,
+        houseFeeBps: null,
+        jackpotShareBps: null,
+      };
+      const configBefore = await program.account.config.fetch(configPda);
+
+      try {
+        await program.rpc.updateConfig(
+          { ...unchanged, minWager: configBefore.maxWager.addn(1) },
+          { accounts: updateAccounts }
+        );
+        assert.fail("A minimum above the maximum wager should be rejected");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidWagerLimits");
+      }
+
+      await program.rpc.updateConfig(
+        { ...unchanged, maxWager: configBefore.maxWager.addn(1), houseFeeBps: 100 },
+        { accounts: updateAccounts }
+      );
+      let configData = await program.account.config.fetch(configPda);
+      assert.equal(configData.maxWager.toNumber(), configBefore.maxWager.toNumber() + 1);
+      assert.equal(configData.houseFeeBps, 100);
+      assert.isTrue(configData.house.equals(configBefore.house), "Unset fields are kept");
+
+      await program.rpc.updateConfig(
+        { ...unchanged, maxWager: configBefore.maxWager, houseFeeBps: configBefore.houseFeeBps },
+        { accounts: updateAccounts }
+      );
+      configData = await program.account.config.fetch(configPda);
+      assert.equal(configData.maxWager.toNumber(), configBefore.maxWager.toNumber());
+    });
+
+    it("Keeps new games on the current seed schema unless it moves forward", async () => {
+      const configData = await program.account.config.fetch(configPda);
+      assert.equal(configData.seedVersion, 0, "Games use the original seed layout");
+      try {
+        await program.rpc.setSeedVersion(0, new BN(0), {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+        assert.fail("The seed schema can't stay the same");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidSeedVersion");
+      }
+    });
+
+    it("Only the admin can pause", async () => {
+      try {
+        await program.rpc.setPaused(true, {
+          accounts: { config: configPda, admin: creator.publicKey },
+          signers: [creator],
+        });
+        assert.fail("The transaction should have failed because the signer is not the admin");
+      } catch (err: any) {
+        assert.include(
+          err.message,
+          "Unauthorized",
+          "The error message should contain 'Unauthorized'"
+        );
+      }
+    });
   });
 
   describe("Create Game", () => {
@@ -80,8 +509,14 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
         {
           accounts: {
             gameAccount: gameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
             creator: creator.publicKey,
+            payer: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
           },
           signers: [creator],
         }
@@ -107,20 +542,179 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
         Array(32).fill(0),
         "Joiner move hashed should be initialized to zeroes"
       );
-      assert.equal(gameAccountData.creatorReady, false, "Creator should not be ready initially");
-      assert.equal(gameAccountData.joinerReady, false, "Joiner should not be ready initially");
+      assert.equal(gameAccountData.flags, 0, "No flags should be set initially");
       assert.equal(gameAccountData.wager.toNumber(), wager, "Wager amount mismatch");
+      assert.equal(gameAccountData.escrow.toNumber(), wager, "Escrow should hold the creator's wager");
       assert.deepEqual(
         gameAccountData.status,
         { open: {} },
         "Game status should be Open"
       );
       assert.equal(gameAccountData.bump, bump, "Bump seed mismatch");
+      assert.isAbove(gameAccountData.createdAt.toNumber(), 0, "Creation time should be set");
+      assert.equal(
+        gameAccountData.joinDeadline.toNumber(),
+        gameAccountData.createdAt.toNumber() + 7 * 24 * 60 * 60,
+        "Join deadline should be a week after creation"
+      );
+      assert.equal(gameAccountData.revealDeadline.toNumber(), 0, "No reveal deadline until ready");
+
+      // The open game is listed in its lobby bucket
+      const lobbyData = await program.account.lobby.fetch(lobbyPda(gameAccountPda));
+      assert.isTrue(
+        lobbyData.games.some((entry: any) => entry.game.equals(gameAccountPda)),
+        "Game should be listed in the lobby"
+      );
 
       // Optional: Log PDA and bump for verification
       console.log("Game Account PDA:", gameAccountPda.toBase58());
       console.log("Stored Bump in Account:", gameAccountData.bump);
     });
+
+    it("Counts spectators on a game", async () => {
+      const [gameAccountPda] = await findGameAccountPda(creator, 100_000_000, program.programId);
+      await program.rpc.spectate({ accounts: { gameAccount: gameAccountPda } });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.spectators, 1);
+    });
+
+    it("Lets a sponsor pay the rent of a new player's game", async () => {
+      const wager = 34_600_000;
+      const [player, sponsor] = [Keypair.generate(), Keypair.generate()];
+      for (const wallet of [player, sponsor]) {
+        await provider.connection.confirmTransaction(
+          await provider.connection.requestAirdrop(wallet.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+          "confirmed"
+        );
+      }
+      const [gameAccountPda] = await findGameAccountPda(player, wager, program.programId);
+      const playerBefore = await provider.connection.getBalance(player.publicKey);
+      const sponsorBefore = await provider.connection.getBalance(sponsor.publicKey);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: player.publicKey,
+          payer: sponsor.publicKey,
+          creatorStats: playerStatsPda(player.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [player, sponsor],
+      });
+
+      const playerAfter = await provider.connection.getBalance(player.publicKey);
+      const sponsorAfter = await provider.connection.getBalance(sponsor.publicKey);
+      assert.equal(playerBefore - playerAfter, wager, "The player only funds the wager");
+      assert.isAbove(sponsorBefore - sponsorAfter, 0, "The sponsor pays the rent");
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.creator.equals(player.publicKey));
+    });
+  });
+
+  describe("Cancel Game", () => {
+    it("Creator cancels an open game and is refunded", async () => {
+      const wager = 5_000_000;
+
+      const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creatorStats: playerStatsPda(creator.publicKey),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+
+      const gameAccountBalance = await provider.connection.getBalance(gameAccountPda);
+      const initialCreatorBalance = await provider.connection.getBalance(creator.publicKey);
+
+      await program.rpc.cancelGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+
+      const finalCreatorBalance = await provider.connection.getBalance(creator.publicKey);
+      assert.equal(
+        finalCreatorBalance,
+        initialCreatorBalance + gameAccountBalance,
+        "Creator should receive the wager and the rent"
+      );
+
+      const lobbyData = await program.account.lobby.fetch(lobbyPda(gameAccountPda));
+      assert.isFalse(
+        lobbyData.games.some((entry: any) => entry.game.equals(gameAccountPda)),
+        "Cancelled game should be removed from the lobby"
+      );
+    });
+
+    it("Repays the sponsor who fronted a cancelled game's wager", async () => {
+      const wager = 34_700_000;
+      const [player, sponsor] = [Keypair.generate(), Keypair.generate()];
+      for (const wallet of [player, sponsor]) {
+        await provider.connection.confirmTransaction(
+          await provider.connection.requestAirdrop(wallet.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+          "confirmed"
+        );
+      }
+      const [gameAccountPda] = await findGameAccountPda(player, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: player.publicKey,
+          payer: player.publicKey,
+          sponsor: sponsor.publicKey,
+          creatorStats: playerStatsPda(player.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [player, sponsor],
+      });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.creatorSponsor.equals(sponsor.publicKey));
+
+      const cancelAccounts = {
+        gameAccount: gameAccountPda,
+        creatorStats: playerStatsPda(player.publicKey),
+        lobby: lobbyPda(gameAccountPda),
+        creator: player.publicKey,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      try {
+        await program.rpc.cancelGame({ accounts: cancelAccounts, signers: [player] });
+        assert.fail("The sponsor must be repaid");
+      } catch (err: any) {
+        assert.include(err.message, "SponsorMissing");
+      }
+      const sponsorBefore = await provider.connection.getBalance(sponsor.publicKey);
+      await program.rpc.cancelGame({
+        accounts: { ...cancelAccounts, creatorSponsor: sponsor.publicKey },
+        signers: [player],
+      });
+      const sponsorAfter = await provider.connection.getBalance(sponsor.publicKey);
+      assert.equal(sponsorAfter - sponsorBefore, wager, "The sponsor gets the wager back");
+    });
   });
 
   describe("Join Game", () => {
@@ -138,8 +732,15 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       await program.rpc.joinGame({
         accounts: {
           gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
           joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
           systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
         },
         signers: [joiner],
       });
@@ -158,6 +759,18 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
         { committed: {} },
         "Game status should be Committed"
       );
+      assert.equal(
+        gameAccountData.escrow.toNumber(),
+        2 * wager,
+        "Escrow should hold both wagers"
+      );
+
+      // A joined game is no longer listed
+      const lobbyData = await program.account.lobby.fetch(lobbyPda(gameAccountPda));
+      assert.isFalse(
+        lobbyData.games.some((entry: any) => entry.game.equals(gameAccountPda)),
+        "Game should be removed from the lobby"
+      );
 
       // Verify the transfer of lamports
       const finalGameAccountBalance = await provider.connection.getBalance(gameAccountPda);
@@ -201,8 +814,15 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
         await program.rpc.joinGame({
           accounts: {
             gameAccount: gameAccountPda,
+            creatorStats: playerStatsPda(creator.publicKey),
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
             joiner: secondJoiner.publicKey,
+            joinerStats: playerStatsPda(secondJoiner.publicKey),
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
           },
           signers: [secondJoiner],
         });
@@ -227,10 +847,12 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       // Find PDA using helper function
       const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
 
+      // Compute the commitment client-side
+      const expectedHash = await hashMove(gameAccountPda, original_move, salt);
+
       // Invoke the select_move instruction as creator
       await program.rpc.selectMove(
-        original_move,
-        salt,
+        Array.from(expectedHash),
         {
           accounts: {
             gameAccount: gameAccountPda,
@@ -244,9 +866,6 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       // Fetch the account and assert the move is recorded correctly
       const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
 
-      // Compute expected hash
-      const expectedHash = hashMove(original_move, salt);
-
       // Assertions
       assert.deepEqual(
         gameAccountData.creatorMoveHashed,
@@ -263,10 +882,12 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       // Find PDA using helper function
       const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
 
+      // Compute the commitment client-side
+      const expectedHash = await hashMove(gameAccountPda, original_move, salt);
+
       // Invoke the select_move instruction as joiner
       await program.rpc.selectMove(
-        original_move,
-        salt,
+        Array.from(expectedHash),
         {
           accounts: {
             gameAccount: gameAccountPda,
@@ -280,9 +901,6 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       // Fetch the account and assert the move is recorded correctly
       const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
 
-      // Compute expected hash
-      const expectedHash = hashMove(original_move, salt);
-
       // Assertions
       assert.deepEqual(
         gameAccountData.joinerMoveHashed,
@@ -312,8 +930,7 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       try {
         // Attempt to invoke the select_move instruction as unauthorized user
         await program.rpc.selectMove(
-          original_move,
-          salt,
+          Array.from(await hashMove(gameAccountPda, original_move, salt)),
           {
             accounts: {
               gameAccount: gameAccountPda,
@@ -328,8 +945,8 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
         // Assert that the error is the expected one
         assert.include(
           err.message,
-          "Unauthorized",
-          "The error message should contain 'Unauthorized'"
+          "NotAPlayer",
+          "The error message should contain 'NotAPlayer'"
         );
       }
     });
@@ -361,12 +978,12 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
 
       // Assertions
       assert.equal(
-        gameAccountData.creatorReady,
+        hasFlag(gameAccountData, CREATOR_READY),
         true,
         "Creator should be marked as ready"
       );
       assert.equal(
-        gameAccountData.joinerReady,
+        hasFlag(gameAccountData, JOINER_READY),
         false,
         "Joiner should not be marked as ready yet"
       );
@@ -398,19 +1015,19 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
 
       // Assertions
       assert.equal(
-        gameAccountData.creatorReady,
+        hasFlag(gameAccountData, CREATOR_READY),
         true,
         "Creator should be marked as ready"
       );
       assert.equal(
-        gameAccountData.joinerReady,
+        hasFlag(gameAccountData, JOINER_READY),
         true,
         "Joiner should be marked as ready"
       );
       assert.deepEqual(
         gameAccountData.status,
-        { ended: {} },
-        "Game status should be Ended after both players are ready"
+        { revealing: {} },
+        "Game status should be Revealing after both players are ready"
       );
     });
 
@@ -443,8 +1060,14 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
         {
           accounts: {
             gameAccount: newGameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(newGameAccountPda),
             creator: newCreator.publicKey,
+            payer: newCreator.publicKey,
+            creatorStats: playerStatsPda(newCreator.publicKey),
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
           },
           signers: [newCreator],
         }
@@ -454,8 +1077,15 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
       await program.rpc.joinGame({
         accounts: {
           gameAccount: newGameAccountPda,
+          creatorStats: playerStatsPda(newCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(newGameAccountPda),
           joiner: newJoiner.publicKey,
+          joinerStats: playerStatsPda(newJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
           systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
         },
         signers: [newJoiner],
       });
@@ -512,41 +1142,5477 @@ describe("rps_game - Create, Join, Select Move, and Ready Up", () => {
         // Assert that the error is the expected one
         assert.include(
           err.message,
-          "Unauthorized",
-          "The error message should contain 'Unauthorized'"
+          "NotAPlayer",
+          "The error message should contain 'NotAPlayer'"
         );
       }
     });
+  });
 
-    it("Cannot ready up after the game has ended", async () => {
+  describe("Reveal Move", () => {
+    it("Rejects a reveal that does not match the commitment", async () => {
       const wager = 100_000_000; // Must match the wager used in createGame
 
       // Find PDA using helper function
       const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
 
-      // Attempt to ready up again as creator after the game has ended
       try {
-        await program.rpc.readyUp({
+        // Creator committed Rock with "creator_salt"; reveal Paper instead
+        await program.rpc.revealMove(1, "creator_salt", {
           accounts: {
             gameAccount: gameAccountPda,
             player: creator.publicKey,
+            creator: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
             systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
           },
           signers: [creator],
         });
-
-        const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
-        console.log("Game Status:", gameAccountData.status);
-
-        assert.fail("The transaction should have failed because the game has already ended");
+        assert.fail("The transaction should have failed because the move does not match");
       } catch (err: any) {
         // Assert that the error is the expected one
         assert.include(
           err.message,
-          "GameAlreadyEnded",
-          "The error message should contain 'GameAlreadyEnded'"
+          "CommitmentMismatch",
+          "The error message should contain 'CommitmentMismatch'"
         );
       }
     });
+
+    it("Creator reveals their move successfully!", async () => {
+      const wager = 100_000_000; // Must match the wager used in createGame
+
+      // Find PDA using helper function
+      const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.revealMove(0, "creator_salt", {
+        accounts: {
+          gameAccount: gameAccountPda,
+          player: creator.publicKey,
+          creator: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          config: configPda,
+          house: provider.wallet.publicKey,
+          jackpotVault: jackpotVaultPda,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+
+      assert.equal(gameAccountData.creatorMove, 0, "Creator's revealed move mismatch");
+      assert.isNull(gameAccountData.joinerMove, "Joiner should not have revealed yet");
+      assert.deepEqual(
+        gameAccountData.status,
+        { revealing: {} },
+        "Game status should remain Revealing until both players reveal"
+      );
+    });
+
+    it("Previews the settlement before the joiner reveals", async () => {
+      const wager = 100_000_000; // Must match the wager used in createGame
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      // The creator's revealed Rock takes precedence over the Scissors passed in
+      const simulated = await program.methods
+        .simulateResult(2, 1)
+        .accounts({ gameAccount: gameAccountPda, config: configPda })
+        .view();
+      assert.deepEqual(simulated.result, { joinerWins: {} }, "Paper should beat the revealed Rock");
+      assert.isFalse(simulated.replay);
+      assert.equal(simulated.creatorPayout.toNumber(), 0, "Creator should get nothing");
+      assert.equal(
+        simulated.joinerPayout.toNumber() + simulated.houseFee.toNumber(),
+        2 * wager,
+        "Payout and fee should add up to the pot"
+      );
+    });
+
+    it("Previews the fee of the tier the wager falls in", async () => {
+      const wager = 100_000_000; // Must match the wager used in createGame
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+
+      try {
+        await program.rpc.setFeeTiers(
+          [
+            { maxWager: new BN(wager), feeBps: 300 },
+            { maxWager: new BN(wager / 10), feeBps: 500 },
+          ],
+          { accounts: updateAccounts }
+        );
+        assert.fail("Unsorted tiers should be rejected");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidFeeTiers");
+      }
+
+      await program.rpc.setFeeTiers(
+        [
+          { maxWager: new BN(wager / 10), feeBps: 500 },
+          { maxWager: new BN(wager), feeBps: 300 },
+        ],
+        { accounts: updateAccounts }
+      );
+      const simulated = await program.methods
+        .simulateResult(2, 1)
+        .accounts({ gameAccount: gameAccountPda, config: configPda })
+        .view();
+      assert.equal(simulated.houseFee.toNumber(), (2 * wager * 300) / 10_000);
+
+      await program.rpc.setFeeTiers([], { accounts: updateAccounts });
+    });
+
+    it("Returns a versioned view of the game", async () => {
+      const wager = 100_000_000; // Must match the wager used in createGame
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      const view = await program.methods
+        .getGame()
+        .accounts({ gameAccount: gameAccountPda })
+        .view();
+      assert.equal(view.viewVersion, 2);
+      assert.isTrue(view.game.equals(gameAccountPda));
+      assert.isTrue(view.opponent.equals(joiner.publicKey));
+      assert.deepEqual(view.status, { revealing: {} });
+      assert.equal(view.creatorMove, 0, "Creator's reveal should show");
+      assert.isTrue(view.creatorReady && view.joinerReady);
+      assert.isNull(view.winner, "No winner before the game ends");
+      assert.isNull(view.result);
+    });
+
+    it("Players can taunt each other in a live game", async () => {
+      const wager = 100_000_000; // Must match the wager used in createGame
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      const signature = await program.rpc.sendMessage("gg, paper incoming", {
+        accounts: { gameAccount: gameAccountPda, sender: joiner.publicKey },
+        signers: [joiner],
+      });
+      await provider.connection.confirmTransaction(signature, "confirmed");
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const parser = new anchor.EventParser(program.programId, program.coder);
+      const [event] = [...parser.parseLogs(tx.meta.logMessages)];
+      assert.equal(event.name, "gameMessage");
+      assert.equal(event.data.message, "gg, paper incoming");
+      assert.isTrue(event.data.sender.equals(joiner.publicKey));
+
+      try {
+        await program.rpc.sendMessage("x".repeat(65), {
+          accounts: { gameAccount: gameAccountPda, sender: creator.publicKey },
+          signers: [creator],
+        });
+        assert.fail("Messages over 64 bytes should be rejected");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidMessage");
+      }
+    });
+
+    it("Players open their match histories", async () => {
+      for (const player of [creator, joiner]) {
+        await program.rpc.initPlayerHistory({
+          accounts: {
+            playerHistory: playerHistoryPda(player.publicKey),
+            player: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      const history = await program.account.playerHistory.fetch(playerHistoryPda(joiner.publicKey));
+      assert.isTrue(history.player.equals(joiner.publicKey));
+      assert.equal(history.total.toNumber(), 0, "A new history should be empty");
+    });
+
+    it("Joiner reveals their move and the game ends", async () => {
+      const wager = 100_000_000; // Must match the wager used in createGame
+
+      // Find PDA using helper function
+      const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
+
+      const initialGameAccountBalance = await provider.connection.getBalance(gameAccountPda);
+      const initialJoinerBalance = await provider.connection.getBalance(joiner.publicKey);
+
+      const signature = await program.rpc.revealMove(1, "joiner_salt", {
+        accounts: {
+          gameAccount: gameAccountPda,
+          player: joiner.publicKey,
+          creator: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          creatorHistory: playerHistoryPda(creator.publicKey),
+          joinerHistory: playerHistoryPda(joiner.publicKey),
+          config: configPda,
+          house: provider.wallet.publicKey,
+          jackpotVault: jackpotVaultPda,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      // Settlement is also recorded as a self-CPI event
+      await provider.connection.confirmTransaction(signature, "confirmed");
+      const settled = (await cpiEvents(signature)).find((event) => event.name === "gameSettled");
+      assert.isDefined(settled, "GameSettled should be emitted through CPI");
+      assert.isTrue(settled.data.game.equals(gameAccountPda));
+      assert.deepEqual(settled.data.result, { joinerWins: {} });
+
+      // The settling reveal is the heaviest instruction; keep it within budget
+      const tx = await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      assert.isAtMost(
+        tx.meta.computeUnitsConsumed,
+        REVEAL_MOVE_CU_BUDGET,
+        "Settling reveal should stay within its compute budget"
+      );
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+
+      assert.equal(gameAccountData.joinerMove, 1, "Joiner's revealed move mismatch");
+      assert.deepEqual(
+        gameAccountData.status,
+        { ended: {} },
+        "Game status should be Ended after both players reveal"
+      );
+      assert.equal(gameAccountData.escrow.toNumber(), 0, "Escrow should be fully paid out");
+
+      // Settlement records the result in both players' stats
+      const creatorStats = await program.account.playerStats.fetch(playerStatsPda(creator.publicKey));
+      const joinerStats = await program.account.playerStats.fetch(playerStatsPda(joiner.publicKey));
+      assert.equal(creatorStats.losses, 1, "Creator should have one loss");
+      assert.equal(joinerStats.wins, 1, "Joiner should have one win");
+      assert.equal(joinerStats.winStreak, 1, "Joiner should be on a one-game streak");
+      assert.equal(joinerStats.rating, 1216, "Joiner should gain 16 rating points");
+      assert.equal(creatorStats.rating, 1184, "Creator should lose 16 rating points");
+      assert.equal(joinerStats.achievements & 1, 1, "Joiner should unlock FirstWin");
+
+      // Both histories get the result from their owner's side
+      const creatorHistory = await program.account.playerHistory.fetch(playerHistoryPda(creator.publicKey));
+      const joinerHistory = await program.account.playerHistory.fetch(playerHistoryPda(joiner.publicKey));
+      assert.equal(joinerHistory.total.toNumber(), 1, "One result should be recorded");
+      assert.isTrue(joinerHistory.entries[0].opponent.equals(creator.publicKey));
+      assert.equal(joinerHistory.entries[0].wager.toNumber(), wager);
+      assert.equal(joinerHistory.entries[0].outcome, 1, "Joiner's entry should be a win");
+      assert.equal(creatorHistory.entries[0].outcome, 2, "Creator's entry should be a loss");
+
+      // Paper beats Rock, so the joiner takes the whole pot
+      const finalGameAccountBalance = await provider.connection.getBalance(gameAccountPda);
+      const finalJoinerBalance = await provider.connection.getBalance(joiner.publicKey);
+
+      assert.equal(
+        finalGameAccountBalance,
+        initialGameAccountBalance - 2 * wager,
+        "Game account balance should decrease by the pot"
+      );
+      assert.equal(
+        finalJoinerBalance,
+        initialJoinerBalance + 2 * wager,
+        "Joiner's balance should increase by the pot"
+      );
+    });
+
+    it("Splits the pot evenly on a tie", async () => {
+      const wager = 50_000_001; // Odd wager to exercise the split
+
+      const tieCreator = Keypair.generate();
+      const tieJoiner = Keypair.generate();
+
+      for (const player of [tieCreator, tieJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+
+      const [gameAccountPda, bump] = await findGameAccountPda(tieCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: tieCreator.publicKey,
+          payer: tieCreator.publicKey,
+          creatorStats: playerStatsPda(tieCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [tieCreator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(tieCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: tieJoiner.publicKey,
+          joinerStats: playerStatsPda(tieJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [tieJoiner],
+      });
+
+      // Both players commit, ready up and reveal Scissors
+      const revealAccounts = {
+        gameAccount: gameAccountPda,
+        creator: tieCreator.publicKey,
+        creatorStats: playerStatsPda(tieCreator.publicKey),
+        joiner: tieJoiner.publicKey,
+        joinerStats: playerStatsPda(tieJoiner.publicKey),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        config: configPda,
+        lobby: lobbyPda(gameAccountPda),
+        house: provider.wallet.publicKey,
+        jackpotVault: jackpotVaultPda,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      for (const player of [tieCreator, tieJoiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 2, "tie_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+
+      const initialGameAccountBalance = await provider.connection.getBalance(gameAccountPda);
+      const initialCreatorBalance = await provider.connection.getBalance(tieCreator.publicKey);
+      const initialJoinerBalance = await provider.connection.getBalance(tieJoiner.publicKey);
+
+      for (const player of [tieCreator, tieJoiner]) {
+        await program.rpc.revealMove(2, "tie_salt", {
+          accounts: { ...revealAccounts, player: player.publicKey },
+          signers: [player],
+        });
+      }
+
+      const finalGameAccountBalance = await provider.connection.getBalance(gameAccountPda);
+      const finalCreatorBalance = await provider.connection.getBalance(tieCreator.publicKey);
+      const finalJoinerBalance = await provider.connection.getBalance(tieJoiner.publicKey);
+
+      // Each player gets their wager back and nothing is left behind
+      assert.equal(finalCreatorBalance, initialCreatorBalance + wager, "Creator share mismatch");
+      assert.equal(finalJoinerBalance, initialJoinerBalance + wager, "Joiner share mismatch");
+      assert.equal(
+        finalGameAccountBalance,
+        initialGameAccountBalance - 2 * wager,
+        "The whole pot should leave the game account"
+      );
+    });
+
+    it("Cannot ready up after the game has ended", async () => {
+      const wager = 100_000_000; // Must match the wager used in createGame
+
+      // Find PDA using helper function
+      const [gameAccountPda, bump] = await findGameAccountPda(creator, wager, program.programId);
+
+      // Attempt to ready up again as creator after the game has ended
+      try {
+        await program.rpc.readyUp({
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: creator.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [creator],
+        });
+
+        const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+        console.log("Game Status:", gameAccountData.status);
+
+        assert.fail("The transaction should have failed because the game has already ended");
+      } catch (err: any) {
+        // Assert that the error is the expected one
+        assert.include(
+          err.message,
+          "GameAlreadyEnded",
+          "The error message should contain 'GameAlreadyEnded'"
+        );
+      }
+    });
+  });
+
+  describe("Refund Stale Game", () => {
+    it("Cannot refund before the reveal deadline", async () => {
+      const wager = 10_000_000;
+
+      const staleCreator = Keypair.generate();
+      const staleJoiner = Keypair.generate();
+
+      for (const player of [staleCreator, staleJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+
+      const [gameAccountPda, bump] = await findGameAccountPda(staleCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: staleCreator.publicKey,
+          payer: staleCreator.publicKey,
+          creatorStats: playerStatsPda(staleCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [staleCreator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(staleCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: staleJoiner.publicKey,
+          joinerStats: playerStatsPda(staleJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [staleJoiner],
+      });
+      for (const player of [staleCreator, staleJoiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 0, "stale_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isAbove(
+        gameAccountData.revealDeadline.toNumber(),
+        0,
+        "Reveal deadline should be set once both players are ready"
+      );
+
+      try {
+        await program.rpc.refundStaleGame({
+          accounts: {
+            gameAccount: gameAccountPda,
+            caller: provider.wallet.publicKey,
+            creator: staleCreator.publicKey,
+            joiner: staleJoiner.publicKey,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+        });
+        assert.fail("The transaction should have failed because the deadline has not passed");
+      } catch (err: any) {
+        assert.include(
+          err.message,
+          "RevealDeadlineNotReached",
+          "The error message should contain 'RevealDeadlineNotReached'"
+        );
+      }
+    });
+  });
+
+  describe("Emergency Refund", () => {
+    it("Admin refunds a flagged open game", async () => {
+      const wager = 20_000_000;
+
+      const stuckCreator = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        stuckCreator.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+
+      const [gameAccountPda, bump] = await findGameAccountPda(stuckCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: stuckCreator.publicKey,
+          payer: stuckCreator.publicKey,
+          creatorStats: playerStatsPda(stuckCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [stuckCreator],
+      });
+
+      // Refunds are rejected until the admin flags the game
+      const refundAccounts = {
+        gameAccount: gameAccountPda,
+        config: configPda,
+        admin: provider.wallet.publicKey,
+        lobby: lobbyPda(gameAccountPda),
+        creator: stuckCreator.publicKey,
+        creatorStats: playerStatsPda(stuckCreator.publicKey),
+        joiner: null,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      try {
+        await program.rpc.emergencyRefund({ accounts: refundAccounts });
+        assert.fail("The transaction should have failed because the game is not flagged");
+      } catch (err: any) {
+        assert.include(
+          err.message,
+          "GameNotFlagged",
+          "The error message should contain 'GameNotFlagged'"
+        );
+      }
+
+      await program.rpc.flagGame(true, {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          admin: provider.wallet.publicKey,
+        },
+      });
+
+      const gameAccountBalance = await provider.connection.getBalance(gameAccountPda);
+      const initialCreatorBalance = await provider.connection.getBalance(stuckCreator.publicKey);
+
+      await program.rpc.emergencyRefund({ accounts: refundAccounts });
+
+      // The wager and the rent both return to the creator and the game is closed
+      const finalCreatorBalance = await provider.connection.getBalance(stuckCreator.publicKey);
+      assert.equal(
+        finalCreatorBalance,
+        initialCreatorBalance + gameAccountBalance,
+        "Creator should receive the wager and the rent"
+      );
+      assert.isNull(
+        await provider.connection.getAccountInfo(gameAccountPda),
+        "Game account should be closed"
+      );
+    });
+  });
+
+  describe("Badges", () => {
+    it("Cannot mint a badge before reaching its milestone", async () => {
+      const [badgeAuthority] = PublicKey.findProgramAddressSync(
+        [Buffer.from("badge_authority")],
+        program.programId
+      );
+
+      try {
+        // No badge tree is configured, so the default pubkey stands in for it
+        await program.rpc.mintBadge({ hundredGames: {} }, {
+          accounts: {
+            player: joiner.publicKey,
+            playerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            badgeAuthority,
+            treeConfig: Keypair.generate().publicKey,
+            merkleTree: PublicKey.default,
+            logWrapper: new PublicKey("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV"),
+            compressionProgram: new PublicKey("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK"),
+            bubblegumProgram: new PublicKey("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY"),
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [joiner],
+        });
+        assert.fail("The transaction should have failed because the badge is not earned");
+      } catch (err: any) {
+        assert.include(
+          err.message,
+          "BadgeNotEarned",
+          "The error message should contain 'BadgeNotEarned'"
+        );
+      }
+    });
+  });
+
+  describe("Achievements", () => {
+    it("Claims an unlocked achievement once", async () => {
+      const [achievementVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("achievement_vault")],
+        program.programId
+      );
+      const claimAccounts = {
+        player: joiner.publicKey,
+        playerStats: playerStatsPda(joiner.publicKey),
+        config: configPda,
+        achievementVault,
+        systemProgram: SystemProgram.programId,
+      };
+
+      // No reward is attached, so claiming only marks the achievement
+      await program.rpc.claimAchievementReward({ firstWin: {} }, {
+        accounts: claimAccounts,
+        signers: [joiner],
+      });
+
+      const joinerStats = await program.account.playerStats.fetch(playerStatsPda(joiner.publicKey));
+      assert.equal(joinerStats.achievementsClaimed & 1, 1, "FirstWin should be marked as claimed");
+
+      try {
+        await program.rpc.claimAchievementReward({ firstWin: {} }, {
+          accounts: claimAccounts,
+          signers: [joiner],
+        });
+        assert.fail("The transaction should have failed because the reward was already claimed");
+      } catch (err: any) {
+        assert.include(
+          err.message,
+          "AchievementAlreadyClaimed",
+          "The error message should contain 'AchievementAlreadyClaimed'"
+        );
+      }
+    });
+  });
+
+  describe("Seasons", () => {
+    it("Admin rolls the season", async () => {
+      const configBefore = await program.account.config.fetch(configPda);
+
+      await program.rpc.rollSeason({
+        accounts: { config: configPda, admin: provider.wallet.publicKey },
+      });
+
+      const configAfter = await program.account.config.fetch(configPda);
+      assert.equal(configAfter.seasonId, configBefore.seasonId + 1, "Season should advance by one");
+
+      // Stats keep last season's standings until the player's next game
+      const joinerStats = await program.account.playerStats.fetch(playerStatsPda(joiner.publicKey));
+      assert.equal(joinerStats.season.seasonId, configBefore.seasonId, "Stats should not roll eagerly");
+      assert.equal(joinerStats.wins, 1, "Lifetime totals are untouched");
+    });
+
+    it("Decays idle ratings back toward the mean", async () => {
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const decayAccounts = { playerStats: playerStatsPda(joiner.publicKey), config: configPda };
+
+      try {
+        await program.rpc.applyDecay({ accounts: decayAccounts });
+        assert.fail("Decay is off by default");
+      } catch (err: any) {
+        assert.include(err.message, "NoRatingDecay");
+      }
+      try {
+        await program.rpc.setRatingDecay(new BN(-1), 100, { accounts: updateAccounts });
+        assert.fail("A negative window should be rejected");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidRatingDecay");
+      }
+
+      // At full strength one idle second takes back everything above the mean
+      const statsBefore = await program.account.playerStats.fetch(decayAccounts.playerStats);
+      await program.rpc.setRatingDecay(new BN(1), 10_000, { accounts: updateAccounts });
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+      await program.rpc.applyDecay({ accounts: decayAccounts });
+      await program.rpc.setRatingDecay(new BN(0), 0, { accounts: updateAccounts });
+
+      const statsAfter = await program.account.playerStats.fetch(decayAccounts.playerStats);
+      assert.equal(statsAfter.rating, Math.min(statsBefore.rating, 1200), "Only gains over the mean decay");
+      assert.equal(statsAfter.wins, statsBefore.wins, "Only the rating decays");
+    });
+  });
+
+  describe("Leaderboard", () => {
+    const leaderboardPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("leaderboard")],
+      program.programId
+    )[0];
+    const shardPda = (index: number): PublicKey =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("leaderboard"), Buffer.from([index])],
+        program.programId
+      )[0];
+
+    it("Lists players in the shard for their rating band", async () => {
+      const adminAccounts = {
+        config: configPda,
+        admin: provider.wallet.publicKey,
+        payer: provider.wallet.publicKey,
+        systemProgram: SystemProgram.programId,
+      };
+      await program.rpc.initializeLeaderboard(2, 1200, 100, {
+        accounts: { leaderboard: leaderboardPda, ...adminAccounts },
+      });
+      for (const index of [0, 1]) {
+        await program.rpc.initializeLeaderboardShard(index, {
+          accounts: { shard: shardPda(index), leaderboard: leaderboardPda, ...adminAccounts },
+        });
+      }
+
+      const stats = await program.account.playerStats.fetch(playerStatsPda(creator.publicKey));
+      const index = stats.rating >= 1300 ? 1 : 0;
+      try {
+        await program.rpc.updateStanding({
+          accounts: {
+            leaderboard: leaderboardPda,
+            shard: shardPda(1 - index),
+            previousShard: null,
+            playerStats: playerStatsPda(creator.publicKey),
+          },
+        });
+        assert.fail("The player belongs in the other shard");
+      } catch (err: any) {
+        assert.include(err.message, "WrongLeaderboardShard");
+      }
+
+      await program.rpc.updateStanding({
+        accounts: {
+          leaderboard: leaderboardPda,
+          shard: shardPda(index),
+          previousShard: null,
+          playerStats: playerStatsPda(creator.publicKey),
+        },
+      });
+      const shard = await program.account.leaderboardShard.fetch(shardPda(index));
+      assert.equal(shard.entries.length, 1);
+      assert.isTrue(shard.entries[0].player.equals(creator.publicKey));
+      assert.equal(shard.entries[0].rating, stats.rating);
+    });
+
+    it("Snapshots last week's leaders and pays them from the treasury", async () => {
+      const [treasuryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury")],
+        program.programId
+      );
+      const prizePool = 1_000_000;
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: provider.wallet.publicKey,
+            toPubkey: treasuryPda,
+            lamports: prizePool,
+          })
+        )
+      );
+      await program.rpc.setWeeklyPrize(new BN(prizePool), {
+        accounts: { config: configPda, admin: provider.wallet.publicKey },
+      });
+
+      const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+      const week = Math.floor(now / (7 * 24 * 60 * 60)) - 1;
+      const snapshotPda = (week: number): PublicKey =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("snapshot"), new BN(week).toArrayLike(Buffer, "le", 8)],
+          program.programId
+        )[0];
+      const snapshot = (week: number) =>
+        program.rpc.snapshotLeaderboard(new BN(week), {
+          accounts: {
+            snapshot: snapshotPda(week),
+            leaderboard: leaderboardPda,
+            treasury: treasuryPda,
+            config: configPda,
+            payer: provider.wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          remainingAccounts: [shardPda(1), shardPda(0)].map((pubkey) => ({
+            pubkey,
+            isWritable: false,
+            isSigner: false,
+          })),
+        });
+      try {
+        await snapshot(week + 1);
+        assert.fail("The current week hasn't ended");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidSnapshotWeek");
+      }
+      await snapshot(week);
+      await program.rpc.setWeeklyPrize(new BN(0), {
+        accounts: { config: configPda, admin: provider.wallet.publicKey },
+      });
+
+      const snapshotData = await program.account.leaderboardSnapshot.fetch(snapshotPda(week));
+      assert.equal(snapshotData.winners.length, 1, "Only one player is listed");
+      assert.isTrue(snapshotData.winners[0].player.equals(creator.publicKey));
+      assert.equal(snapshotData.prizes[0].toNumber(), prizePool, "An unfilled podium goes to first");
+
+      const claim = () =>
+        program.rpc.claimWeeklyPrize({
+          accounts: { snapshot: snapshotPda(week), player: creator.publicKey },
+          signers: [creator],
+        });
+      const balanceBefore = await provider.connection.getBalance(creator.publicKey);
+      await claim();
+      assert.equal(
+        await provider.connection.getBalance(creator.publicKey),
+        balanceBefore + prizePool
+      );
+      try {
+        await claim();
+        assert.fail("A prize can only be claimed once");
+      } catch (err: any) {
+        assert.include(err.message, "PrizeAlreadyClaimed");
+      }
+    });
+  });
+
+  describe("Matchmaking Queue", () => {
+    it("Matches two queued players into a committed game", async () => {
+      const band = 0;
+      const wager = 2_000_000;
+
+      const [queuePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("queue"), Buffer.from([band])],
+        program.programId
+      );
+
+      await program.rpc.initializeQueue(band, new BN(wager), {
+        accounts: {
+          queue: queuePda,
+          config: configPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+      });
+
+      const playerOne = Keypair.generate();
+      const playerTwo = Keypair.generate();
+      for (const player of [playerOne, playerTwo]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+
+        await program.rpc.joinQueue(band, {
+          accounts: {
+            queue: queuePda,
+            config: configPda,
+            playerStats: playerStatsPda(player.publicKey),
+            player: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      const queueData = await program.account.matchQueue.fetch(queuePda);
+      assert.equal(queueData.waiting.length, 2, "Both players should be waiting");
+
+      const [gameAccountPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("queue_game"), queuePda.toBuffer(), queueData.matches.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+
+      await program.rpc.matchPlayers(band, {
+        accounts: {
+          queue: queuePda,
+          gameAccount: gameAccountPda,
+          config: configPda,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.creator.equals(playerOne.publicKey), "First in line creates");
+      assert.isTrue(gameAccountData.opponent.equals(playerTwo.publicKey), "Second in line joins");
+      assert.deepEqual(gameAccountData.status, { committed: {} }, "Game should start Committed");
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * wager, "Escrow should hold both wagers");
+
+      const queueAfter = await program.account.matchQueue.fetch(queuePda);
+      assert.equal(queueAfter.waiting.length, 0, "Queue should be empty after matching");
+    });
+  });
+
+  describe("Session Keys", () => {
+    const wager = 3_000_000;
+    const sessionCreator = Keypair.generate();
+    const sessionJoiner = Keypair.generate();
+
+    it("A session key reveals on the player's behalf", async () => {
+      const sessionKey = Keypair.generate(); // Holds no SOL; the player pays fees
+
+      for (const player of [sessionCreator, sessionJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+
+      const [gameAccountPda] = await findGameAccountPda(sessionCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: sessionCreator.publicKey,
+          payer: sessionCreator.publicKey,
+          creatorStats: playerStatsPda(sessionCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [sessionCreator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(sessionCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: sessionJoiner.publicKey,
+          joinerStats: playerStatsPda(sessionJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [sessionJoiner],
+      });
+
+      // Expiries beyond the maximum session length are rejected
+      const slot = await provider.connection.getSlot();
+      try {
+        await program.rpc.setSessionKey(sessionKey.publicKey, new BN(slot + 1_000_000), 1, {
+          accounts: {
+            playerStats: playerStatsPda(sessionCreator.publicKey),
+            player: sessionCreator.publicKey,
+          },
+          signers: [sessionCreator],
+        });
+        assert.fail("Session expiry should be capped");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidSessionExpiry");
+      }
+
+      await program.rpc.setSessionKey(sessionKey.publicKey, new BN(slot + 1_000), 1, {
+        accounts: {
+          playerStats: playerStatsPda(sessionCreator.publicKey),
+          player: sessionCreator.publicKey,
+        },
+        signers: [sessionCreator],
+      });
+
+      for (const player of [sessionCreator, sessionJoiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 0, "session_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+
+      await program.rpc.revealMove(0, "session_salt", {
+        accounts: {
+          gameAccount: gameAccountPda,
+          player: sessionKey.publicKey,
+          creator: sessionCreator.publicKey,
+          creatorStats: playerStatsPda(sessionCreator.publicKey),
+          joiner: sessionJoiner.publicKey,
+          joinerStats: playerStatsPda(sessionJoiner.publicKey),
+          config: configPda,
+          house: provider.wallet.publicKey,
+          jackpotVault: jackpotVaultPda,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [sessionKey],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.creatorMove, 0, "Session key should reveal the creator's move");
+      assert.isNull(gameAccountData.joinerMove, "Joiner has not revealed yet");
+    });
+
+    it("A relayer submits a reveal signed by the player", async () => {
+      const [gameAccountPda] = await findGameAccountPda(sessionCreator, wager, program.programId);
+
+      // The joiner signs game key || move || salt off-chain
+      const message = Buffer.concat([
+        gameAccountPda.toBuffer(),
+        Buffer.from([0]),
+        Buffer.from("session_salt"),
+      ]);
+      const verifyIx = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: sessionJoiner.secretKey,
+        message,
+      });
+
+      const relayAccounts = {
+        gameAccount: gameAccountPda,
+        player: provider.wallet.publicKey, // The relayer
+        creator: sessionCreator.publicKey,
+        creatorStats: playerStatsPda(sessionCreator.publicKey),
+        joiner: sessionJoiner.publicKey,
+        joinerStats: playerStatsPda(sessionJoiner.publicKey),
+        config: configPda,
+        house: provider.wallet.publicKey,
+        jackpotVault: jackpotVaultPda,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+
+      // A signature over a different move is rejected
+      try {
+        await program.rpc.relayRevealMove(sessionJoiner.publicKey, 1, "session_salt", {
+          accounts: relayAccounts,
+          instructions: [verifyIx],
+        });
+        assert.fail("Reveal should not match the signed message");
+      } catch (err: any) {
+        assert.include(err.message, "SignatureMismatch");
+      }
+
+      await program.rpc.relayRevealMove(sessionJoiner.publicKey, 0, "session_salt", {
+        accounts: relayAccounts,
+        instructions: [verifyIx],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.joinerMove, 0, "Relayed reveal should record the joiner's move");
+      assert.deepEqual(gameAccountData.status, { ended: {} }, "Both reveals settle the game");
+    });
+  });
+
+  describe("Join Permits", () => {
+    it("Invite-only games require a permit signed by the creator", async () => {
+      const wager = 4_000_000;
+      const invited = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        invited.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setInviteOnly(true, {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+
+      const joinAccounts = {
+        gameAccount: gameAccountPda,
+        creatorStats: playerStatsPda(creator.publicKey),
+        config: configPda,
+        lobby: lobbyPda(gameAccountPda),
+        joiner: invited.publicKey,
+        joinerStats: playerStatsPda(invited.publicKey),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+
+      try {
+        await program.rpc.joinGame({ accounts: joinAccounts, signers: [invited] });
+        assert.fail("Joining without a permit should fail");
+      } catch (err: any) {
+        assert.include(err.message, "InviteRequired");
+      }
+
+      // The creator signs game key || joiner || expiry off-chain
+      const expiresAt = new BN(Math.floor(Date.now() / 1000) + 3600);
+      const verifyIx = Ed25519Program.createInstructionWithPrivateKey({
+        privateKey: creator.secretKey,
+        message: Buffer.concat([
+          gameAccountPda.toBuffer(),
+          invited.publicKey.toBuffer(),
+          expiresAt.toArrayLike(Buffer, "le", 8),
+        ]),
+      });
+
+      await program.rpc.joinGameWithPermit(expiresAt, {
+        accounts: joinAccounts,
+        signers: [invited],
+        instructions: [verifyIx],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.opponent.equals(invited.publicKey), "Permit holder should join");
+      assert.deepEqual(gameAccountData.status, { committed: {} }, "Game should be Committed");
+    });
+  });
+
+  describe("Create And Join", () => {
+    it("Creates a committed game signed by both players", async () => {
+      const wager = 6_000_000;
+      const nonce = new BN(42);
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      // Commitments are bound to the nonce the players agreed on
+      const commit = (original_move: number, salt: string): number[] => {
+        const hash = crypto.createHash("sha256");
+        hash.update(gameAccountPda.toBuffer());
+        hash.update(nonce.toArrayLike(Buffer, "le", 8));
+        hash.update(Buffer.from([original_move]));
+        hash.update(Buffer.from(salt));
+        return Array.from(hash.digest());
+      };
+
+      await program.rpc.createAndJoin(
+        new BN(wager),
+        nonce,
+        commit(0, "instant_creator"),
+        commit(1, "instant_joiner"),
+        {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joinerStats: playerStatsPda(joiner.publicKey),
+            creator: creator.publicKey,
+            joiner: joiner.publicKey,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [creator, joiner],
+        }
+      );
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { committed: {} }, "Game should start Committed");
+      assert.isTrue(gameAccountData.opponent.equals(joiner.publicKey), "Joiner should be seated");
+      assert.equal(gameAccountData.nonce.toNumber(), 42, "Nonce should be the agreed one");
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * wager, "Escrow should hold both wagers");
+    });
+
+    it("Lets a sponsor front a player's wager", async () => {
+      const wager = 36_700_000;
+      const [player, sponsor] = [Keypair.generate(), Keypair.generate()];
+      for (const wallet of [player, sponsor]) {
+        await provider.connection.confirmTransaction(
+          await provider.connection.requestAirdrop(wallet.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+          "confirmed"
+        );
+      }
+      const [gameAccountPda] = await findGameAccountPda(player, wager, program.programId);
+
+      const sponsorBefore = await provider.connection.getBalance(sponsor.publicKey);
+      await program.rpc.createAndJoin(
+        new BN(wager),
+        new BN(7),
+        Array(32).fill(1),
+        Array(32).fill(2),
+        {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            creatorStats: playerStatsPda(player.publicKey),
+            joinerStats: playerStatsPda(joiner.publicKey),
+            creator: player.publicKey,
+            joiner: joiner.publicKey,
+            creatorSponsor: sponsor.publicKey,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player, joiner, sponsor],
+        }
+      );
+      const sponsorAfter = await provider.connection.getBalance(sponsor.publicKey);
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.creatorSponsor.equals(sponsor.publicKey));
+      assert.isNull(gameAccountData.joinerSponsor, "The joiner funded their own wager");
+      assert.equal(sponsorBefore - sponsorAfter, wager, "The sponsor fronts the creator's wager");
+    });
+  });
+
+  describe("Join And Reveal", () => {
+    it("Joiner joins and reveals once the creator is locked in", async () => {
+      const wager = 7_000_000;
+      const fastJoiner = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        fastJoiner.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+
+      const joinAccounts = {
+        gameAccount: gameAccountPda,
+        creatorStats: playerStatsPda(creator.publicKey),
+        config: configPda,
+        lobby: lobbyPda(gameAccountPda),
+        joiner: fastJoiner.publicKey,
+        joinerStats: playerStatsPda(fastJoiner.publicKey),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+
+      // The creator's commitment must be locked first
+      try {
+        await program.rpc.joinAndReveal(1, "fast_salt", {
+          accounts: joinAccounts,
+          signers: [fastJoiner],
+        });
+        assert.fail("Fast path should require a ready creator");
+      } catch (err: any) {
+        assert.include(err.message, "CreatorNotReady");
+      }
+
+      const hashedMove = await hashMove(gameAccountPda, 0, "creator_salt");
+      await program.rpc.selectMove(Array.from(hashedMove), {
+        accounts: { gameAccount: gameAccountPda, player: creator.publicKey },
+        signers: [creator],
+      });
+      await program.rpc.readyUp({
+        accounts: { gameAccount: gameAccountPda, player: creator.publicKey },
+        signers: [creator],
+      });
+
+      await program.rpc.joinAndReveal(1, "fast_salt", {
+        accounts: joinAccounts,
+        signers: [fastJoiner],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { revealing: {} }, "Only the creator must reveal");
+      assert.equal(gameAccountData.joinerMove, 1, "Joiner's move should be revealed");
+      assert.isTrue(hasFlag(gameAccountData, JOINER_READY), "Joiner should be ready");
+    });
+  });
+
+  describe("Elimination Game", () => {
+    it("Three players play until one takes the pot", async () => {
+      const wager = 8_000_000;
+      const players = [creator, Keypair.generate(), Keypair.generate()];
+      for (const player of players.slice(1)) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+
+      const [multiGamePda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("multi_game"),
+          creator.publicKey.toBuffer(),
+          new BN(wager).toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      await program.rpc.createMultiGame(new BN(wager), 3, {
+        accounts: {
+          multiGame: multiGamePda,
+          config: configPda,
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [creator],
+      });
+      for (const player of players.slice(1)) {
+        await program.rpc.joinMultiGame({
+          accounts: {
+            multiGame: multiGamePda,
+            config: configPda,
+            player: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      // Rock, Scissors, Scissors: the creator knocks both others out
+      const moves = [0, 2, 2];
+      const commit = (player: Keypair, original_move: number): number[] => {
+        const hash = crypto.createHash("sha256");
+        hash.update(multiGamePda.toBuffer());
+        hash.update(player.publicKey.toBuffer());
+        hash.update(Buffer.from([0, 0, 0, 0])); // Round 0
+        hash.update(Buffer.from([original_move]));
+        hash.update(Buffer.from("multi_salt"));
+        return Array.from(hash.digest());
+      };
+      for (const [i, player] of players.entries()) {
+        await program.rpc.commitMultiMove(commit(player, moves[i]), {
+          accounts: { multiGame: multiGamePda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const [i, player] of players.entries()) {
+        await program.rpc.revealMultiMove(moves[i], "multi_salt", {
+          accounts: { multiGame: multiGamePda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+
+      const multiGameData = await program.account.multiGame.fetch(multiGamePda);
+      assert.equal(multiGameData.status, 3, "Game should be over"); // GameStatus::Ended
+      assert.equal(multiGameData.seatedCount, 3);
+      assert.deepEqual(
+        multiGameData.players.slice(0, 3).map((seat) => seat.eliminated),
+        [0, 1, 1],
+        "Both Scissors should be eliminated"
+      );
+      assert.isTrue(multiGameData.winner.equals(creator.publicKey), "Creator should win");
+
+      const initialCreatorBalance = await provider.connection.getBalance(creator.publicKey);
+      await program.rpc.claimMultiPot({
+        accounts: {
+          multiGame: multiGamePda,
+          config: configPda,
+          house: provider.wallet.publicKey,
+          winner: creator.publicKey,
+        },
+        signers: [creator],
+      });
+      const finalCreatorBalance = await provider.connection.getBalance(creator.publicKey);
+      assert.isAbove(
+        finalCreatorBalance,
+        initialCreatorBalance + 2 * wager,
+        "Winner should collect the pot"
+      );
+    });
+  });
+
+  describe("League", () => {
+    it("Reports a scheduled fixture into the standings", async () => {
+      const leagueId = new BN(1);
+      const entryFee = 5_000_000;
+      const now = Math.floor(Date.now() / 1000);
+
+      const [leaguePda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("league"),
+          provider.wallet.publicKey.toBuffer(),
+          leagueId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      await program.rpc.createLeague(
+        leagueId,
+        new BN(entryFee),
+        new BN(now - 60),
+        new BN(now + 4),
+        new BN(now + 4),
+        new BN(now + 7 * 24 * 60 * 60),
+        [creator.publicKey, joiner.publicKey],
+        {
+          accounts: {
+            league: leaguePda,
+            organizer: provider.wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+        }
+      );
+      for (const player of [creator, joiner]) {
+        await program.rpc.enterLeague({
+          accounts: {
+            league: leaguePda,
+            player: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [player],
+        });
+      }
+      // Fixtures can only be scheduled once the league has started
+      await new Promise((resolve) => setTimeout(resolve, 5000));
+      try {
+        await program.rpc.enterLeague({
+          accounts: {
+            league: leaguePda,
+            player: creator.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [creator],
+        });
+        assert.fail("Registration closes when the league starts");
+      } catch (err: any) {
+        assert.include(err.message, "RegistrationClosed");
+      }
+
+      // Seeding puts the better-rated player first
+      const ratings = await Promise.all(
+        [creator, joiner].map(async (player) =>
+          (await program.account.playerStats.fetch(playerStatsPda(player.publicKey))).rating
+        )
+      );
+      await program.rpc.seedLeague({
+        accounts: { league: leaguePda },
+        remainingAccounts: [creator, joiner].map((player) => ({
+          pubkey: playerStatsPda(player.publicKey),
+          isWritable: false,
+          isSigner: false,
+        })),
+      });
+      const topSeed = ratings[1] > ratings[0] ? joiner : creator;
+      const seededLeague = await program.account.league.fetch(leaguePda);
+      assert.isTrue(seededLeague.standings[0].player.equals(topSeed.publicKey));
+
+      const wager = 9_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.scheduleLeagueGame(joiner.publicKey, {
+        accounts: {
+          league: leaguePda,
+          gameAccount: gameAccountPda,
+          creator: creator.publicKey,
+        },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      // Creator plays Paper, joiner plays Rock
+      const moves: [Keypair, number][] = [
+        [creator, 1],
+        [joiner, 0],
+      ];
+      for (const [player, original_move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, original_move, "league_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const [player, original_move] of moves) {
+        await program.rpc.revealMove(original_move, "league_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      await program.rpc.reportLeagueResult({
+        accounts: { league: leaguePda, gameAccount: gameAccountPda },
+      });
+
+      const leagueData = await program.account.league.fetch(leaguePda);
+      assert.equal(leagueData.prizePool.toNumber(), 2 * entryFee, "Both entry fees are pooled");
+      const standing = (player: Keypair) =>
+        leagueData.standings.find((s: any) => s.player.equals(player.publicKey));
+      assert.equal(standing(creator).points, 3, "Creator should earn a win");
+      assert.equal(standing(joiner).losses, 1, "Joiner should record a loss");
+      assert.equal(leagueData.rosterLen, 2);
+
+      // A fixture only counts once
+      try {
+        await program.rpc.reportLeagueResult({
+          accounts: { league: leaguePda, gameAccount: gameAccountPda },
+        });
+        assert.fail("Fixture should not be reported twice");
+      } catch (err: any) {
+        assert.include(err.message, "FixtureAlreadyPlayed");
+      }
+    });
+
+    it("Pays every prize and closes the league in one call", async () => {
+      const leagueId = new BN(2);
+      const entryFee = 1_000_000;
+      const now = Math.floor(Date.now() / 1000);
+      const [leaguePda] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("league"),
+          provider.wallet.publicKey.toBuffer(),
+          leagueId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      await program.rpc.createLeague(
+        leagueId,
+        new BN(entryFee),
+        new BN(now - 60),
+        new BN(now + 4),
+        new BN(now + 4),
+        new BN(now + 5),
+        [creator.publicKey, joiner.publicKey],
+        {
+          accounts: {
+            league: leaguePda,
+            organizer: provider.wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+        }
+      );
+      for (const player of [creator, joiner]) {
+        await program.rpc.enterLeague({
+          accounts: {
+            league: leaguePda,
+            player: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [player],
+        });
+      }
+      await new Promise((resolve) => setTimeout(resolve, 7000));
+      await program.rpc.finalizeLeague({ accounts: { league: leaguePda } });
+
+      const creatorBefore = await provider.connection.getBalance(creator.publicKey);
+      const joinerBefore = await provider.connection.getBalance(joiner.publicKey);
+      await program.rpc.distributeLeaguePrizes({
+        accounts: { league: leaguePda, organizer: provider.wallet.publicKey },
+        remainingAccounts: [creator, joiner].map((player) => ({
+          pubkey: player.publicKey,
+          isWritable: true,
+          isSigner: false,
+        })),
+      });
+
+      // No games were played, so the roster order breaks the tie
+      const pool = 2 * entryFee;
+      assert.equal(
+        (await provider.connection.getBalance(creator.publicKey)) - creatorBefore,
+        pool * 0.7
+      );
+      assert.equal(
+        (await provider.connection.getBalance(joiner.publicKey)) - joinerBefore,
+        pool * 0.3
+      );
+      assert.isNull(await provider.connection.getAccountInfo(leaguePda), "League should be closed");
+    });
+  });
+
+  describe("Rematch", () => {
+    it("Loser offers double-or-nothing for the held payout and the winner accepts", async () => {
+      const wager = 36_600_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const rematchAccounts = (player: Keypair) => ({
+        gameAccount: gameAccountPda,
+        config: configPda,
+        player: player.publicKey,
+        systemProgram: SystemProgram.programId,
+      });
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setRollover(true, {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      // The creator's Paper beats the joiner's Rock
+      const moves: [Keypair, number][] = [
+        [creator, 1],
+        [joiner, 0],
+      ];
+      for (const [player, move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, move, "rollover_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const [player, move] of moves) {
+        await program.rpc.revealMove(move, "rollover_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.result, { creatorWins: {} });
+      const held = gameAccountData.escrow.toNumber();
+      assert.isAbove(held, wager, "The winner's payout stays in the game");
+
+      try {
+        await program.rpc.proposeRematch({
+          accounts: rematchAccounts(creator),
+          signers: [creator],
+        });
+        assert.fail("Only the loser can offer a rematch");
+      } catch (err: any) {
+        assert.include(err.message, "NotRematchLoser");
+      }
+
+      const joinerBefore = await provider.connection.getBalance(joiner.publicKey);
+      await program.rpc.proposeRematch({
+        accounts: rematchAccounts(joiner),
+        signers: [joiner],
+      });
+      assert.equal(
+        joinerBefore - (await provider.connection.getBalance(joiner.publicKey)),
+        held,
+        "The loser matches the held payout"
+      );
+      const creatorBefore = await provider.connection.getBalance(creator.publicKey);
+      await program.rpc.acceptRematch({
+        accounts: rematchAccounts(creator),
+        signers: [creator],
+      });
+      assert.equal(
+        await provider.connection.getBalance(creator.publicKey),
+        creatorBefore,
+        "The winner stakes the held payout without topping up"
+      );
+
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { committed: {} }, "Rematch starts Committed");
+      assert.equal(gameAccountData.wager.toNumber(), held, "The held payout is the new stake");
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * held, "Both stakes are escrowed");
+      assert.isNull(gameAccountData.creatorMove, "Moves should be cleared");
+      assert.isNull(gameAccountData.winner, "The settled result should be cleared");
+      assert.isNull(gameAccountData.result);
+      assert.equal(gameAccountData.endedAt.toNumber(), 0);
+    });
+  });
+
+  describe("NFT Wagers", () => {
+    it("Escrows a staked NFT and releases it to the winner", async () => {
+      const wager = 11_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const [nftAuthorityPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nft_authority")],
+        program.programId
+      );
+
+      // A supply-1, zero-decimal mint held by the creator
+      const mint = await createMint(provider.connection, creator, creator.publicKey, null, 0);
+      const creatorToken = await createAccount(provider.connection, creator, mint, creator.publicKey);
+      const joinerToken = await createAccount(provider.connection, joiner, mint, joiner.publicKey);
+      await mintTo(provider.connection, creator, mint, creatorToken, creator, 1);
+
+      const [escrowTokenPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nft_escrow"), gameAccountPda.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.stakeNft({
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          house: provider.wallet.publicKey,
+          mint,
+          escrowToken: escrowTokenPda,
+          playerToken: creatorToken,
+          nftAuthority: nftAuthorityPda,
+          player: creator.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [creator],
+      });
+      assert.equal(Number((await getAccount(provider.connection, escrowTokenPda)).amount), 1);
+
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      // Joiner plays Paper, creator plays Rock
+      const moves: [Keypair, number][] = [
+        [creator, 0],
+        [joiner, 1],
+      ];
+      for (const [player, original_move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, original_move, "nft_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const [player, original_move] of moves) {
+        await program.rpc.revealMove(original_move, "nft_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      await program.rpc.releaseNft({
+        accounts: {
+          gameAccount: gameAccountPda,
+          mint,
+          escrowToken: escrowTokenPda,
+          recipientToken: joinerToken,
+          nftAuthority: nftAuthorityPda,
+          recipient: joiner.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [joiner],
+      });
+
+      assert.equal(Number((await getAccount(provider.connection, joinerToken)).amount), 1, "Winner gets the NFT");
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isNull(gameAccountData.creatorNft, "Game should no longer hold the NFT");
+    });
+  });
+
+  describe("Multi-Asset Pots", () => {
+    it("Escrows a token asset and refunds it from an open game", async () => {
+      const wager = 12_000_000;
+      const amount = 100;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const [tokenAuthorityPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("nft_authority")],
+        program.programId
+      );
+
+      const mint = await createMint(provider.connection, creator, creator.publicKey, null, 6);
+      const creatorToken = await createAccount(provider.connection, creator, mint, creator.publicKey);
+      // Never opened: settlement creates it
+      const houseToken = getAssociatedTokenAddressSync(mint, provider.wallet.publicKey);
+      await mintTo(provider.connection, creator, mint, creatorToken, creator, amount);
+
+      const [escrowTokenPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pot_escrow"), gameAccountPda.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.addPotAsset(new BN(amount), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          mint,
+          escrowToken: escrowTokenPda,
+          creatorToken,
+          tokenAuthority: tokenAuthorityPda,
+          creator: creator.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [creator],
+      });
+      assert.equal(Number((await getAccount(provider.connection, escrowTokenPda)).amount), amount);
+
+      const cancelAccounts = {
+        gameAccount: gameAccountPda,
+        creatorStats: playerStatsPda(creator.publicKey),
+        lobby: lobbyPda(gameAccountPda),
+        creator: creator.publicKey,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      try {
+        await program.rpc.cancelGame({ accounts: cancelAccounts, signers: [creator] });
+        assert.fail("Cancel should wait for the pot asset to be released");
+      } catch (err: any) {
+        assert.include(err.message, "TokensStillEscrowed");
+      }
+
+      // Anyone can refund the asset while the game is open
+      await program.rpc.settlePotAsset(0, {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          escrowToken: escrowTokenPda,
+          mint,
+          creatorToken,
+          joinerToken: creatorToken,
+          houseToken,
+          creator: creator.publicKey,
+          joiner: creator.publicKey,
+          house: provider.wallet.publicKey,
+          tokenAuthority: tokenAuthorityPda,
+          payer: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        },
+      });
+      assert.equal(Number((await getAccount(provider.connection, creatorToken)).amount), amount);
+      assert.equal(
+        Number((await getAccount(provider.connection, houseToken)).amount),
+        0,
+        "Missing house account should be created at settlement"
+      );
+
+      await program.rpc.cancelGame({ accounts: cancelAccounts, signers: [creator] });
+    });
+  });
+
+  describe("House Bankroll", () => {
+    const [bankrollPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bankroll")],
+      program.programId
+    );
+    const lpPositionPda = (owner: PublicKey): PublicKey =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("lp_position"), owner.toBuffer()],
+        program.programId
+      )[0];
+
+    it("Issues shares for deposits and redeems them", async () => {
+      await program.rpc.initializeBankroll({
+        accounts: {
+          bankroll: bankrollPda,
+          config: configPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+      });
+
+      const deposit = 200_000_000;
+      await program.rpc.depositBankroll(new BN(deposit), {
+        accounts: {
+          bankroll: bankrollPda,
+          lpPosition: lpPositionPda(house.publicKey),
+          depositor: house.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [house],
+      });
+
+      const position = await program.account.lpPosition.fetch(lpPositionPda(house.publicKey));
+      assert.equal(position.shares.toNumber(), deposit, "First deposit mints shares 1:1");
+
+      const balanceBefore = await provider.connection.getBalance(house.publicKey);
+      await program.rpc.withdrawBankroll(new BN(deposit / 2), {
+        accounts: {
+          bankroll: bankrollPda,
+          lpPosition: lpPositionPda(house.publicKey),
+          depositor: house.publicKey,
+        },
+        signers: [house],
+      });
+      const balanceAfter = await provider.connection.getBalance(house.publicKey);
+      assert.equal(balanceAfter - balanceBefore, deposit / 2, "Half the shares redeem half the SOL");
+    });
+
+    it("Backs a game against the house and settles it", async () => {
+      const wager = 10_000_000;
+      const [houseGamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("house_game"), joiner.publicKey.toBuffer()],
+        program.programId
+      );
+
+      await program.rpc.createHouseGame(new BN(wager), 0, {
+        accounts: {
+          houseGame: houseGamePda,
+          bankroll: bankrollPda,
+          config: configPda,
+          player: joiner.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [joiner],
+      });
+      assert.equal(
+        await provider.connection.getBalance(houseGamePda),
+        (await provider.connection.getMinimumBalanceForRentExemption(8 + 50)) + 2 * wager,
+        "Both sides of the wager should be escrowed"
+      );
+
+      // Wait for the deciding slot hash to land
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      await program.rpc.settleHouseGame({
+        accounts: {
+          houseGame: houseGamePda,
+          bankroll: bankrollPda,
+          player: joiner.publicKey,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        },
+      });
+      assert.isNull(
+        await provider.connection.getAccountInfo(houseGamePda),
+        "House game should be closed"
+      );
+    });
+
+    it("Settles resolvable games in a batch", async () => {
+      const wager = 10_000_000;
+      const [houseGamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("house_game"), creator.publicKey.toBuffer()],
+        program.programId
+      );
+      await program.rpc.createHouseGame(new BN(wager), 1, {
+        accounts: {
+          houseGame: houseGamePda,
+          bankroll: bankrollPda,
+          config: configPda,
+          player: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [creator],
+      });
+
+      // Wait for the deciding slot hash to land
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      await program.rpc.settleMany({
+        accounts: {
+          bankroll: bankrollPda,
+          slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        remainingAccounts: [
+          { pubkey: houseGamePda, isWritable: true, isSigner: false },
+          { pubkey: creator.publicKey, isWritable: true, isSigner: false },
+        ],
+      });
+      assert.isNull(
+        await provider.connection.getAccountInfo(houseGamePda),
+        "Batch should settle and close the house game"
+      );
+    });
+
+    it("Books a parlay and returns the stake when its games are voided", async () => {
+      const stake = minWager;
+      const parlayId = new BN(1);
+      const [parlayPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("parlay"), house.publicKey.toBuffer(), parlayId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const games: PublicKey[] = [];
+      for (const wager of [31_000_000, 32_000_000]) {
+        const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+        await program.rpc.createGame(new BN(wager), {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            creator: creator.publicKey,
+            payer: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [creator],
+        });
+        games.push(gameAccountPda);
+      }
+      const gameAccounts = games.map((pubkey) => ({ pubkey, isWritable: false, isSigner: false }));
+      const placeAccounts = {
+        parlay: parlayPda,
+        bankroll: bankrollPda,
+        config: configPda,
+        bettor: house.publicKey,
+        systemProgram: SystemProgram.programId,
+      };
+
+      try {
+        await program.rpc.placeParlay(parlayId, new BN(stake), [{ creatorWins: {} }], {
+          accounts: placeAccounts,
+          remainingAccounts: gameAccounts.slice(0, 1),
+          signers: [house],
+        });
+        assert.fail("A parlay needs at least two games");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidParlayLegs");
+      }
+      await program.rpc.placeParlay(
+        parlayId,
+        new BN(stake),
+        [{ creatorWins: {} }, { joinerWins: {} }],
+        { accounts: placeAccounts, remainingAccounts: gameAccounts, signers: [house] }
+      );
+      const parlay = await program.account.parlay.fetch(parlayPda);
+      assert.equal(parlay.payout.toNumber(), stake * 2.5 * 2.5, "Both picks at 2.5x");
+
+      // Cancelled games can't be graded, so both legs are void
+      for (const gameAccountPda of games) {
+        await program.rpc.cancelGame({
+          accounts: {
+            gameAccount: gameAccountPda,
+            creatorStats: playerStatsPda(creator.publicKey),
+            lobby: lobbyPda(gameAccountPda),
+            creator: creator.publicKey,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [creator],
+        });
+      }
+      const bettorBefore = await provider.connection.getBalance(house.publicKey);
+      await program.rpc.settleParlay({
+        accounts: { parlay: parlayPda, bankroll: bankrollPda, bettor: house.publicKey },
+        remainingAccounts: gameAccounts,
+      });
+      assert.isNull(await provider.connection.getAccountInfo(parlayPda), "Parlay should be closed");
+      assert.isAtLeast(
+        (await provider.connection.getBalance(house.publicKey)) - bettorBefore,
+        stake,
+        "A fully void parlay returns the stake"
+      );
+    });
+  });
+
+  describe("Fee Recipients", () => {
+    it("Admin splits the house fee across weighted recipients", async () => {
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+
+      try {
+        await program.rpc.setFeeRecipients(
+          [
+            { recipient: house.publicKey, weightBps: 5_000 },
+            { recipient: provider.wallet.publicKey, weightBps: 4_000 },
+          ],
+          { accounts: updateAccounts }
+        );
+        assert.fail("Weights must add up to 100%");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidFeeRecipients");
+      }
+
+      await program.rpc.setFeeRecipients(
+        [
+          { recipient: house.publicKey, weightBps: 6_000 },
+          { recipient: provider.wallet.publicKey, weightBps: 4_000 },
+        ],
+        { accounts: updateAccounts }
+      );
+      const configData = await program.account.config.fetch(configPda);
+      assert.equal(configData.feeRecipients.length, 2, "Both recipients should be stored");
+      assert.equal(configData.feeRecipients[0].weightBps, 6_000);
+
+      // Back to a single house account for the remaining tests
+      await program.rpc.setFeeRecipients([], { accounts: updateAccounts });
+    });
+  });
+
+  describe("Fee Modes", () => {
+    it("Rakes only the winner's profit in winner-pays mode", async () => {
+      const wager = 35_100_000;
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const configBefore = await program.account.config.fetch(configPda);
+      await program.rpc.setFees(configBefore.house, 300, configBefore.jackpotShareBps, {
+        accounts: updateAccounts,
+      });
+
+      const feeCreator = Keypair.generate();
+      const feeJoiner = Keypair.generate();
+      for (const player of [feeCreator, feeJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const [gameAccountPda] = await findGameAccountPda(feeCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: feeCreator.publicKey,
+          payer: feeCreator.publicKey,
+          creatorStats: playerStatsPda(feeCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [feeCreator],
+      });
+      await program.rpc.setFeeMode({ winnerProfit: {} }, {
+        accounts: { gameAccount: gameAccountPda, creator: feeCreator.publicKey },
+        signers: [feeCreator],
+      });
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.feeMode, { winnerProfit: {} }, "Mode should be recorded");
+
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(feeCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: feeJoiner.publicKey,
+          joinerStats: playerStatsPda(feeJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [feeJoiner],
+      });
+
+      // The creator's Rock beats the joiner's Scissors
+      const moves: [Keypair, number][] = [
+        [feeCreator, 0],
+        [feeJoiner, 2],
+      ];
+      for (const [player, move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, move, "fee_mode_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const initialCreatorBalance = await provider.connection.getBalance(feeCreator.publicKey);
+      for (const [player, move] of moves) {
+        await program.rpc.revealMove(move, "fee_mode_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: feeCreator.publicKey,
+            creatorStats: playerStatsPda(feeCreator.publicKey),
+            joiner: feeJoiner.publicKey,
+            joinerStats: playerStatsPda(feeJoiner.publicKey),
+            config: configPda,
+            house: configBefore.house,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      // 3% of the joiner's stake instead of 3% of the whole pot
+      const fee = (wager * 300) / 10_000;
+      const finalCreatorBalance = await provider.connection.getBalance(feeCreator.publicKey);
+      assert.equal(finalCreatorBalance, initialCreatorBalance + 2 * wager - fee);
+
+      await program.rpc.setFees(
+        configBefore.house,
+        configBefore.houseFeeBps,
+        configBefore.jackpotShareBps,
+        { accounts: updateAccounts }
+      );
+    });
+  });
+
+  describe("Loss Insurance", () => {
+    it("Refunds part of an insured loser's stake from the vault", async () => {
+      const wager = 35_200_000;
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const [insuranceVaultPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("insurance_vault")],
+        program.programId
+      );
+      await program.rpc.initializeInsuranceVault({
+        accounts: {
+          insuranceVault: insuranceVaultPda,
+          config: configPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+      });
+      await program.rpc.setInsuranceRates(1_000, 2_500, { accounts: updateAccounts });
+
+      const insuredCreator = Keypair.generate();
+      const insuredJoiner = Keypair.generate();
+      for (const player of [insuredCreator, insuredJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const [gameAccountPda] = await findGameAccountPda(insuredCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: insuredCreator.publicKey,
+          payer: insuredCreator.publicKey,
+          creatorStats: playerStatsPda(insuredCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [insuredCreator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(insuredCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: insuredJoiner.publicKey,
+          joinerStats: playerStatsPda(insuredJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [insuredJoiner],
+      });
+
+      const insureAccounts = {
+        gameAccount: gameAccountPda,
+        player: insuredCreator.publicKey,
+        config: configPda,
+        insuranceVault: insuranceVaultPda,
+        systemProgram: SystemProgram.programId,
+      };
+      try {
+        await program.rpc.insureWager({ accounts: insureAccounts, signers: [insuredCreator] });
+        assert.fail("An empty vault can't back the refund");
+      } catch (err: any) {
+        assert.include(err.message, "InsuranceVaultInsolvent");
+      }
+
+      await provider.sendAndConfirm(
+        new anchor.web3.Transaction().add(
+          SystemProgram.transfer({
+            fromPubkey: provider.wallet.publicKey,
+            toPubkey: insuranceVaultPda,
+            lamports: wager,
+          })
+        )
+      );
+      await program.rpc.insureWager({ accounts: insureAccounts, signers: [insuredCreator] });
+      const refund = (wager * 2_500) / 10_000;
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.creatorCover.toNumber(), refund);
+      let vaultData = await program.account.insuranceVault.fetch(insuranceVaultPda);
+      assert.equal(vaultData.exposure.toNumber(), refund);
+      assert.equal(vaultData.premiumsCollected.toNumber(), (wager * 1_000) / 10_000);
+
+      // The creator's Paper loses to the joiner's Scissors
+      const moves: [Keypair, number][] = [
+        [insuredCreator, 1],
+        [insuredJoiner, 2],
+      ];
+      for (const [player, move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, move, "insured_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const initialCreatorBalance = await provider.connection.getBalance(insuredCreator.publicKey);
+      for (const [player, move] of moves) {
+        await program.rpc.revealMove(move, "insured_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: insuredCreator.publicKey,
+            creatorStats: playerStatsPda(insuredCreator.publicKey),
+            joiner: insuredJoiner.publicKey,
+            joinerStats: playerStatsPda(insuredJoiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            insuranceVault: insuranceVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      const finalCreatorBalance = await provider.connection.getBalance(insuredCreator.publicKey);
+      assert.equal(finalCreatorBalance, initialCreatorBalance + refund, "Loser gets the cover");
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.creatorCover.toNumber(), 0);
+      vaultData = await program.account.insuranceVault.fetch(insuranceVaultPda);
+      assert.equal(vaultData.exposure.toNumber(), 0, "The policy is closed out");
+      assert.equal(vaultData.claimsPaid.toNumber(), refund);
+
+      await program.rpc.setInsuranceRates(0, 0, { accounts: updateAccounts });
+    });
+  });
+
+  describe("Handicap", () => {
+    it("Awards a tie to the lower-rated player", async () => {
+      const favourite = Keypair.generate();
+      const underdog = Keypair.generate();
+      for (const player of [favourite, underdog]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+
+      // Plays one game between the two, the favourite creating it
+      const playGame = async (wager: number, moves: [number, number], handicap: boolean) => {
+        const [gameAccountPda] = await findGameAccountPda(favourite, wager, program.programId);
+        await program.rpc.createGame(new BN(wager), {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            creator: favourite.publicKey,
+            payer: favourite.publicKey,
+            creatorStats: playerStatsPda(favourite.publicKey),
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [favourite],
+        });
+        await program.rpc.setHandicap(handicap, {
+          accounts: { gameAccount: gameAccountPda, creator: favourite.publicKey },
+          signers: [favourite],
+        });
+        const handicapped = await program.account.gameState.fetch(gameAccountPda);
+        assert.equal(hasFlag(handicapped, HANDICAP), handicap, "The handicap is a game flag");
+        await program.rpc.joinGame({
+          accounts: {
+            gameAccount: gameAccountPda,
+            creatorStats: playerStatsPda(favourite.publicKey),
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            joiner: underdog.publicKey,
+            joinerStats: playerStatsPda(underdog.publicKey),
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [underdog],
+        });
+        const players: [Keypair, number][] = [
+          [favourite, moves[0]],
+          [underdog, moves[1]],
+        ];
+        for (const [player, move] of players) {
+          const hashedMove = await hashMove(gameAccountPda, move, "handicap_salt");
+          await program.rpc.selectMove(Array.from(hashedMove), {
+            accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+            signers: [player],
+          });
+          await program.rpc.readyUp({
+            accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+            signers: [player],
+          });
+        }
+        for (const [player, move] of players) {
+          await program.rpc.revealMove(move, "handicap_salt", {
+            accounts: {
+              gameAccount: gameAccountPda,
+              player: player.publicKey,
+              creator: favourite.publicKey,
+              creatorStats: playerStatsPda(favourite.publicKey),
+              joiner: underdog.publicKey,
+              joinerStats: playerStatsPda(underdog.publicKey),
+              config: configPda,
+              house: provider.wallet.publicKey,
+              jackpotVault: jackpotVaultPda,
+              slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+              instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+              systemProgram: SystemProgram.programId,
+              eventAuthority: eventAuthorityPda,
+              program: program.programId,
+            },
+            signers: [player],
+          });
+        }
+      };
+
+      // A first win opens a rating gap
+      await playGame(35_300_000, [0, 2], false);
+      const favouriteStats = await program.account.playerStats.fetch(
+        playerStatsPda(favourite.publicKey)
+      );
+      const underdogStats = await program.account.playerStats.fetch(
+        playerStatsPda(underdog.publicKey)
+      );
+      assert.isAbove(favouriteStats.rating, underdogStats.rating);
+
+      // Both throw Rock, and the handicap hands the tie to the underdog
+      const wager = 35_400_000;
+      const initialUnderdogBalance = await provider.connection.getBalance(underdog.publicKey);
+      await playGame(wager, [0, 0], true);
+      const finalUnderdogBalance = await provider.connection.getBalance(underdog.publicKey);
+      assert.equal(finalUnderdogBalance, initialUnderdogBalance + wager, "Underdog takes the pot");
+      const underdogAfter = await program.account.playerStats.fetch(
+        playerStatsPda(underdog.publicKey)
+      );
+      assert.equal(underdogAfter.wins, underdogStats.wins + 1);
+    });
+  });
+
+  describe("Move Variants", () => {
+    it("Plays an RPS-7 game with the cyclic rule", async () => {
+      const wager = 35_500_000;
+      const sevenCreator = Keypair.generate();
+      const sevenJoiner = Keypair.generate();
+      for (const player of [sevenCreator, sevenJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const [gameAccountPda] = await findGameAccountPda(sevenCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: sevenCreator.publicKey,
+          payer: sevenCreator.publicKey,
+          creatorStats: playerStatsPda(sevenCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [sevenCreator],
+      });
+      const updateAccounts = { gameAccount: gameAccountPda, creator: sevenCreator.publicKey };
+      try {
+        await program.rpc.setMoveCount(4, { accounts: updateAccounts, signers: [sevenCreator] });
+        assert.fail("An even move count can't be fair");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidMoveCount");
+      }
+      await program.rpc.setMoveCount(7, { accounts: updateAccounts, signers: [sevenCreator] });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.moveCount, 7);
+
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(sevenCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: sevenJoiner.publicKey,
+          joinerStats: playerStatsPda(sevenJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [sevenJoiner],
+      });
+
+      // Move 6 beats the three moves before it, 3 included
+      const moves: [Keypair, number][] = [
+        [sevenCreator, 6],
+        [sevenJoiner, 3],
+      ];
+      for (const [player, move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, move, "seven_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const initialCreatorBalance = await provider.connection.getBalance(sevenCreator.publicKey);
+      for (const [player, move] of moves) {
+        await program.rpc.revealMove(move, "seven_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: sevenCreator.publicKey,
+            creatorStats: playerStatsPda(sevenCreator.publicKey),
+            joiner: sevenJoiner.publicKey,
+            joinerStats: playerStatsPda(sevenJoiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      const finalCreatorBalance = await provider.connection.getBalance(sevenCreator.publicKey);
+      assert.equal(finalCreatorBalance, initialCreatorBalance + 2 * wager, "Creator takes the pot");
+    });
+  });
+
+  describe("Rulesets", () => {
+    it("Plays a game by a published ruleset", async () => {
+      const wager = 35_600_000;
+      const author = Keypair.generate();
+      const rulesCreator = Keypair.generate();
+      const rulesJoiner = Keypair.generate();
+      for (const player of [author, rulesCreator, rulesJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const rulesetPda = (id: number) =>
+        PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("ruleset"),
+            author.publicKey.toBuffer(),
+            new BN(id).toArrayLike(Buffer, "le", 8),
+          ],
+          program.programId
+        )[0];
+      const publishAccounts = (id: number) => ({
+        ruleset: rulesetPda(id),
+        author: author.publicKey,
+        systemProgram: SystemProgram.programId,
+      });
+
+      // Rock, Paper, Scissors and Well: Well beats Rock and Scissors, Paper
+      // beats Well
+      const beats = [0b0100, 0b1001, 0b0010, 0b0101, 0, 0, 0, 0, 0];
+      try {
+        await program.rpc.publishRuleset(
+          new BN(0),
+          4,
+          [0b0101, ...beats.slice(1)],
+          { split: {} },
+          new BN(3600),
+          new BN(0),
+          { accounts: publishAccounts(0), signers: [author] }
+        );
+        assert.fail("Rock can't beat itself");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidRuleset");
+      }
+      await program.rpc.publishRuleset(
+        new BN(1),
+        4,
+        beats,
+        { split: {} },
+        new BN(3600),
+        new BN(0),
+        { accounts: publishAccounts(1), signers: [author] }
+      );
+
+      const [gameAccountPda] = await findGameAccountPda(rulesCreator, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: rulesCreator.publicKey,
+          payer: rulesCreator.publicKey,
+          creatorStats: playerStatsPda(rulesCreator.publicKey),
+          ruleset: rulesetPda(1),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [rulesCreator],
+      });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.moveCount, 4);
+      assert.isTrue(gameAccountData.ruleset.equals(rulesetPda(1)));
+      try {
+        await program.rpc.setMoveCount(3, {
+          accounts: { gameAccount: gameAccountPda, creator: rulesCreator.publicKey },
+          signers: [rulesCreator],
+        });
+        assert.fail("The ruleset fixes the moves");
+      } catch (err: any) {
+        assert.include(err.message, "RulesetGame");
+      }
+
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(rulesCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: rulesJoiner.publicKey,
+          joinerStats: playerStatsPda(rulesJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [rulesJoiner],
+      });
+
+      // Well beats Rock
+      const moves: [Keypair, number][] = [
+        [rulesCreator, 3],
+        [rulesJoiner, 0],
+      ];
+      for (const [player, move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, move, "ruleset_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const initialCreatorBalance = await provider.connection.getBalance(rulesCreator.publicKey);
+      for (const [player, move] of moves) {
+        await program.rpc.revealMove(move, "ruleset_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: rulesCreator.publicKey,
+            creatorStats: playerStatsPda(rulesCreator.publicKey),
+            joiner: rulesJoiner.publicKey,
+            joinerStats: playerStatsPda(rulesJoiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      const finalCreatorBalance = await provider.connection.getBalance(rulesCreator.publicKey);
+      assert.equal(finalCreatorBalance, initialCreatorBalance + 2 * wager, "Well takes the pot");
+    });
+  });
+
+  describe("Labels", () => {
+    it("Names an open game", async () => {
+      const wager = 35_700_000;
+      const labelCreator = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        labelCreator.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      const [gameAccountPda] = await findGameAccountPda(labelCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: labelCreator.publicKey,
+          payer: labelCreator.publicKey,
+          creatorStats: playerStatsPda(labelCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [labelCreator],
+      });
+      const updateAccounts = { gameAccount: gameAccountPda, creator: labelCreator.publicKey };
+      try {
+        await program.rpc.setLabel("x".repeat(33), {
+          accounts: updateAccounts,
+          signers: [labelCreator],
+        });
+        assert.fail("Labels are capped at 32 bytes");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidLabel");
+      }
+
+      const label = "Friday night grudge match";
+      await program.rpc.setLabel(label, { accounts: updateAccounts, signers: [labelCreator] });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      const stored = Buffer.from(gameAccountData.label);
+      assert.equal(stored.subarray(0, label.length).toString(), label);
+      assert.isTrue(stored.subarray(label.length).every((byte) => byte === 0), "Zero-padded");
+    });
+  });
+
+  describe("Categories", () => {
+    it("Mirrors a game's category into its lobby entry", async () => {
+      const wager = 35_800_000;
+      const categoryCreator = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        categoryCreator.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      const [gameAccountPda] = await findGameAccountPda(categoryCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: categoryCreator.publicKey,
+          payer: categoryCreator.publicKey,
+          creatorStats: playerStatsPda(categoryCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [categoryCreator],
+      });
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.category, { casual: {} });
+
+      await program.rpc.setCategory({ ranked: {} }, {
+        accounts: {
+          gameAccount: gameAccountPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: categoryCreator.publicKey,
+        },
+        signers: [categoryCreator],
+      });
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.category, { ranked: {} });
+      const lobbyData = await program.account.lobby.fetch(lobbyPda(gameAccountPda));
+      const entry = lobbyData.games.find((game) => game.game.equals(gameAccountPda));
+      assert.deepEqual(entry.category, { ranked: {} });
+    });
+  });
+
+  describe("Garbage Collection", () => {
+    it("Pays a bounty for collecting an expired open game", async () => {
+      const wager = 35_900_000;
+      const gcCreator = Keypair.generate();
+      const collector = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        gcCreator.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      const rulesetPda = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("ruleset"),
+          gcCreator.publicKey.toBuffer(),
+          new BN(0).toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+
+      // Classic moves with a one second join window
+      await program.rpc.publishRuleset(
+        new BN(0),
+        3,
+        [0b100, 0b001, 0b010, 0, 0, 0, 0, 0, 0],
+        { split: {} },
+        new BN(1),
+        new BN(0),
+        {
+          accounts: {
+            ruleset: rulesetPda,
+            author: gcCreator.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [gcCreator],
+        }
+      );
+      const [gameAccountPda] = await findGameAccountPda(gcCreator, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: gcCreator.publicKey,
+          payer: gcCreator.publicKey,
+          creatorStats: playerStatsPda(gcCreator.publicKey),
+          ruleset: rulesetPda,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [gcCreator],
+      });
+      await new Promise((resolve) => setTimeout(resolve, 2000));
+
+      const creatorBefore = await provider.connection.getBalance(gcCreator.publicKey);
+      await program.rpc.gcGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(gcCreator.publicKey),
+          lobby: lobbyPda(gameAccountPda),
+          caller: collector.publicKey,
+          creator: gcCreator.publicKey,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [collector],
+      });
+
+      const bounty = 1_000_000;
+      assert.equal(await provider.connection.getBalance(collector.publicKey), bounty);
+      const creatorAfter = await provider.connection.getBalance(gcCreator.publicKey);
+      assert.isAbove(creatorAfter - creatorBefore, wager, "Wager and most of the rent");
+      assert.isNull(await provider.connection.getAccountInfo(gameAccountPda));
+      const lobbyData = await program.account.lobby.fetch(lobbyPda(gameAccountPda));
+      assert.isUndefined(lobbyData.games.find((game) => game.game.equals(gameAccountPda)));
+    });
+  });
+
+  describe("Open Game Limit", () => {
+    it("Caps the open games a wallet can list", async () => {
+      const wager = 36_000_000;
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const busyCreator = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        busyCreator.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      const createGame = async (stake: number) => {
+        const [gameAccountPda] = await findGameAccountPda(busyCreator, stake, program.programId);
+        await program.rpc.createGame(new BN(stake), {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            creator: busyCreator.publicKey,
+            payer: busyCreator.publicKey,
+            creatorStats: playerStatsPda(busyCreator.publicKey),
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [busyCreator],
+        });
+        return gameAccountPda;
+      };
+
+      await program.rpc.setMaxOpenGames(1, { accounts: updateAccounts });
+      const firstGame = await createGame(wager);
+      try {
+        await createGame(wager + 1);
+        assert.fail("Only one open game is allowed");
+      } catch (err: any) {
+        assert.include(err.message, "TooManyOpenGames");
+      }
+
+      // Cancelling the first game frees the slot
+      await program.rpc.cancelGame({
+        accounts: {
+          gameAccount: firstGame,
+          lobby: lobbyPda(firstGame),
+          creator: busyCreator.publicKey,
+          creatorStats: playerStatsPda(busyCreator.publicKey),
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [busyCreator],
+      });
+      let statsData = await program.account.playerStats.fetch(
+        playerStatsPda(busyCreator.publicKey)
+      );
+      assert.equal(statsData.openGames, 0);
+      await createGame(wager + 1);
+      statsData = await program.account.playerStats.fetch(playerStatsPda(busyCreator.publicKey));
+      assert.equal(statsData.openGames, 1);
+
+      // Hidden games count against the same cap
+      const wagerCommitment = crypto
+        .createHash("sha256")
+        .update(new BN(wager).toArrayLike(Buffer, "le", 8))
+        .update(Buffer.from("capped_hidden_salt"))
+        .digest();
+      const [hiddenGamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("hidden_game"), busyCreator.publicKey.toBuffer(), wagerCommitment],
+        program.programId
+      );
+      try {
+        await program.rpc.createHiddenGame([...wagerCommitment], new BN(wager), {
+          accounts: {
+            gameAccount: hiddenGamePda,
+            config: configPda,
+            creatorStats: playerStatsPda(busyCreator.publicKey),
+            creator: busyCreator.publicKey,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [busyCreator],
+        });
+        assert.fail("A hidden game is an open game too");
+      } catch (err: any) {
+        assert.include(err.message, "TooManyOpenGames");
+      }
+
+      await program.rpc.setMaxOpenGames(0, { accounts: updateAccounts });
+    });
+  });
+
+  describe("Game Index", () => {
+    it("Links each new game to the one created before it", async () => {
+      const wager = 36_100_000;
+      const indexCreator = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        indexCreator.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      const createGame = async (stake: number) => {
+        const [gameAccountPda] = await findGameAccountPda(indexCreator, stake, program.programId);
+        await program.rpc.createGame(new BN(stake), {
+          accounts: {
+            gameAccount: gameAccountPda,
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            creator: indexCreator.publicKey,
+            payer: indexCreator.publicKey,
+            creatorStats: playerStatsPda(indexCreator.publicKey),
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [indexCreator],
+        });
+        return gameAccountPda;
+      };
+
+      const firstGame = await createGame(wager);
+      const secondGame = await createGame(wager + 1);
+      const first = await program.account.gameState.fetch(firstGame);
+      const second = await program.account.gameState.fetch(secondGame);
+      assert.equal(second.index.toNumber(), first.index.toNumber() + 1);
+      assert.isTrue(second.prevGame.equals(firstGame), "Links back to the previous game");
+
+      const configData = await program.account.config.fetch(configPda);
+      assert.isTrue(configData.latestGame.equals(secondGame));
+      assert.equal(configData.gameCount.toNumber(), second.index.toNumber());
+    });
+  });
+
+  describe("Filterable Layout", () => {
+    it("Finds open games by creator and wager with memcmp filters", async () => {
+      const wager = 36_200_000;
+      const listedCreator = Keypair.generate();
+      const airdropSignature = await provider.connection.requestAirdrop(
+        listedCreator.publicKey,
+        anchor.web3.LAMPORTS_PER_SOL
+      );
+      await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      const [gameAccountPda] = await findGameAccountPda(listedCreator, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: listedCreator.publicKey,
+          payer: listedCreator.publicKey,
+          creatorStats: playerStatsPda(listedCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [listedCreator],
+      });
+
+      // GAME_STATUS_OFFSET, GAME_CREATOR_OFFSET and GAME_WAGER_OFFSET
+      const bs58 = anchor.utils.bytes.bs58;
+      const games = await program.account.gameState.all([
+        { memcmp: { offset: 9, bytes: bs58.encode(Buffer.from([0])) } },
+        { memcmp: { offset: 10, bytes: listedCreator.publicKey.toBase58() } },
+        { memcmp: { offset: 42, bytes: bs58.encode(new BN(wager).toArrayLike(Buffer, "le", 8)) } },
+      ]);
+      assert.equal(games.length, 1);
+      assert.isTrue(games[0].publicKey.equals(gameAccountPda));
+      assert.deepEqual(games[0].account.status, { open: {} });
+    });
+  });
+
+  describe("Lifecycle Timestamps", () => {
+    it("Stamps when a game was joined, first revealed and ended", async () => {
+      const wager = 36_300_000;
+      const configData = await program.account.config.fetch(configPda);
+      const timedCreator = Keypair.generate();
+      const timedJoiner = Keypair.generate();
+      for (const player of [timedCreator, timedJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const [gameAccountPda] = await findGameAccountPda(timedCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: timedCreator.publicKey,
+          payer: timedCreator.publicKey,
+          creatorStats: playerStatsPda(timedCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [timedCreator],
+      });
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isAbove(gameAccountData.createdAt.toNumber(), 0);
+      assert.equal(gameAccountData.joinedAt.toNumber(), 0, "Not joined yet");
+
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: timedJoiner.publicKey,
+          joinerStats: playerStatsPda(timedJoiner.publicKey),
+          creatorStats: playerStatsPda(timedCreator.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [timedJoiner],
+      });
+
+      const moves: [Keypair, number][] = [
+        [timedCreator, 0],
+        [timedJoiner, 1],
+      ];
+      for (const [player, move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, move, "timestamp_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const [player, move] of moves) {
+        await program.rpc.revealMove(move, "timestamp_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: timedCreator.publicKey,
+            creatorStats: playerStatsPda(timedCreator.publicKey),
+            joiner: timedJoiner.publicKey,
+            joinerStats: playerStatsPda(timedJoiner.publicKey),
+            config: configPda,
+            house: configData.house,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      const createdAt = gameAccountData.createdAt.toNumber();
+      const joinedAt = gameAccountData.joinedAt.toNumber();
+      const firstRevealAt = gameAccountData.firstRevealAt.toNumber();
+      const endedAt = gameAccountData.endedAt.toNumber();
+      assert.isAtLeast(joinedAt, createdAt);
+      assert.isAtLeast(firstRevealAt, joinedAt);
+      assert.isAtLeast(endedAt, firstRevealAt);
+
+      // Paper beats Rock, and the outcome stays on the account
+      assert.deepEqual(gameAccountData.result, { joinerWins: {} });
+      assert.isTrue(gameAccountData.winner.equals(timedJoiner.publicKey));
+    });
+  });
+
+  describe("Settlement Return Data", () => {
+    it("Returns the winner, payout and fee from the settling reveal", async () => {
+      const wager = 36_400_000;
+      const configData = await program.account.config.fetch(configPda);
+      const returnCreator = Keypair.generate();
+      const returnJoiner = Keypair.generate();
+      for (const player of [returnCreator, returnJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const [gameAccountPda] = await findGameAccountPda(returnCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: returnCreator.publicKey,
+          payer: returnCreator.publicKey,
+          creatorStats: playerStatsPda(returnCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [returnCreator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: returnJoiner.publicKey,
+          joinerStats: playerStatsPda(returnJoiner.publicKey),
+          creatorStats: playerStatsPda(returnCreator.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [returnJoiner],
+      });
+
+      // The creator's Scissors beat the joiner's Paper
+      const moves: [Keypair, number][] = [
+        [returnCreator, 2],
+        [returnJoiner, 1],
+      ];
+      for (const [player, move] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, move, "return_data_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const revealAccounts = (player: Keypair) => ({
+        gameAccount: gameAccountPda,
+        player: player.publicKey,
+        creator: returnCreator.publicKey,
+        creatorStats: playerStatsPda(returnCreator.publicKey),
+        joiner: returnJoiner.publicKey,
+        joinerStats: playerStatsPda(returnJoiner.publicKey),
+        config: configPda,
+        house: configData.house,
+        jackpotVault: jackpotVaultPda,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      });
+      await program.rpc.revealMove(2, "return_data_salt", {
+        accounts: revealAccounts(returnCreator),
+        signers: [returnCreator],
+      });
+
+      // The second reveal settles the game
+      const tx = program.transaction.revealMove(1, "return_data_salt", {
+        accounts: revealAccounts(returnJoiner),
+      });
+      const simulation = await provider.simulate(tx, [returnJoiner]);
+      assert.isTrue(simulation.returnData.programId.equals(program.programId));
+      const data = Buffer.from(simulation.returnData.data[0], "base64");
+      assert.equal(data[0], 1, "A winner is set");
+      assert.isTrue(new PublicKey(data.subarray(1, 33)).equals(returnCreator.publicKey));
+      const payout = new BN(data.subarray(33, 41), "le").toNumber();
+      const fee = new BN(data.subarray(41, 49), "le").toNumber();
+      assert.equal(payout + fee, 2 * wager, "The whole pot is accounted for");
+    });
+  });
+
+  describe("Ready Up Once", () => {
+    it("Rejects a second ready up once the reveal phase has started", async () => {
+      const wager = 36_500_000;
+      const readyCreator = Keypair.generate();
+      const readyJoiner = Keypair.generate();
+      for (const player of [readyCreator, readyJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const [gameAccountPda] = await findGameAccountPda(readyCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: readyCreator.publicKey,
+          payer: readyCreator.publicKey,
+          creatorStats: playerStatsPda(readyCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [readyCreator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: readyJoiner.publicKey,
+          joinerStats: playerStatsPda(readyJoiner.publicKey),
+          creatorStats: playerStatsPda(readyCreator.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [readyJoiner],
+      });
+      for (const [player, move] of [
+        [readyCreator, 0],
+        [readyJoiner, 1],
+      ] as [Keypair, number][]) {
+        const hashedMove = await hashMove(gameAccountPda, move, "ready_once_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const revealing = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(revealing.status, { revealing: {} });
+      assert.isAbove(revealing.revealSlot.toNumber(), 0, "The jackpot's slot is fixed");
+
+      // Readying up again must not push the reveal deadline back
+      for (const player of [readyCreator, readyJoiner]) {
+        try {
+          await program.rpc.readyUp({
+            accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+            signers: [player],
+          });
+          assert.fail("The transaction should have failed because the reveal phase started");
+        } catch (err: any) {
+          assert.match(err.message, /GameNotCommitted|AlreadyReady/);
+        }
+      }
+      const after = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(after.revealDeadline.toNumber(), revealing.revealDeadline.toNumber());
+      assert.equal(after.phaseStartedAt.toNumber(), revealing.phaseStartedAt.toNumber());
+      assert.equal(after.revealSlot.toNumber(), revealing.revealSlot.toNumber());
+    });
+  });
+
+  describe("Tie Policy", () => {
+    it("Replays a tie instead of splitting the pot", async () => {
+      const wager = 13_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setTiePolicy({ replay: {} }, {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      // Both players throw Rock
+      for (const player of [creator, joiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 0, "replay_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const player of [creator, joiner]) {
+        await program.rpc.revealMove(0, "replay_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { committed: {} }, "Tie should reopen commits");
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * wager, "No fee or payout on a replay");
+      assert.isNull(gameAccountData.creatorMove, "Moves should be cleared");
+      assert.isFalse(hasFlag(gameAccountData, CREATOR_READY), "Players must ready up again");
+      assert.equal(gameAccountData.suddenDeathRounds, 1);
+
+      // The sudden-death round gets the shorter reveal window
+      for (const player of [creator, joiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 1, "sudden_death_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const suddenDeath = await program.account.gameState.fetch(gameAccountPda);
+      const now = await provider.connection.getBlockTime(await provider.connection.getSlot());
+      assert.isAtMost(suddenDeath.revealDeadline.toNumber() - now, 60 * 60);
+    });
+
+    it("Requests VRF randomness to break a tie", async () => {
+      const wager = 23_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      await program.rpc.setTieBreakVrf(true, { accounts: updateAccounts });
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setTiePolicy({ random: {} }, {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      // Both players throw Paper
+      for (const player of [creator, joiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 1, "random_tie_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      const seed = crypto
+        .createHash("sha256")
+        .update(Buffer.from("tie_break"))
+        .update(gameAccountPda.toBuffer())
+        .update(gameAccountData.nonce.toArrayLike(Buffer, "le", 8))
+        .digest();
+
+      // ORAO VRF and its network state are cloned into the test validator
+      const vrfProgram = new PublicKey("VRFzZoJdhFWL8rkvu87LpKM3RbcVezpMEc6X5GVDr7y");
+      const [vrfNetworkState] = PublicKey.findProgramAddressSync(
+        [Buffer.from("orao-vrf-network-configuration")],
+        vrfProgram
+      );
+      const [vrfRandomness] = PublicKey.findProgramAddressSync(
+        [Buffer.from("orao-vrf-randomness-request"), seed],
+        vrfProgram
+      );
+      const networkState = await provider.connection.getAccountInfo(vrfNetworkState);
+      const vrfTreasury = new PublicKey(networkState.data.subarray(40, 72));
+      const revealAccounts = (player: PublicKey) => ({
+        gameAccount: gameAccountPda,
+        player,
+        creator: creator.publicKey,
+        creatorStats: playerStatsPda(creator.publicKey),
+        joiner: joiner.publicKey,
+        joinerStats: playerStatsPda(joiner.publicKey),
+        config: configPda,
+        house: provider.wallet.publicKey,
+        jackpotVault: jackpotVaultPda,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      });
+      const vrfAccounts = { vrfProgram, vrfNetworkState, vrfTreasury, vrfRandomness };
+
+      await program.rpc.revealMove(1, "random_tie_salt", {
+        accounts: revealAccounts(creator.publicKey),
+        signers: [creator],
+      });
+      try {
+        await program.rpc.revealMove(1, "random_tie_salt", {
+          accounts: revealAccounts(joiner.publicKey),
+          signers: [joiner],
+        });
+        assert.fail("The tying reveal must request randomness");
+      } catch (err: any) {
+        assert.include(err.message, "MissingVrfAccounts");
+      }
+      await program.rpc.revealMove(1, "random_tie_salt", {
+        accounts: { ...revealAccounts(joiner.publicKey), ...vrfAccounts },
+        signers: [joiner],
+      });
+
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { revealing: {} }, "Tie waits on the VRF");
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * wager, "Nothing is paid out yet");
+      assert.deepEqual(Buffer.from(gameAccountData.tieBreakSeed), seed);
+      const request = await provider.connection.getAccountInfo(vrfRandomness);
+      assert.isTrue(request.owner.equals(vrfProgram), "The request is stored by the VRF");
+
+      // Nobody can settle the tie before the VRF network answers
+      try {
+        await program.rpc.fulfillTieBreak({
+          accounts: { ...revealAccounts(provider.wallet.publicKey), ...vrfAccounts },
+        });
+        assert.fail("The randomness is not fulfilled yet");
+      } catch (err: any) {
+        assert.include(err.message, "RandomnessNotFulfilled");
+      }
+
+      await program.rpc.setTieBreakVrf(false, { accounts: updateAccounts });
+    });
+
+    it("Refunds both stakes in full on a draw when the fee is waived", async () => {
+      const wager = 35_000_000;
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const configBefore = await program.account.config.fetch(configPda);
+      await program.rpc.setFees(configBefore.house, 300, configBefore.jackpotShareBps, {
+        accounts: updateAccounts,
+      });
+      await program.rpc.setDrawFeeWaiver(true, { accounts: updateAccounts });
+      let configData = await program.account.config.fetch(configPda);
+      assert.isTrue(configData.waiveDrawFees);
+
+      const drawCreator = Keypair.generate();
+      const drawJoiner = Keypair.generate();
+      for (const player of [drawCreator, drawJoiner]) {
+        const airdropSignature = await provider.connection.requestAirdrop(
+          player.publicKey,
+          anchor.web3.LAMPORTS_PER_SOL
+        );
+        await provider.connection.confirmTransaction(airdropSignature, "confirmed");
+      }
+      const [gameAccountPda] = await findGameAccountPda(drawCreator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: drawCreator.publicKey,
+          payer: drawCreator.publicKey,
+          creatorStats: playerStatsPda(drawCreator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [drawCreator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(drawCreator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: drawJoiner.publicKey,
+          joinerStats: playerStatsPda(drawJoiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [drawJoiner],
+      });
+
+      // Both players throw Rock under the default Split policy
+      for (const player of [drawCreator, drawJoiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 0, "draw_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const initialCreatorBalance = await provider.connection.getBalance(drawCreator.publicKey);
+      const initialJoinerBalance = await provider.connection.getBalance(drawJoiner.publicKey);
+      for (const player of [drawCreator, drawJoiner]) {
+        await program.rpc.revealMove(0, "draw_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: drawCreator.publicKey,
+            creatorStats: playerStatsPda(drawCreator.publicKey),
+            joiner: drawJoiner.publicKey,
+            joinerStats: playerStatsPda(drawJoiner.publicKey),
+            config: configPda,
+            house: configBefore.house,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      // No fee came off the pot, so neither player loses anything
+      const finalCreatorBalance = await provider.connection.getBalance(drawCreator.publicKey);
+      const finalJoinerBalance = await provider.connection.getBalance(drawJoiner.publicKey);
+      assert.equal(finalCreatorBalance, initialCreatorBalance + wager, "Creator stake mismatch");
+      assert.equal(finalJoinerBalance, initialJoinerBalance + wager, "Joiner stake mismatch");
+
+      await program.rpc.setDrawFeeWaiver(false, { accounts: updateAccounts });
+      await program.rpc.setFees(
+        configBefore.house,
+        configBefore.houseFeeBps,
+        configBefore.jackpotShareBps,
+        { accounts: updateAccounts }
+      );
+      configData = await program.account.config.fetch(configPda);
+      assert.isFalse(configData.waiveDrawFees);
+    });
+  });
+
+  describe("Reveal Order", () => {
+    it("Rejects a creator reveal before the joiner's when the joiner goes first", async () => {
+      const wager = 17_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setRevealOrder({ joinerFirst: {} }, {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      for (const player of [creator, joiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 0, "order_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const revealAccounts = (player: Keypair) => ({
+        gameAccount: gameAccountPda,
+        player: player.publicKey,
+        creator: creator.publicKey,
+        creatorStats: playerStatsPda(creator.publicKey),
+        joiner: joiner.publicKey,
+        joinerStats: playerStatsPda(joiner.publicKey),
+        config: configPda,
+        house: provider.wallet.publicKey,
+        jackpotVault: jackpotVaultPda,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      });
+
+      try {
+        await program.rpc.revealMove(0, "order_salt", {
+          accounts: revealAccounts(creator),
+          signers: [creator],
+        });
+        assert.fail("The creator should not be able to reveal first");
+      } catch (err: any) {
+        assert.include(err.message, "RevealOutOfOrder");
+      }
+
+      await program.rpc.revealMove(0, "order_salt", {
+        accounts: revealAccounts(joiner),
+        signers: [joiner],
+      });
+      await program.rpc.revealMove(0, "order_salt", {
+        accounts: revealAccounts(creator),
+        signers: [creator],
+      });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { ended: {} });
+    });
+  });
+
+  describe("Time Banks", () => {
+    it("Forfeits the match of a player whose clock runs out", async () => {
+      const wager = 24_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setTimeBank(new BN(5), {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      const claimAccounts = {
+        gameAccount: gameAccountPda,
+        creator: creator.publicKey,
+        joiner: joiner.publicKey,
+        config: configPda,
+        house: provider.wallet.publicKey,
+        jackpotVault: jackpotVaultPda,
+        creatorStats: playerStatsPda(creator.publicKey),
+        joinerStats: playerStatsPda(joiner.publicKey),
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      try {
+        await program.rpc.claimTimeForfeit({ accounts: claimAccounts });
+        assert.fail("Both clocks still have time left");
+      } catch (err: any) {
+        assert.include(err.message, "NoTimeForfeit");
+      }
+
+      // The creator commits in time, the joiner lets the clock run out
+      const hashedMove = await hashMove(gameAccountPda, 0, "clock_salt");
+      for (const player of [creator, joiner]) {
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      await program.rpc.readyUp({
+        accounts: { gameAccount: gameAccountPda, player: creator.publicKey },
+        signers: [creator],
+      });
+      await new Promise((resolve) => setTimeout(resolve, 7000));
+      try {
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: joiner.publicKey },
+          signers: [joiner],
+        });
+        assert.fail("An exhausted clock can't ready up");
+      } catch (err: any) {
+        assert.include(err.message, "TimeBankExhausted");
+      }
+
+      await program.rpc.claimTimeForfeit({ accounts: claimAccounts });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { ended: {} });
+      assert.isAbove(gameAccountData.creatorTimeLeft.toNumber(), 0);
+      assert.equal(gameAccountData.escrow.toNumber(), 0, "The creator wins the pot");
+    });
+  });
+
+  describe("Surrender", () => {
+    it("Refunds part of a surrendering player's stake", async () => {
+      const wager = 25_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      await program.rpc.setSurrenderRefund(5_000, { accounts: updateAccounts });
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      const creatorBefore = await provider.connection.getBalance(creator.publicKey);
+      await program.rpc.surrender({
+        accounts: {
+          gameAccount: gameAccountPda,
+          player: joiner.publicKey,
+          creator: creator.publicKey,
+          joiner: joiner.publicKey,
+          config: configPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          joinerStats: playerStatsPda(joiner.publicKey),
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { ended: {} });
+      assert.deepEqual(gameAccountData.result, { creatorWins: {} });
+      assert.isTrue(gameAccountData.winner.equals(creator.publicKey), "The creator won");
+      assert.equal(gameAccountData.escrow.toNumber(), 0);
+      assert.equal(
+        (await provider.connection.getBalance(creator.publicKey)) - creatorBefore,
+        wager + wager / 2,
+        "The creator keeps their stake plus the half the joiner gave up"
+      );
+
+      await program.rpc.setSurrenderRefund(0, { accounts: updateAccounts });
+    });
+  });
+
+  describe("Wager Changes", () => {
+    it("Tops up the wager of an open game", async () => {
+      const wager = 26_000_000;
+      const topUp = 2_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      const changeAccounts = {
+        gameAccount: gameAccountPda,
+        config: configPda,
+        lobby: lobbyPda(gameAccountPda),
+        creator: creator.publicKey,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      await program.rpc.increaseWager(new BN(topUp), {
+        accounts: changeAccounts,
+        signers: [creator],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.wager.toNumber(), wager + topUp);
+      assert.equal(gameAccountData.escrow.toNumber(), wager + topUp);
+      const lobby = await program.account.lobby.fetch(lobbyPda(gameAccountPda));
+      const entry = lobby.games.find((game) => game.game.equals(gameAccountPda));
+      assert.equal(entry.wager.toNumber(), wager + topUp, "The lobby lists the new stake");
+
+      try {
+        await program.rpc.increaseWager(new BN(maxWager), {
+          accounts: changeAccounts,
+          signers: [creator],
+        });
+        assert.fail("The wager can't go above the config's limit");
+      } catch (err: any) {
+        assert.include(err.message, "WagerTooHigh");
+      }
+    });
+
+    it("Lowers the wager of an open game and refunds the difference", async () => {
+      const [gameAccountPda] = await findGameAccountPda(creator, 26_000_000, program.programId);
+      const cut = 3_000_000;
+      const before = await program.account.gameState.fetch(gameAccountPda);
+      const creatorBefore = await provider.connection.getBalance(creator.publicKey);
+
+      const signature = await program.rpc.decreaseWager(new BN(cut), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await provider.connection.confirmTransaction(signature, "confirmed");
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      const wager = before.wager.toNumber() - cut;
+      assert.equal(gameAccountData.wager.toNumber(), wager);
+      assert.equal(gameAccountData.escrow.toNumber(), wager);
+      assert.isAbove(await provider.connection.getBalance(creator.publicKey), creatorBefore);
+      const changed = (await cpiEvents(signature)).find((event) => event.name === "wagerChanged");
+      assert.isDefined(changed, "Lobby UIs learn about the new stake");
+      assert.equal(changed.data.wager.toNumber(), wager);
+
+      await program.rpc.cancelGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+    });
+
+    it("Settles on a joiner's counter-offer", async () => {
+      const wager = 29_000_000;
+      const offer = 30_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.offerWager(new BN(offer), {
+        accounts: { gameAccount: gameAccountPda, config: configPda, joiner: joiner.publicKey },
+        signers: [joiner],
+      });
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.counterOffer.joiner.equals(joiner.publicKey));
+      assert.equal(gameAccountData.counterOffer.wager.toNumber(), offer);
+
+      const changeAccounts = {
+        gameAccount: gameAccountPda,
+        config: configPda,
+        lobby: lobbyPda(gameAccountPda),
+        creator: creator.publicKey,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      try {
+        await program.rpc.acceptCounterOffer(joiner.publicKey, new BN(offer + 1), {
+          accounts: changeAccounts,
+          signers: [creator],
+        });
+        assert.fail("Only the pending offer can be accepted");
+      } catch (err: any) {
+        assert.include(err.message, "CounterOfferMismatch");
+      }
+      await program.rpc.acceptCounterOffer(joiner.publicKey, new BN(offer), {
+        accounts: changeAccounts,
+        signers: [creator],
+      });
+
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.wager.toNumber(), offer);
+      assert.equal(gameAccountData.escrow.toNumber(), offer, "The creator matched the offer");
+      assert.isNull(gameAccountData.counterOffer);
+      assert.isTrue(gameAccountData.reservedFor.equals(joiner.publicKey), "Seat held for the offerer");
+
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * offer);
+    });
+  });
+
+  describe("Presets", () => {
+    it("Creates a game with the settings saved in a preset", async () => {
+      const wager = 33_000_000;
+      const presetId = new BN(1);
+      const [presetPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("preset"), creator.publicKey.toBuffer(), presetId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const settings = {
+        wager: new BN(wager),
+        joinWindow: new BN(3600),
+        tiePolicy: { replay: {} },
+        revealOrder: { creatorFirst: {} },
+        timeBank: new BN(600),
+        minReputation: 50,
+        inviteOnly: false,
+        approvalRequired: true,
+      };
+      const presetAccounts = {
+        preset: presetPda,
+        creator: creator.publicKey,
+        systemProgram: SystemProgram.programId,
+      };
+
+      try {
+        await program.rpc.savePreset(presetId, { ...settings, joinWindow: new BN(0) }, {
+          accounts: presetAccounts,
+          signers: [creator],
+        });
+        assert.fail("A preset needs a join window");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidJoinWindow");
+      }
+      await program.rpc.savePreset(presetId, settings, {
+        accounts: presetAccounts,
+        signers: [creator],
+      });
+
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      await program.rpc.createGameFromPreset({
+        accounts: {
+          preset: presetPda,
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creatorStats: playerStatsPda(creator.publicKey),
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.wager.toNumber(), wager);
+      assert.equal(gameAccountData.escrow.toNumber(), wager);
+      assert.equal(
+        gameAccountData.joinDeadline.toNumber() - gameAccountData.createdAt.toNumber(),
+        3600
+      );
+      assert.deepEqual(gameAccountData.revealOrder, { creatorFirst: {} });
+      assert.equal(gameAccountData.timeBank.toNumber(), 600);
+      assert.equal(gameAccountData.minReputation, 50);
+      assert.equal(gameAccountData.flags & (1 << 4), 1 << 4, "Ties are replayed");
+      assert.equal(gameAccountData.flags & (1 << 6), 1 << 6, "Joiners need approval");
+
+      await program.rpc.cancelGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.closePreset({
+        accounts: { preset: presetPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+      assert.isNull(await provider.connection.getAccountInfo(presetPda), "Preset should be closed");
+    });
+  });
+
+  describe("Reveal Bonds", () => {
+    it("Returns both bonds when both players reveal in time", async () => {
+      const wager = 18_000_000;
+      const bond = 5_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setRevealBond(new BN(bond), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.equal(gameAccountData.bond.toNumber(), bond);
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * wager, "Bonds stay out of the escrow");
+
+      for (const player of [creator, joiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 1, "bond_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      const revealAccounts = (player: Keypair) => ({
+        gameAccount: gameAccountPda,
+        player: player.publicKey,
+        creator: creator.publicKey,
+        creatorStats: playerStatsPda(creator.publicKey),
+        joiner: joiner.publicKey,
+        joinerStats: playerStatsPda(joiner.publicKey),
+        config: configPda,
+        house: provider.wallet.publicKey,
+        jackpotVault: jackpotVaultPda,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      });
+
+      // The reveal window is still open, so nobody has timed out yet
+      await program.rpc.revealMove(1, "bond_salt", {
+        accounts: revealAccounts(creator),
+        signers: [creator],
+      });
+      try {
+        await program.rpc.claimRevealTimeout({
+          accounts: revealAccounts(creator),
+          signers: [creator],
+        });
+        assert.fail("The joiner's bond should not be claimable before the deadline");
+      } catch (err: any) {
+        assert.include(err.message, "RevealDeadlineNotReached");
+      }
+
+      await program.rpc.revealMove(1, "bond_salt", {
+        accounts: revealAccounts(joiner),
+        signers: [joiner],
+      });
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { ended: {} });
+      assert.equal(gameAccountData.bond.toNumber(), 0);
+
+      // Only rent is left behind once the pot and both bonds are paid out
+      const gameInfo = await provider.connection.getAccountInfo(gameAccountPda);
+      assert.equal(
+        gameInfo.lamports,
+        await provider.connection.getMinimumBalanceForRentExemption(gameInfo.data.length)
+      );
+    });
+  });
+
+  describe("Reputation", () => {
+    it("Keeps joiners below the game's minimum reputation out", async () => {
+      const wager = 19_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+
+      const joinerStats = await program.account.playerStats.fetch(playerStatsPda(joiner.publicKey));
+      const setMinReputation = (minReputation: number) =>
+        program.rpc.setMinReputation(minReputation, {
+          accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+          signers: [creator],
+        });
+      const joinGame = () =>
+        program.rpc.joinGame({
+          accounts: {
+            gameAccount: gameAccountPda,
+            creatorStats: playerStatsPda(creator.publicKey),
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [joiner],
+        });
+
+      await setMinReputation(joinerStats.reputation + 1);
+      try {
+        await joinGame();
+        assert.fail("A joiner below the minimum reputation should not be able to join");
+      } catch (err: any) {
+        assert.include(err.message, "ReputationTooLow");
+      }
+
+      await setMinReputation(joinerStats.reputation);
+      await joinGame();
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.opponent.equals(joiner.publicKey));
+    });
+  });
+
+  describe("Rakeback", () => {
+    it("Pays rakeback only on fees actually paid", async () => {
+      const [treasuryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury")],
+        program.programId
+      );
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+      const claimRakeback = () =>
+        program.rpc.claimRakeback({
+          accounts: {
+            playerStats: playerStatsPda(joiner.publicKey),
+            treasury: treasuryPda,
+            config: configPda,
+            player: joiner.publicKey,
+          },
+          signers: [joiner],
+        });
+
+      // The test games charge no house fee, so nothing has been paid yet
+      const joinerStats = await program.account.playerStats.fetch(playerStatsPda(joiner.publicKey));
+      assert.equal(joinerStats.rakePaid.toNumber(), 0);
+
+      await program.rpc.setRakeback(5_000, new BN(1), { accounts: updateAccounts });
+      try {
+        await claimRakeback();
+        assert.fail("Rakeback should not be claimable below the threshold");
+      } catch (err: any) {
+        assert.include(err.message, "RakebackThresholdNotMet");
+      }
+
+      await program.rpc.setRakeback(5_000, new BN(0), { accounts: updateAccounts });
+      try {
+        await claimRakeback();
+        assert.fail("Rakeback on zero fees should be nothing");
+      } catch (err: any) {
+        assert.include(err.message, "NoRakebackOwed");
+      }
+
+      await program.rpc.setRakeback(0, new BN(0), { accounts: updateAccounts });
+    });
+  });
+
+  describe("Loyalty Points", () => {
+    it("Mints frozen points to players of a settled game", async () => {
+      const wager = 21_000_000;
+      const pointsPerSol = 1_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const [pointsMintPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("points_mint")],
+        program.programId
+      );
+      const updateAccounts = { config: configPda, admin: provider.wallet.publicKey };
+
+      await program.rpc.initializePointsMint({
+        accounts: {
+          pointsMint: pointsMintPda,
+          config: configPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        },
+      });
+      await program.rpc.setPoints(new BN(pointsPerSol), false, { accounts: updateAccounts });
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+      for (const player of [creator, joiner]) {
+        const hashedMove = await hashMove(gameAccountPda, 0, "points_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const player of [creator, joiner]) {
+        await program.rpc.revealMove(0, "points_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+      await program.rpc.setPoints(new BN(0), false, { accounts: updateAccounts });
+
+      const points = (wager * pointsPerSol) / anchor.web3.LAMPORTS_PER_SOL;
+      const joinerStats = await program.account.playerStats.fetch(playerStatsPda(joiner.publicKey));
+      assert.equal(joinerStats.pointsUnclaimed.toNumber(), points);
+
+      const joinerToken = await createAccount(
+        provider.connection,
+        joiner,
+        pointsMintPda,
+        joiner.publicKey
+      );
+      await program.rpc.claimPoints({
+        accounts: {
+          playerStats: playerStatsPda(joiner.publicKey),
+          config: configPda,
+          pointsMint: pointsMintPda,
+          playerToken: joinerToken,
+          player: joiner.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        },
+        signers: [joiner],
+      });
+      const tokenAccount = await getAccount(provider.connection, joinerToken);
+      assert.equal(Number(tokenAccount.amount), points);
+      assert.isTrue(tokenAccount.isFrozen, "Non-tradeable points should stay frozen");
+    });
+  });
+
+  describe("wSOL Payouts", () => {
+    it("Pays a player who opted in as wrapped SOL", async () => {
+      const wager = 22_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const setWsolPayouts = (enabled: boolean) =>
+        program.rpc.setWsolPayouts(enabled, {
+          accounts: { playerStats: playerStatsPda(joiner.publicKey), player: joiner.publicKey },
+          signers: [joiner],
+        });
+      const joinerWsol = await createWrappedNativeAccount(
+        provider.connection,
+        joiner,
+        joiner.publicKey,
+        0
+      );
+      await setWsolPayouts(true);
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.joinGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [joiner],
+      });
+      // Rock for the creator, Paper for the joiner
+      const moves: [Keypair, number][] = [
+        [creator, 0],
+        [joiner, 1],
+      ];
+      for (const [player, playerMove] of moves) {
+        const hashedMove = await hashMove(gameAccountPda, playerMove, "wsol_salt");
+        await program.rpc.selectMove(Array.from(hashedMove), {
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+        await program.rpc.readyUp({
+          accounts: { gameAccount: gameAccountPda, player: player.publicKey },
+          signers: [player],
+        });
+      }
+      for (const [player, playerMove] of moves) {
+        await program.rpc.revealMove(playerMove, "wsol_salt", {
+          accounts: {
+            gameAccount: gameAccountPda,
+            player: player.publicKey,
+            creator: creator.publicKey,
+            creatorStats: playerStatsPda(creator.publicKey),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            config: configPda,
+            house: provider.wallet.publicKey,
+            jackpotVault: jackpotVaultPda,
+            slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            joinerWsol,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [player],
+        });
+      }
+      await setWsolPayouts(false);
+
+      const wsolAccount = await getAccount(provider.connection, joinerWsol);
+      assert.equal(Number(wsolAccount.amount), 2 * wager, "The pot should arrive as wSOL");
+    });
+  });
+
+  describe("Join Approval", () => {
+    it("Creator picks an opponent from the join requests", async () => {
+      const wager = 14_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const joinRequestPda = (player: PublicKey): PublicKey =>
+        PublicKey.findProgramAddressSync(
+          [Buffer.from("join_request"), gameAccountPda.toBuffer(), player.toBuffer()],
+          program.programId
+        )[0];
+
+      const challenger = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(challenger.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+        "confirmed"
+      );
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setJoinApproval(true, {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+
+      // Joining directly is no longer possible
+      try {
+        await program.rpc.joinGame({
+          accounts: {
+            gameAccount: gameAccountPda,
+            creatorStats: playerStatsPda(creator.publicKey),
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            joiner: joiner.publicKey,
+            joinerStats: playerStatsPda(joiner.publicKey),
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [joiner],
+        });
+        assert.fail("Joining without approval should fail");
+      } catch (err: any) {
+        assert.include(err.message, "ApprovalRequired");
+      }
+
+      for (const player of [joiner, challenger]) {
+        await program.rpc.requestJoin({
+          accounts: {
+            gameAccount: gameAccountPda,
+            joinRequest: joinRequestPda(player.publicKey),
+            joiner: player.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [player],
+        });
+      }
+
+      await program.rpc.acceptJoinRequest({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          joinRequest: joinRequestPda(joiner.publicKey),
+          joiner: joiner.publicKey,
+          joinerStats: playerStatsPda(joiner.publicKey),
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.opponent.equals(joiner.publicKey));
+      assert.deepEqual(gameAccountData.status, { committed: {} });
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * wager);
+      assert.isNull(
+        await provider.connection.getAccountInfo(joinRequestPda(joiner.publicKey)),
+        "The accepted request should be closed"
+      );
+
+      // The passed-over challenger takes their wager back
+      const balanceBefore = await provider.connection.getBalance(challenger.publicKey);
+      await program.rpc.withdrawJoinRequest({
+        accounts: {
+          joinRequest: joinRequestPda(challenger.publicKey),
+          joiner: challenger.publicKey,
+        },
+        signers: [challenger],
+      });
+      const balanceAfter = await provider.connection.getBalance(challenger.publicKey);
+      assert.isAbove(balanceAfter - balanceBefore, wager, "Wager and rent should be returned");
+    });
+  });
+
+  describe("Allowlisted Challenges", () => {
+    it("Only players on the allowlist can join", async () => {
+      const wager = 15_000_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const [allowlistPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("allowlist"), gameAccountPda.toBuffer()],
+        program.programId
+      );
+      const outsider = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(outsider.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+        "confirmed"
+      );
+
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setAllowlist([joiner.publicKey, Keypair.generate().publicKey], {
+        accounts: {
+          gameAccount: gameAccountPda,
+          allowlist: allowlistPda,
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [creator],
+      });
+
+      const joinAccounts = (player: Keypair) => ({
+        gameAccount: gameAccountPda,
+        creatorStats: playerStatsPda(creator.publicKey),
+        config: configPda,
+        lobby: lobbyPda(gameAccountPda),
+        joiner: player.publicKey,
+        joinerStats: playerStatsPda(player.publicKey),
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+        allowlist: allowlistPda,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      });
+      try {
+        await program.rpc.joinGame({ accounts: joinAccounts(outsider), signers: [outsider] });
+        assert.fail("Players off the allowlist should not be able to join");
+      } catch (err: any) {
+        assert.include(err.message, "NotOnAllowlist");
+      }
+
+      await program.rpc.joinGame({ accounts: joinAccounts(joiner), signers: [joiner] });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.opponent.equals(joiner.publicKey));
+
+      // The game is taken, so the allowlist's rent can be reclaimed
+      await program.rpc.closeAllowlist({
+        accounts: {
+          allowlist: allowlistPda,
+          gameAccount: gameAccountPda,
+          creator: creator.publicKey,
+        },
+        signers: [creator],
+      });
+      assert.isNull(await provider.connection.getAccountInfo(allowlistPda));
+    });
+  });
+
+  describe("Hidden Wagers", () => {
+    it("Joiner reveals the committed wager and the excess deposit is refunded", async () => {
+      const wager = 16_000_000;
+      const deposit = 50_000_000;
+      const salt = "hidden_wager_salt";
+      const wagerCommitment = crypto
+        .createHash("sha256")
+        .update(new BN(wager).toArrayLike(Buffer, "le", 8))
+        .update(Buffer.from(salt))
+        .digest();
+      const [gameAccountPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("hidden_game"), creator.publicKey.toBuffer(), wagerCommitment],
+        program.programId
+      );
+
+      const volumeBefore = (await program.account.playerStats.fetch(
+        playerStatsPda(creator.publicKey)
+      )).wagerVolume.toNumber();
+      await program.rpc.createHiddenGame([...wagerCommitment], new BN(deposit), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          creatorStats: playerStatsPda(creator.publicKey),
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      let gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { hidden: {} });
+      assert.equal(gameAccountData.wager.toNumber(), 0, "The wager should stay hidden");
+
+      const joinAccounts = {
+        gameAccount: gameAccountPda,
+        config: configPda,
+        creator: creator.publicKey,
+        creatorStats: playerStatsPda(creator.publicKey),
+        joinerStats: playerStatsPda(joiner.publicKey),
+        joiner: joiner.publicKey,
+        systemProgram: SystemProgram.programId,
+        eventAuthority: eventAuthorityPda,
+        program: program.programId,
+      };
+      try {
+        await program.rpc.joinHiddenGame(new BN(wager + 1), salt, {
+          accounts: joinAccounts,
+          signers: [joiner],
+        });
+        assert.fail("A wager that does not open the commitment should be rejected");
+      } catch (err: any) {
+        assert.include(err.message, "ConstraintSeeds");
+      }
+
+      const creatorBalanceBefore = await provider.connection.getBalance(creator.publicKey);
+      await program.rpc.joinHiddenGame(new BN(wager), salt, {
+        accounts: joinAccounts,
+        signers: [joiner],
+      });
+      const creatorBalanceAfter = await provider.connection.getBalance(creator.publicKey);
+
+      gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.deepEqual(gameAccountData.status, { committed: {} });
+      assert.equal(gameAccountData.wager.toNumber(), wager);
+      assert.equal(gameAccountData.escrow.toNumber(), 2 * wager);
+      assert.equal(
+        creatorBalanceAfter - creatorBalanceBefore,
+        deposit - wager,
+        "The creator should get back the deposit above the wager"
+      );
+      const creatorStats = await program.account.playerStats.fetch(
+        playerStatsPda(creator.publicKey)
+      );
+      assert.equal(
+        creatorStats.wagerVolume.toNumber() - volumeBefore,
+        wager,
+        "Only the wager should count against the creator's daily cap"
+      );
+    });
+
+    it("Repays a hidden game's sponsor the whole deposit on cancel", async () => {
+      const deposit = 36_800_000;
+      const [player, sponsor] = [Keypair.generate(), Keypair.generate()];
+      for (const wallet of [player, sponsor]) {
+        await provider.connection.confirmTransaction(
+          await provider.connection.requestAirdrop(wallet.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+          "confirmed"
+        );
+      }
+      const wagerCommitment = crypto
+        .createHash("sha256")
+        .update(new BN(deposit).toArrayLike(Buffer, "le", 8))
+        .update(Buffer.from("sponsored_hidden_salt"))
+        .digest();
+      const [gameAccountPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("hidden_game"), player.publicKey.toBuffer(), wagerCommitment],
+        program.programId
+      );
+
+      const sponsorBefore = await provider.connection.getBalance(sponsor.publicKey);
+      await program.rpc.createHiddenGame([...wagerCommitment], new BN(deposit), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          creatorStats: playerStatsPda(player.publicKey),
+          creator: player.publicKey,
+          sponsor: sponsor.publicKey,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [player, sponsor],
+      });
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      assert.isTrue(gameAccountData.creatorSponsor.equals(sponsor.publicKey));
+
+      await program.rpc.cancelGame({
+        accounts: {
+          gameAccount: gameAccountPda,
+          creatorStats: playerStatsPda(player.publicKey),
+          lobby: lobbyPda(gameAccountPda),
+          creator: player.publicKey,
+          creatorSponsor: sponsor.publicKey,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [player],
+      });
+      const sponsorAfter = await provider.connection.getBalance(sponsor.publicKey);
+      assert.equal(sponsorAfter, sponsorBefore, "The sponsor gets the whole deposit back");
+    });
+  });
+
+  describe("Callbacks", () => {
+    it("Registers a callback that settlement can notify", async () => {
+      const wager = 34_900_000;
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      const [callbackPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("callback"), gameAccountPda.toBuffer()],
+        program.programId
+      );
+      const callbackProgram = TOKEN_PROGRAM_ID;
+      const watched = Keypair.generate().publicKey;
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setCallback(callbackProgram, [{ pubkey: watched, isWritable: false }], {
+        accounts: {
+          gameAccount: gameAccountPda,
+          callback: callbackPda,
+          creator: creator.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [creator],
+      });
+      const callbackData = await program.account.gameCallback.fetch(callbackPda);
+      assert.isTrue(callbackData.program.equals(callbackProgram));
+      assert.equal(callbackData.accounts.length, 1);
+
+      assert.isTrue(
+        hasFlag(await program.account.gameState.fetch(gameAccountPda), CALLBACK),
+        "Settlers should know to pass the callback"
+      );
+
+      await program.rpc.closeCallback({
+        accounts: {
+          callback: callbackPda,
+          gameAccount: gameAccountPda,
+          creator: creator.publicKey,
+        },
+        signers: [creator],
+      });
+      assert.isNull(await provider.connection.getAccountInfo(callbackPda));
+      assert.isFalse(
+        hasFlag(await program.account.gameState.fetch(gameAccountPda), CALLBACK),
+        "Closing the callback should clear its flag"
+      );
+    });
+  });
+
+  describe("Bots", () => {
+    it("Keeps registered bots out of humans-only games", async () => {
+      const wager = 34_800_000;
+      const bot = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(bot.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+        "confirmed"
+      );
+      await program.rpc.registerBot({
+        accounts: {
+          config: configPda,
+          botStats: playerStatsPda(bot.publicKey),
+          bot: bot.publicKey,
+          authority: bot.publicKey,
+          systemProgram: SystemProgram.programId,
+        },
+        signers: [bot],
+      });
+      const botStats = await program.account.playerStats.fetch(playerStatsPda(bot.publicKey));
+      assert.isTrue(botStats.bot);
+
+      const [gameAccountPda] = await findGameAccountPda(creator, wager, program.programId);
+      await program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: creator.publicKey,
+          payer: creator.publicKey,
+          creatorStats: playerStatsPda(creator.publicKey),
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [creator],
+      });
+      await program.rpc.setHumansOnly(true, {
+        accounts: { gameAccount: gameAccountPda, creator: creator.publicKey },
+        signers: [creator],
+      });
+
+      try {
+        await program.rpc.joinGame({
+          accounts: {
+            gameAccount: gameAccountPda,
+            creatorStats: playerStatsPda(creator.publicKey),
+            config: configPda,
+            lobby: lobbyPda(gameAccountPda),
+            joiner: bot.publicKey,
+            joinerStats: playerStatsPda(bot.publicKey),
+            instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+            eventAuthority: eventAuthorityPda,
+            program: program.programId,
+          },
+          signers: [bot],
+        });
+        assert.fail("Bots can't join humans-only games");
+      } catch (err: any) {
+        assert.include(err.message, "BotsNotAllowed");
+      }
+
+      await program.rpc.unregisterBot({
+        accounts: {
+          config: configPda,
+          botStats: playerStatsPda(bot.publicKey),
+          admin: provider.wallet.publicKey,
+        },
+      });
+      const unregistered = await program.account.playerStats.fetch(playerStatsPda(bot.publicKey));
+      assert.isFalse(unregistered.bot);
+    });
+  });
+
+  describe("Responsible Gaming", () => {
+    const fundedPlayer = async (): Promise<Keypair> => {
+      const player = Keypair.generate();
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(player.publicKey, anchor.web3.LAMPORTS_PER_SOL),
+        "confirmed"
+      );
+      return player;
+    };
+    const treasuryPda = PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury")],
+      program.programId
+    )[0];
+    const createGameAs = async (
+      player: Keypair,
+      wager: number,
+      optionalAccounts: { creatorStake?: PublicKey; treasury?: PublicKey } = {}
+    ) => {
+      const [gameAccountPda] = await findGameAccountPda(player, wager, program.programId);
+      return program.rpc.createGame(new BN(wager), {
+        accounts: {
+          gameAccount: gameAccountPda,
+          config: configPda,
+          lobby: lobbyPda(gameAccountPda),
+          creator: player.publicKey,
+          payer: player.publicKey,
+          creatorStats: playerStatsPda(player.publicKey),
+          ...optionalAccounts,
+          systemProgram: SystemProgram.programId,
+          eventAuthority: eventAuthorityPda,
+          program: program.programId,
+        },
+        signers: [player],
+      });
+    };
+
+    it("Enforces self-imposed limits and self-exclusion", async () => {
+      const player = await fundedPlayer();
+      const createGame = (wager: number) => createGameAs(player, wager);
+      const limitAccounts = {
+        playerStats: playerStatsPda(player.publicKey),
+        player: player.publicKey,
+      };
+
+      // The first game opens the player's stats
+      await createGame(34_000_000);
+      await program.rpc.setPlayerLimits(new BN(35_000_000), new BN(0), new BN(3600), {
+        accounts: limitAccounts,
+        signers: [player],
+      });
+      try {
+        await createGame(36_000_000);
+        assert.fail("The wager is over the player's limit");
+      } catch (err: any) {
+        assert.include(err.message, "OverPlayerLimit");
+      }
+      try {
+        await program.rpc.setPlayerLimits(new BN(0), new BN(0), new BN(0), {
+          accounts: limitAccounts,
+          signers: [player],
+        });
+        assert.fail("Limits can't be lifted while locked");
+      } catch (err: any) {
+        assert.include(err.message, "LimitsLocked");
+      }
+
+      const until = Math.floor(Date.now() / 1000) + 3600;
+      await program.rpc.selfExclude(new BN(until), {
+        accounts: limitAccounts,
+        signers: [player],
+      });
+      await program.rpc.selfExclude(new BN(0), {
+        accounts: limitAccounts,
+        signers: [player],
+      });
+      try {
+        await createGame(34_500_000);
+        assert.fail("Self-excluded players can't create games");
+      } catch (err: any) {
+        assert.include(err.message, "SelfExcluded");
+      }
+
+      const stats = await program.account.playerStats.fetch(playerStatsPda(player.publicKey));
+      assert.equal(stats.maxWagerLimit.toNumber(), 35_000_000);
+      assert.equal(stats.excludedUntil.toNumber(), until, "Exclusions can't be shortened");
+    });
+
+    it("Caps the volume a wallet can wager in a day", async () => {
+      const player = await fundedPlayer();
+      const setCap = (cap: number) =>
+        program.rpc.setDailyWagerCap(new BN(cap), {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+      await setCap(70_000_000);
+
+      await createGameAs(player, 34_000_000);
+      try {
+        await createGameAs(player, 36_000_000);
+        assert.fail("The second game would take the player over the cap");
+      } catch (err: any) {
+        assert.include(err.message, "DailyWagerCapReached");
+      }
+      await setCap(0);
+
+      const stats = await program.account.playerStats.fetch(playerStatsPda(player.publicKey));
+      assert.equal(stats.wagerVolume.toNumber(), 34_000_000);
+    });
+
+    it("Throttles game creation with a cooldown", async () => {
+      const player = await fundedPlayer();
+      const setCooldown = (cooldown: number) =>
+        program.rpc.setCreationCooldown(new BN(cooldown), {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+      await setCooldown(3600);
+
+      await createGameAs(player, 34_000_000);
+      try {
+        await createGameAs(player, 34_500_000);
+        assert.fail("The second game is inside the cooldown");
+      } catch (err: any) {
+        assert.include(err.message, "CreationCooldown");
+      }
+      await setCooldown(0);
+
+      const stats = await program.account.playerStats.fetch(playerStatsPda(player.publicKey));
+      assert.isAbove(stats.lastActionTs.toNumber(), 0);
+    });
+
+    it("Requires lobby creators to hold the operator's stake token", async () => {
+      const player = await fundedPlayer();
+      const mint = await createMint(provider.connection, creator, creator.publicKey, null, 0);
+      const setStake = (stakeMint: PublicKey, minAmount: number) =>
+        program.rpc.setCreationStake(stakeMint, new BN(minAmount), {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+      await setStake(mint, 10);
+
+      const stake = await createAccount(provider.connection, player, mint, player.publicKey);
+      await mintTo(provider.connection, creator, mint, stake, creator, 5);
+      for (const optionalAccounts of [{}, { creatorStake: stake }]) {
+        try {
+          await createGameAs(player, 34_000_000, optionalAccounts);
+          assert.fail("The player doesn't hold enough of the stake token");
+        } catch (err: any) {
+          assert.include(err.message, "CreationStakeRequired");
+        }
+      }
+      await mintTo(provider.connection, creator, mint, stake, creator, 10);
+      await createGameAs(player, 34_000_000, { creatorStake: stake });
+      await setStake(PublicKey.default, 0);
+    });
+
+    it("Charges the creation fee into the treasury", async () => {
+      const player = await fundedPlayer();
+      const fee = 5_000;
+      const setFee = (creationFee: number) =>
+        program.rpc.setCreationFee(new BN(creationFee), {
+          accounts: { config: configPda, admin: provider.wallet.publicKey },
+        });
+      await setFee(fee);
+
+      try {
+        await createGameAs(player, 34_000_000);
+        assert.fail("The fee needs the treasury");
+      } catch (err: any) {
+        assert.include(err.message, "TreasuryMissing");
+      }
+      const treasuryBefore = await provider.connection.getBalance(treasuryPda);
+      await createGameAs(player, 34_000_000, { treasury: treasuryPda });
+      await setFee(0);
+
+      const treasuryAfter = await provider.connection.getBalance(treasuryPda);
+      assert.equal(treasuryAfter - treasuryBefore, fee);
+    });
+  });
+
+  describe("Profiles", () => {
+    const profilePda = (player: PublicKey): PublicKey =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("profile"), player.toBuffer()],
+        program.programId
+      )[0];
+    const usernamePda = (username: string): PublicKey =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("username"), Buffer.from(username)],
+        program.programId
+      )[0];
+    const registerAccounts = (player: Keypair, username: string) => ({
+      profile: profilePda(player.publicKey),
+      usernameRecord: usernamePda(username),
+      player: player.publicKey,
+      systemProgram: SystemProgram.programId,
+    });
+
+    it("Registers unique usernames with a reverse lookup", async () => {
+      const username = "rock_solid";
+      try {
+        await program.rpc.registerUsername("Rock", {
+          accounts: registerAccounts(creator, "Rock"),
+          signers: [creator],
+        });
+        assert.fail("Uppercase names should be rejected");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidUsername");
+      }
+
+      await program.rpc.registerUsername(username, {
+        accounts: registerAccounts(creator, username),
+        signers: [creator],
+      });
+      const profile = await program.account.playerProfile.fetch(profilePda(creator.publicKey));
+      assert.equal(profile.username, username);
+      const record = await program.account.usernameRecord.fetch(usernamePda(username));
+      assert.isTrue(record.player.equals(creator.publicKey), "The name resolves to its wallet");
+
+      try {
+        await program.rpc.registerUsername(username, {
+          accounts: registerAccounts(joiner, username),
+          signers: [joiner],
+        });
+        assert.fail("A taken name can't be registered again");
+      } catch (err: any) {
+        assert.ok(err);
+      }
+
+      await program.rpc.releaseUsername({
+        accounts: {
+          profile: profilePda(creator.publicKey),
+          usernameRecord: usernamePda(username),
+          player: creator.publicKey,
+        },
+        signers: [creator],
+      });
+      await program.rpc.registerUsername(username, {
+        accounts: registerAccounts(joiner, username),
+        signers: [joiner],
+      });
+      const joinerProfile = await program.account.playerProfile.fetch(profilePda(joiner.publicKey));
+      assert.equal(joinerProfile.username, username, "A released name is free again");
+    });
+
+    it("Only takes NFTs with token metadata as avatars", async () => {
+      const mint = await createMint(provider.connection, creator, creator.publicKey, null, 0);
+      const creatorToken = await createAccount(provider.connection, creator, mint, creator.publicKey);
+      await mintTo(provider.connection, creator, mint, creatorToken, creator, 1);
+      const metadataProgram = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+      const [metadataPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("metadata"), metadataProgram.toBuffer(), mint.toBuffer()],
+        metadataProgram
+      );
+
+      try {
+        await program.rpc.setAvatar({
+          accounts: {
+            profile: profilePda(creator.publicKey),
+            mint,
+            playerToken: creatorToken,
+            metadata: metadataPda,
+            player: creator.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+          signers: [creator],
+        });
+        assert.fail("A mint without a metadata account can't be an avatar");
+      } catch (err: any) {
+        assert.include(err.message, "InvalidMetadata");
+      }
+      const profile = await program.account.playerProfile.fetch(profilePda(creator.publicKey));
+      assert.isNull(profile.avatar);
+    });
+  });
+
+  describe("Account Versioning", () => {
+    it("Leaves current accounts on their version", async () => {
+      const [gameAccountPda] = await findGameAccountPda(creator, 13_000_000, program.programId);
+      for (const [method, account] of [
+        ["migrateGame", gameAccountPda],
+        ["migrateConfig", configPda],
+        ["migratePlayerStats", playerStatsPda(creator.publicKey)],
+      ] as const) {
+        await program.rpc[method]({
+          accounts: {
+            account,
+            payer: provider.wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+        });
+      }
+
+      const gameAccountData = await program.account.gameState.fetch(gameAccountPda);
+      const configData = await program.account.config.fetch(configPda);
+      const statsData = await program.account.playerStats.fetch(playerStatsPda(creator.publicKey));
+      assert.equal(gameAccountData.version, 1);
+      assert.equal(configData.version, 1);
+      assert.equal(statsData.version, 1);
+    });
+
+    it("Rejects migrating an account of another type", async () => {
+      try {
+        await program.rpc.migrateGame({
+          accounts: {
+            account: configPda,
+            payer: provider.wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+          },
+        });
+        assert.fail("Migrating the config as a game should fail");
+      } catch (err) {
+        assert.include(err.toString(), "InvalidMigrationAccount");
+      }
+    });
   });
-});
\ No newline at end of file
+});